@@ -0,0 +1,86 @@
+use std::{
+    env,
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+};
+
+use anyhow::Result;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use simple_redis::{network, Backend};
+use tokio::{net::TcpListener, runtime::Runtime};
+
+// set to a running redis, e.g. 127.0.0.1:6379, to run the same pipelines against it
+const REDIS_ADDR_ENV: &str = "PIPELINE_BENCH_REDIS_ADDR";
+
+// serve a fresh backend on a free local port, like main does
+fn start_server(rt: &Runtime) -> Result<SocketAddr> {
+    let listener = rt.block_on(TcpListener::bind("127.0.0.1:0"))?;
+    let addr = listener.local_addr()?;
+    let backend = Backend::default();
+    rt.spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            stream.set_nodelay(true).unwrap();
+            tokio::spawn(network::stream_handler(stream, backend.clone()));
+        }
+    });
+    Ok(addr)
+}
+
+// `n` SETs followed by `n` GETs of the same keys, in a single write
+fn set_get_pipeline(n: usize) -> (Vec<u8>, usize) {
+    let mut request = Vec::new();
+    let mut reply_len = 0;
+    for i in 0..n {
+        let key = format!("key:{:06}", i);
+        request.extend_from_slice(
+            format!(
+                "*3\r\n$3\r\nSET\r\n${}\r\n{}\r\n$3\r\nxxx\r\n",
+                key.len(),
+                key
+            )
+            .as_bytes(),
+        );
+        reply_len += b"+OK\r\n".len();
+    }
+    for i in 0..n {
+        let key = format!("key:{:06}", i);
+        request.extend_from_slice(
+            format!("*2\r\n$3\r\nGET\r\n${}\r\n{}\r\n", key.len(), key).as_bytes(),
+        );
+        reply_len += b"$3\r\nxxx\r\n".len();
+    }
+    (request, reply_len)
+}
+
+// write the whole pipeline, then read until every reply has arrived
+fn run_pipeline(stream: &mut TcpStream, request: &[u8], reply: &mut [u8]) -> Result<()> {
+    stream.write_all(request)?;
+    stream.read_exact(reply)?;
+    Ok(())
+}
+
+fn bench_pipeline(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut targets = vec![("simple-redis", start_server(&rt).unwrap())];
+    if let Ok(addr) = env::var(REDIS_ADDR_ENV) {
+        targets.push(("redis", addr.parse().unwrap()));
+    }
+
+    let mut group = c.benchmark_group("pipeline");
+    for n in [1, 16, 256, 4096] {
+        let (request, reply_len) = set_get_pipeline(n);
+        let mut reply = vec![0; reply_len];
+        group.throughput(Throughput::Elements(2 * n as u64));
+        for (name, addr) in &targets {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.set_nodelay(true).unwrap();
+            group.bench_with_input(BenchmarkId::new(*name, n), &request, |b, request| {
+                b.iter(|| run_pipeline(&mut stream, request, &mut reply).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
@@ -1,10 +1,62 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
 use anyhow::Result;
-use bytes::BytesMut;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use simple_redis::{parse_frame, parse_frame_length, RespFrame};
+use bytes::{Bytes, BytesMut};
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use simple_redis::{
+    parse_frame, parse_frame_length, BulkString, RespArray, RespEncode, RespError, RespFrame,
+    RespFrameDecoder,
+};
+
+// counts heap allocations, so the encode benches can report allocations per reply
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
 
 const DATA: &str = "+OK\r\n-ERR\r\n:1000\r\n$6\r\nfoobar\r\n$-1\r\n*2\r\n+hello\r\n$5\r\nworld\r\n+foo\r\n$3\r\nbar\r\n%2\r\n+foo\r\n,-123456.789\r\n+hello\r\n$5\r\nworld\r\n*3\r\n$3\r\nset\r\n$5\r\nhello\r\n$5\r\nworld\r\n%2\r\n+hello\r\n$5\r\nworld\r\n+foo\r\n$3\r\nbar\r\n";
 
+// a SET carrying a 4MB value, decoding it must not copy the value out of the read buffer
+const LARGE_VALUE_LEN: usize = 4 * 1024 * 1024;
+
+fn large_set_command() -> BytesMut {
+    let mut buf = BytesMut::with_capacity(LARGE_VALUE_LEN + 64);
+    buf.extend_from_slice(b"*3\r\n$3\r\nset\r\n$5\r\nhello\r\n");
+    buf.extend_from_slice(format!("${}\r\n", LARGE_VALUE_LEN).as_bytes());
+    buf.extend_from_slice(&vec![b'x'; LARGE_VALUE_LEN]);
+    buf.extend_from_slice(b"\r\n");
+    buf
+}
+
 fn v1_decode(buf: &mut BytesMut) -> Result<Vec<RespFrame>> {
     use simple_redis::RespDecode;
     let mut frames = Vec::new();
@@ -25,12 +77,13 @@ fn v2_decode(buf: &mut BytesMut) -> Result<Vec<RespFrame>> {
     Ok(frames)
 }
 
-fn v2_decode_no_buf_clone(buf: &mut &[u8]) -> Result<Vec<RespFrame>> {
+fn v2_decode_no_buf_clone(src: &Bytes) -> Result<Vec<RespFrame>> {
+    let buf = &mut src.as_ref();
     let mut frames = Vec::new();
     while !buf.is_empty() {
         let _len = parse_frame_length(buf)?;
 
-        let frame = parse_frame(buf).unwrap();
+        let frame = parse_frame(buf, src).unwrap();
         frames.push(frame);
     }
     Ok(frames)
@@ -54,17 +107,72 @@ fn v1_decode_parse_length(buf: &mut &[u8]) -> Result<()> {
     Ok(())
 }
 
-fn v2_decode_parse_frame(buf: &mut &[u8]) -> Result<Vec<RespFrame>> {
+fn v2_decode_parse_frame(src: &Bytes) -> Result<Vec<RespFrame>> {
+    let buf = &mut src.as_ref();
     let mut frames = Vec::new();
     while !buf.is_empty() {
-        let frame = parse_frame(buf).unwrap();
+        let frame = parse_frame(buf, src).unwrap();
         frames.push(frame);
     }
     Ok(frames)
 }
 
+// a pipelined array of `n` elements, received in TCP segments
+const SEGMENT_LEN: usize = 1460;
+
+fn pipelined_array(n: usize) -> Vec<u8> {
+    let frames = (0..n)
+        .map(|i| BulkString::from(format!("value:{}", i)).into())
+        .collect::<Vec<RespFrame>>();
+    RespFrame::from(RespArray::new(frames)).encode()
+}
+
+// the two pass decoder starts over on every segment
+fn v2_decode_fragmented(data: &[u8]) -> Result<Option<RespFrame>> {
+    use simple_redis::RespDecodeV2;
+    let mut buf = BytesMut::new();
+    for segment in data.chunks(SEGMENT_LEN) {
+        buf.extend_from_slice(segment);
+        match RespFrame::decode(&mut buf) {
+            Ok(frame) => return Ok(Some(frame)),
+            Err(RespError::NotComplete) => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(None)
+}
+
+// the incremental decoder resumes where the last segment ended
+fn incremental_decode_fragmented(data: &[u8]) -> Result<Option<RespFrame>> {
+    let mut decoder = RespFrameDecoder::default();
+    let mut buf = BytesMut::new();
+    for segment in data.chunks(SEGMENT_LEN) {
+        buf.extend_from_slice(segment);
+        if let Some(frame) = decoder.decode(&mut buf)? {
+            return Ok(Some(frame));
+        }
+    }
+    Ok(None)
+}
+
+// the old codec path, every frame is encoded into its own vector then copied
+fn encode_vec(frames: Vec<RespFrame>, dst: &mut BytesMut) {
+    for frame in frames {
+        dst.extend_from_slice(&frame.encode());
+    }
+}
+
+// the codec path, frames are written straight into the reused output buffer
+fn encode_to(frames: Vec<RespFrame>, dst: &mut BytesMut) {
+    for frame in frames {
+        dst.reserve(frame.encoded_len());
+        frame.encode_to(dst);
+    }
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let buf = BytesMut::from(DATA);
+    let data = Bytes::from_static(DATA.as_bytes());
 
     c.bench_function("v1_decode", |b| {
         b.iter(|| v1_decode(black_box(&mut buf.clone())))
@@ -75,7 +183,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 
     c.bench_function("v2_decode_no_buf_clone", |b| {
-        b.iter(|| v2_decode_no_buf_clone(black_box(&mut DATA.as_bytes())))
+        b.iter(|| v2_decode_no_buf_clone(black_box(&data)))
     });
 
     c.bench_function("v1_decode_parse_length", |b| {
@@ -87,8 +195,84 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 
     c.bench_function("v2_decode_parse_frame", |b| {
-        b.iter(|| v2_decode_parse_frame(black_box(&mut DATA.as_bytes())))
+        b.iter(|| v2_decode_parse_frame(black_box(&data)))
+    });
+
+    let replies = v1_decode(&mut buf.clone()).unwrap();
+    let mut dst = BytesMut::with_capacity(4096);
+    let per_reply = |n: usize| n as f64 / replies.len() as f64;
+    let frames = replies.clone();
+    let vec_allocations = allocations(|| encode_vec(frames, &mut dst));
+    dst.clear();
+    let frames = replies.clone();
+    let encode_to_allocations = allocations(|| encode_to(frames, &mut dst));
+    println!(
+        "allocations per reply: encode {:.2}, encode_to {:.2}",
+        per_reply(vec_allocations),
+        per_reply(encode_to_allocations)
+    );
+    assert_eq!(encode_to_allocations, 0);
+
+    c.bench_function("encode_vec", |b| {
+        b.iter_batched(
+            || replies.clone(),
+            |frames| {
+                dst.clear();
+                encode_vec(black_box(frames), &mut dst)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    c.bench_function("encode_to", |b| {
+        b.iter_batched(
+            || replies.clone(),
+            |frames| {
+                dst.clear();
+                encode_to(black_box(frames), &mut dst)
+            },
+            BatchSize::SmallInput,
+        )
     });
+
+    // the buffer clone happens in the untimed setup, so only the decoding is measured
+    let large = large_set_command();
+    c.bench_function("v1_decode_large_value", |b| {
+        b.iter_batched(
+            || large.clone(),
+            |mut buf| v1_decode(black_box(&mut buf)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    c.bench_function("v2_decode_large_value", |b| {
+        b.iter_batched(
+            || large.clone(),
+            |mut buf| v2_decode(black_box(&mut buf)),
+            BatchSize::LargeInput,
+        )
+    });
+
+    // what a single copy of the value costs, the decoders above should stay well under it
+    c.bench_function("large_value_copy", |b| {
+        b.iter(|| black_box(large[large.len() - LARGE_VALUE_LEN - 2..].to_vec()))
+    });
+
+    // the time per byte of the incremental decoder stays flat as the array grows, the two pass
+    // decoder's grows with it
+    let mut group = c.benchmark_group("fragmented");
+    group.sample_size(10);
+    for n in [1_000, 4_000, 16_000] {
+        let data = pipelined_array(n);
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::new("v2_decode", n), &data, |b, data| {
+            b.iter(|| v2_decode_fragmented(black_box(data)))
+        });
+        group.bench_with_input(BenchmarkId::new("incremental", n), &data, |b, data| {
+            b.iter(|| incremental_decode_fragmented(black_box(data)))
+        });
+    }
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,29 @@
+#![no_main]
+
+// feed arbitrary bytes to every decoder, they must return an error instead of panicking,
+// allocating without bound or overflowing the stack. the frames they accept are turned into
+// commands, which must not panic either. run with `cargo fuzz run decode`
+use bytes::BytesMut;
+use libfuzzer_sys::fuzz_target;
+use simple_redis::{Command, RespDecode, RespDecodeV2, RespEncode, RespFrame, RespFrameDecoder};
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BytesMut::from(data);
+    let v1 = <RespFrame as RespDecode>::decode(&mut buf);
+    let mut buf = BytesMut::from(data);
+    let v2 = <RespFrame as RespDecodeV2>::decode(&mut buf);
+
+    // a frame both decoders accept must survive a round trip
+    if let (Ok(v1), Ok(v2)) = (&v1, &v2) {
+        if v1 == v2 {
+            let mut buf = BytesMut::from(&v1.clone().encode()[..]);
+            assert_eq!(<RespFrame as RespDecode>::decode(&mut buf).as_ref(), Ok(v1));
+        }
+    }
+
+    let mut decoder = RespFrameDecoder::default();
+    let mut buf = BytesMut::from(data);
+    while let Ok(Some(frame)) = decoder.decode(&mut buf) {
+        let _ = Command::try_from(frame);
+    }
+});
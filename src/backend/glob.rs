@@ -0,0 +1,115 @@
+// redis style glob matching: `*`, `?`, `[abc]`, `[^a-z]` and `\` escapes
+pub(crate) fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let s = s.as_bytes();
+    match_from(pattern, s)
+}
+
+// iterative, a `*` only ever resumes from the last one seen, so matching stays linear in the
+// pattern times the string instead of exponential in the number of stars
+fn match_from(p: &[u8], s: &[u8]) -> bool {
+    let (mut pi, mut si) = (0, 0);
+    // the pattern after the last `*` and the string position it was last tried at
+    let mut star: Option<(usize, usize)> = None;
+    loop {
+        if pi < p.len() {
+            if p[pi] == b'*' {
+                pi += 1;
+                star = Some((pi, si));
+                continue;
+            }
+            if let Some(len) = s.get(si).and_then(|&ch| match_one(&p[pi..], ch)) {
+                pi += len;
+                si += 1;
+                continue;
+            }
+        } else if si == s.len() {
+            return true;
+        }
+        // let the last `*` swallow one more character
+        match star {
+            Some((sp, ss)) if ss < s.len() => {
+                star = Some((sp, ss + 1));
+                pi = sp;
+                si = ss + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+// match the pattern token at the start of `p`, returning the pattern length consumed
+fn match_one(p: &[u8], ch: u8) -> Option<usize> {
+    match p[0] {
+        b'?' => Some(1),
+        b'[' => {
+            let (matched, len) = match_class(&p[1..], ch);
+            matched.then_some(1 + len)
+        }
+        b'\\' if p.len() > 1 => (p[1] == ch).then_some(2),
+        c => (c == ch).then_some(1),
+    }
+}
+
+// match a character class, returning whether it matched and the pattern length consumed
+fn match_class(p: &[u8], ch: u8) -> (bool, usize) {
+    let mut i = 0;
+    let negate = p.first() == Some(&b'^');
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    while i < p.len() && p[i] != b']' {
+        if p[i] == b'\\' && i + 1 < p.len() {
+            matched |= p[i + 1] == ch;
+            i += 2;
+        } else if i + 2 < p.len() && p[i + 1] == b'-' && p[i + 2] != b']' {
+            let (lo, hi) = (p[i].min(p[i + 2]), p[i].max(p[i + 2]));
+            matched |= lo <= ch && ch <= hi;
+            i += 3;
+        } else {
+            matched |= p[i] == ch;
+            i += 1;
+        }
+    }
+    // skip the closing bracket
+    if i < p.len() {
+        i += 1;
+    }
+    (matched != negate, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("news.*", "news.tech"));
+        assert!(!glob_match("news.*", "sport.tech"));
+        assert!(glob_match("h?llo", "hello"));
+        assert!(glob_match("h[ae]llo", "hallo"));
+        assert!(!glob_match("h[^e]llo", "hello"));
+        assert!(glob_match("h[a-c]llo", "hbllo"));
+        assert!(glob_match("a\\*b", "a*b"));
+        assert!(!glob_match("a\\*b", "axb"));
+        assert!(glob_match("*max*", "maxmemory"));
+        assert!(glob_match("*a", "banana"));
+        assert!(!glob_match("*a", "bananas"));
+        assert!(!glob_match("a*?s", "bananas"));
+        assert!(glob_match("b*n?s", "bananas"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("?", ""));
+        assert!(glob_match("", ""));
+    }
+
+    #[test]
+    fn test_glob_match_many_stars() {
+        // backtracking into every star would take exponential time here
+        let pattern = format!("{}b", "a*".repeat(30));
+        let s = "a".repeat(100);
+        assert!(!glob_match(&pattern, &s));
+        assert!(glob_match(&pattern, &format!("{}b", s)));
+    }
+}
@@ -0,0 +1,114 @@
+use std::process;
+
+use super::Backend;
+use crate::network::REDIS_VERSION;
+
+// the sections of INFO, in the order they are printed
+const SECTIONS: &[&str] = &["server", "clients", "stats"];
+
+impl Backend {
+    // INFO [section ...], unknown sections are left out like in redis
+    pub fn info(&self, sections: &[String]) -> String {
+        let all = sections.is_empty()
+            || sections.iter().any(|s| {
+                ["default", "all", "everything"]
+                    .iter()
+                    .any(|all| s.eq_ignore_ascii_case(all))
+            });
+        let mut lines = Vec::new();
+        for section in SECTIONS {
+            if !all && !sections.iter().any(|s| s.eq_ignore_ascii_case(section)) {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            let (title, fields) = match *section {
+                "server" => ("Server", self.server_info()),
+                "clients" => ("Clients", self.clients_info()),
+                _ => ("Stats", self.stats_info()),
+            };
+            lines.push(format!("# {}", title));
+            lines.extend(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| format!("{}:{}", name, value)),
+            );
+        }
+        lines.into_iter().map(|line| line + "\r\n").collect()
+    }
+
+    fn server_info(&self) -> Vec<(&'static str, String)> {
+        let config = self.config();
+        vec![
+            ("redis_version", REDIS_VERSION.to_string()),
+            ("redis_mode", "standalone".to_string()),
+            ("process_id", process::id().to_string()),
+            ("tcp_port", config.port.to_string()),
+            (
+                "config_file",
+                config
+                    .file
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            ),
+        ]
+    }
+
+    fn clients_info(&self) -> Vec<(&'static str, String)> {
+        let config = self.config();
+        vec![
+            (
+                "connected_clients",
+                self.stats.connected_clients().to_string(),
+            ),
+            ("maxclients", config.maxclients.to_string()),
+            // no command blocks yet
+            ("blocked_clients", "0".to_string()),
+            ("tracking_clients", self.tracking_clients().to_string()),
+            ("timeout", config.timeout.to_string()),
+            ("tcp_keepalive", config.tcp_keepalive.to_string()),
+            ("tcp_backlog", config.tcp_backlog.to_string()),
+        ]
+    }
+
+    fn stats_info(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (
+                "total_connections_received",
+                self.stats.total_connections_received().to_string(),
+            ),
+            (
+                "total_commands_processed",
+                self.stats.total_commands_processed().to_string(),
+            ),
+            (
+                "rejected_connections",
+                self.stats.rejected_connections().to_string(),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_sections() {
+        let backend = Backend::new();
+        let _slot = backend.try_connect().unwrap();
+        let clients = backend.info(&["CLIENTS".to_string()]);
+        assert_eq!(
+            clients,
+            "# Clients\r\nconnected_clients:1\r\nmaxclients:10000\r\nblocked_clients:0\r\ntracking_clients:0\r\ntimeout:0\r\ntcp_keepalive:300\r\ntcp_backlog:511\r\n"
+        );
+
+        let info = backend.info(&[]);
+        assert!(info.starts_with("# Server\r\nredis_version:7.2.0\r\n"));
+        assert!(info.contains("\r\n\r\n# Clients\r\n"));
+        assert!(info.ends_with("rejected_connections:0\r\n"));
+        assert_eq!(backend.info(&["all".to_string()]), info);
+        assert_eq!(backend.info(&["keyspace".to_string()]), "");
+    }
+}
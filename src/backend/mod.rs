@@ -1,17 +1,69 @@
-use std::{ops::Deref, sync::Arc};
+mod glob;
+mod info;
+mod notify;
+mod pubsub;
+mod search;
+mod shutdown;
+mod slot;
+mod stats;
+mod tls;
+mod tracking;
+mod ts;
+mod txn;
 
-use dashmap::{DashMap, DashSet};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{atomic::AtomicU32, Arc, RwLock},
+};
 
-use crate::RespFrame;
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
+use tokio::sync::watch;
+use tokio_rustls::rustls::ServerConfig;
+
+use crate::{Config, RespFrame, RespLimits};
+
+pub(crate) use glob::glob_match;
+
+pub use notify::*;
+pub use pubsub::*;
+pub use search::*;
+pub use shutdown::*;
+pub use slot::*;
+pub use stats::*;
+pub use tracking::*;
+pub use ts::*;
 
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
 #[derive(Debug)]
 pub struct BackendInner {
-    pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub(crate) set: DashMap<String, DashSet<String>>,
+    // keys, hash fields and set members are binary safe, as in redis
+    pub(crate) map: DashMap<Vec<u8>, RespFrame>,
+    pub(crate) hmap: DashMap<Vec<u8>, DashMap<Vec<u8>, RespFrame>>,
+    pub(crate) set: DashMap<Vec<u8>, DashSet<Vec<u8>>>,
+    pub(crate) ts: DashMap<String, TimeSeries>,
+    pub(crate) indexes: DashMap<String, SearchIndex>,
+    // watched key -> (number of watchers, modification count)
+    pub(crate) watches: DashMap<Vec<u8>, (usize, u64)>,
+    // commands run under the shared lock, EXEC takes it exclusively to run atomically
+    pub(crate) txn_lock: RwLock<()>,
+    pub(crate) pubsub: PubSub,
+    // notify-keyspace-events classes, zero when notifications are disabled
+    pub(crate) notify_flags: AtomicU32,
+    pub(crate) tracking: Tracking,
+    // proto-max-bulk-len and the other limits on what clients send, read by every connection
+    pub(crate) resp_limits: RwLock<RespLimits>,
+    // requirepass, connections must AUTH with it before running commands
+    pub(crate) requirepass: RwLock<Option<String>>,
+    // the running configuration, CONFIG SET changes it
+    pub(crate) config: RwLock<Config>,
+    pub(crate) stats: Stats,
+    // the exit code once SHUTDOWN or a signal stopped the server
+    pub(crate) shutdown: watch::Sender<Option<i32>>,
+    // certificates for tls connections, replaced when CONFIG SET reloads them
+    pub(crate) tls: RwLock<Option<Arc<ServerConfig>>>,
 }
 
 impl Deref for Backend {
@@ -34,6 +86,19 @@ impl Default for BackendInner {
             map: DashMap::new(),
             hmap: DashMap::new(),
             set: DashMap::new(),
+            ts: DashMap::new(),
+            indexes: DashMap::new(),
+            watches: DashMap::new(),
+            txn_lock: RwLock::new(()),
+            pubsub: PubSub::default(),
+            notify_flags: AtomicU32::new(0),
+            tracking: Tracking::default(),
+            resp_limits: RwLock::new(RespLimits::default()),
+            requirepass: RwLock::new(None),
+            config: RwLock::new(Config::default()),
+            stats: Stats::default(),
+            shutdown: watch::Sender::new(None),
+            tls: RwLock::new(None),
         }
     }
 }
@@ -43,30 +108,109 @@ impl Backend {
         Self::default()
     }
 
-    pub fn get(&self, key: &str) -> Option<RespFrame> {
-        self.map.get(key).map(|v| v.value().clone())
+    pub fn set_resp_limits(&self, limits: RespLimits) {
+        *self.resp_limits.write().unwrap_or_else(|e| e.into_inner()) = limits;
     }
 
-    pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+    pub fn resp_limits(&self) -> RespLimits {
+        *self.resp_limits.read().unwrap_or_else(|e| e.into_inner())
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|inner| inner.get(field).map(|v| v.value().clone()))
+    pub fn set_requirepass(&self, password: Option<String>) {
+        *self.requirepass.write().unwrap_or_else(|e| e.into_inner()) = password;
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    pub fn requirepass(&self) -> Option<String> {
+        self.requirepass
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<RespFrame> {
+        self.track_read(key);
+        let ret = self.map.get(key).map(|v| v.value().clone());
+        if ret.is_none() {
+            self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+        }
+        ret
+    }
+
+    pub fn set(&self, key: Vec<u8>, value: RespFrame) {
+        let old = self.map.insert(key.clone(), value);
+        self.touch(&key);
+        if old.is_none() {
+            self.notify_keyspace_event(NOTIFY_NEW, "new", &key);
+        }
+        self.notify_keyspace_event(NOTIFY_STRING, "set", &key);
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let inner = self.hmap.entry(key).or_default();
+    pub fn hget(&self, key: &[u8], field: &[u8]) -> Option<RespFrame> {
+        self.track_read(key);
+        let Some(inner) = self.hmap.get(key) else {
+            self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+            return None;
+        };
+        inner.get(field).map(|v| v.value().clone())
+    }
+
+    pub fn hgetall(&self, key: &[u8]) -> Option<DashMap<Vec<u8>, RespFrame>> {
+        self.track_read(key);
+        let ret = self.hmap.get(key).map(|v| v.clone());
+        if ret.is_none() {
+            self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+        }
+        ret
+    }
+
+    pub fn hset(&self, key: Vec<u8>, field: Vec<u8>, value: RespFrame) {
+        let (inner, created) = match self.hmap.entry(key.clone()) {
+            Entry::Occupied(entry) => (entry.into_ref(), false),
+            Entry::Vacant(entry) => (entry.insert(DashMap::new()), true),
+        };
         inner.insert(field, value);
+        drop(inner);
+        self.touch(&key);
+        self.index_hash(&key);
+        if created {
+            self.notify_keyspace_event(NOTIFY_NEW, "new", &key);
+        }
+        self.notify_keyspace_event(NOTIFY_HASH, "hset", &key);
+    }
+
+    // keep every index whose prefix matches the key in sync with the hash content,
+    // only textual keys can be indexed
+    fn index_hash(&self, key: &[u8]) {
+        if self.indexes.is_empty() {
+            return;
+        }
+        let Ok(key) = std::str::from_utf8(key) else {
+            return;
+        };
+        let values = self.hash_strings(key);
+        for mut index in self.indexes.iter_mut() {
+            if index.matches_key(key) {
+                index.index(key, &values);
+            }
+        }
+    }
+
+    fn hash_strings(&self, key: &str) -> HashMap<String, String> {
+        self.hmap
+            .get(key.as_bytes())
+            .map(|hmap| {
+                hmap.iter()
+                    .filter_map(|v| {
+                        let field = String::from_utf8(v.key().clone()).ok()?;
+                        frame_to_string(v.value()).map(|s| (field, s))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
     }
 
-    pub fn hmget(&self, key: &str, fields: &[String]) -> Vec<Option<RespFrame>> {
+    pub fn hmget(&self, key: &[u8], fields: &[Vec<u8>]) -> Vec<Option<RespFrame>> {
+        self.track_read(key);
         let hmap = self.hmap.get(key);
 
         match hmap {
@@ -79,26 +223,324 @@ impl Backend {
 
                 data
             }
-            None => vec![None; fields.len()],
+            None => {
+                self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+                vec![None; fields.len()]
+            }
         }
     }
 
-    pub fn sadd(&self, key: String, members: Vec<String>) -> usize {
-        let set = self.set.entry(key).or_default();
+    pub fn sadd(&self, key: Vec<u8>, members: Vec<Vec<u8>>) -> usize {
+        let (set, created) = match self.set.entry(key.clone()) {
+            Entry::Occupied(entry) => (entry.into_ref(), false),
+            Entry::Vacant(entry) => (entry.insert(DashSet::new()), true),
+        };
         let mut added = 0;
         for member in members {
             if set.insert(member) {
                 added += 1;
             }
         }
+        drop(set);
+        if created {
+            self.notify_keyspace_event(NOTIFY_NEW, "new", &key);
+        }
+        if added > 0 {
+            self.touch(&key);
+            self.notify_keyspace_event(NOTIFY_SET, "sadd", &key);
+        }
         added
     }
 
-    pub fn smembers(&self, key: &str) -> Option<DashSet<String>> {
-        self.set.get(key).map(|v| v.clone())
+    pub fn smembers(&self, key: &[u8]) -> Option<DashSet<Vec<u8>>> {
+        self.track_read(key);
+        let ret = self.set.get(key).map(|v| v.clone());
+        if ret.is_none() {
+            self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+        }
+        ret
+    }
+
+    pub fn sismember(&self, key: &[u8], member: &[u8]) -> bool {
+        self.track_read(key);
+        let Some(set) = self.set.get(key) else {
+            self.notify_keyspace_event(NOTIFY_KEY_MISS, "keymiss", key);
+            return false;
+        };
+        set.contains(member)
+    }
+
+    pub fn ts_create(&self, key: String, opts: TimeSeriesOptions) -> Result<(), TimeSeriesError> {
+        match self.ts.entry(key) {
+            Entry::Occupied(_) => Err(TimeSeriesError::KeyExists),
+            Entry::Vacant(entry) => {
+                let key = entry.key().clone();
+                self.touch(key.as_bytes());
+                entry.insert(TimeSeries::new(opts));
+                self.notify_keyspace_event(NOTIFY_MODULE, "ts.create", key.as_bytes());
+                Ok(())
+            }
+        }
+    }
+
+    // add a sample, creating the series with the given options if it does not exist
+    pub fn ts_add(
+        &self,
+        key: String,
+        timestamp: u64,
+        value: f64,
+        opts: TimeSeriesOptions,
+        on_duplicate: Option<DuplicatePolicy>,
+    ) -> Result<u64, TimeSeriesError> {
+        let compacted = {
+            let mut series = self
+                .ts
+                .entry(key.clone())
+                .or_insert_with(|| TimeSeries::new(opts));
+            series.add(timestamp, value, on_duplicate)?
+        };
+        self.touch(key.as_bytes());
+        self.notify_keyspace_event(NOTIFY_MODULE, "ts.add", key.as_bytes());
+        // the source entry is released before touching the destinations, which may share a shard
+        for (dest, ts, v) in compacted {
+            self.ts_append(&dest, ts, v, Some(DuplicatePolicy::Last))?;
+        }
+        Ok(timestamp)
+    }
+
+    // add a sample to an existing series
+    pub fn ts_append(
+        &self,
+        key: &str,
+        timestamp: u64,
+        value: f64,
+        on_duplicate: Option<DuplicatePolicy>,
+    ) -> Result<u64, TimeSeriesError> {
+        let ret = self.ts_push(key, timestamp, value, on_duplicate)?;
+        self.notify_keyspace_event(NOTIFY_MODULE, "ts.add", key.as_bytes());
+        Ok(ret)
+    }
+
+    fn ts_push(
+        &self,
+        key: &str,
+        timestamp: u64,
+        value: f64,
+        on_duplicate: Option<DuplicatePolicy>,
+    ) -> Result<u64, TimeSeriesError> {
+        let compacted = match self.ts.get_mut(key) {
+            Some(mut series) => series.add(timestamp, value, on_duplicate)?,
+            None => return Err(TimeSeriesError::KeyNotFound),
+        };
+        self.touch(key.as_bytes());
+        for (dest, ts, v) in compacted {
+            self.ts_append(&dest, ts, v, Some(DuplicatePolicy::Last))?;
+        }
+        Ok(timestamp)
+    }
+
+    // add a sample holding the latest value plus delta, a new series starts from 0
+    pub fn ts_incrby(
+        &self,
+        key: String,
+        delta: f64,
+        timestamp: u64,
+        opts: TimeSeriesOptions,
+    ) -> Result<u64, TimeSeriesError> {
+        self.ts_add_delta(key, delta, timestamp, opts, "ts.incrby")
+    }
+
+    // add a sample holding the latest value minus delta, a new series starts from 0
+    pub fn ts_decrby(
+        &self,
+        key: String,
+        delta: f64,
+        timestamp: u64,
+        opts: TimeSeriesOptions,
+    ) -> Result<u64, TimeSeriesError> {
+        self.ts_add_delta(key, -delta, timestamp, opts, "ts.decrby")
+    }
+
+    fn ts_add_delta(
+        &self,
+        key: String,
+        delta: f64,
+        timestamp: u64,
+        opts: TimeSeriesOptions,
+        event: &str,
+    ) -> Result<u64, TimeSeriesError> {
+        // the latest value is read and the sample written under one guard, so concurrent
+        // increments don't start from the same value
+        let compacted = {
+            let mut series = self
+                .ts
+                .entry(key.clone())
+                .or_insert_with(|| TimeSeries::new(opts));
+            let value = match series.last() {
+                Some((last_ts, _)) if timestamp < last_ts => {
+                    return Err(TimeSeriesError::NotLatest)
+                }
+                Some((_, last)) => last + delta,
+                None => delta,
+            };
+            series.add(timestamp, value, Some(DuplicatePolicy::Last))?
+        };
+        self.touch(key.as_bytes());
+        self.notify_keyspace_event(NOTIFY_MODULE, event, key.as_bytes());
+        // the source entry is released before touching the destinations, which may share a shard
+        for (dest, ts, v) in compacted {
+            self.ts_append(&dest, ts, v, Some(DuplicatePolicy::Last))?;
+        }
+        Ok(timestamp)
+    }
+
+    pub fn ts_range(
+        &self,
+        key: &str,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<(u64, f64)>, TimeSeriesError> {
+        self.ts
+            .get(key)
+            .map(|series| series.range(from, to))
+            .ok_or(TimeSeriesError::KeyNotFound)
+    }
+
+    // range over every series matching the filters, sorted by key
+    pub fn ts_mrange(
+        &self,
+        from: u64,
+        to: u64,
+        filters: &[LabelFilter],
+    ) -> Vec<(String, Labels, Samples)> {
+        let mut ret: Vec<_> = self
+            .ts
+            .iter()
+            .filter(|v| v.value().matches(filters))
+            .map(|v| {
+                (
+                    v.key().clone(),
+                    v.value().labels.clone(),
+                    v.value().range(from, to),
+                )
+            })
+            .collect();
+        ret.sort_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    pub fn ts_create_rule(
+        &self,
+        src: &str,
+        dest: &str,
+        aggregation: Aggregation,
+        bucket_duration: u64,
+    ) -> Result<(), TimeSeriesError> {
+        if src == dest {
+            return Err(TimeSeriesError::SameKey);
+        }
+        {
+            let series = self.ts.get(dest).ok_or(TimeSeriesError::KeyNotFound)?;
+            if series.source.is_some() {
+                return Err(TimeSeriesError::DestinationHasSource);
+            }
+            if !series.rules.is_empty() {
+                return Err(TimeSeriesError::DestinationHasRules);
+            }
+        }
+        {
+            let mut series = self.ts.get_mut(src).ok_or(TimeSeriesError::KeyNotFound)?;
+            if series.source.is_some() {
+                return Err(TimeSeriesError::SourceIsDestination);
+            }
+            series.rules.push(CompactionRule::new(
+                dest.to_string(),
+                aggregation,
+                bucket_duration,
+            ));
+        }
+        self.touch(src.as_bytes());
+        self.touch(dest.as_bytes());
+        if let Some(mut series) = self.ts.get_mut(dest) {
+            series.source = Some(src.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn ft_create(&self, name: String, schema: IndexSchema) -> Result<(), SearchError> {
+        if self.indexes.contains_key(&name) {
+            return Err(SearchError::IndexExists);
+        }
+        let mut index = SearchIndex::new(schema);
+        let keys = self
+            .hmap
+            .iter()
+            .filter_map(|v| String::from_utf8(v.key().clone()).ok())
+            .filter(|key| index.matches_key(key))
+            .collect::<Vec<_>>();
+        for key in keys {
+            index.index(&key, &self.hash_strings(&key));
+        }
+        match self.indexes.entry(name) {
+            dashmap::mapref::entry::Entry::Occupied(_) => Err(SearchError::IndexExists),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(index);
+                Ok(())
+            }
+        }
+    }
+
+    // the keys of the matching documents, ordered by key unless a sort field is given
+    pub fn ft_search(
+        &self,
+        name: &str,
+        query: &Query,
+        sort_by: Option<(&str, bool)>,
+    ) -> Result<Vec<String>, SearchError> {
+        let index = self.indexes.get(name).ok_or(SearchError::UnknownIndex)?;
+        let mut keys = index.search(query)?.into_iter().collect::<Vec<_>>();
+        keys.sort();
+        if let Some((field, asc)) = sort_by {
+            index.sort(&mut keys, field, asc)?;
+        }
+        Ok(keys)
     }
 
-    pub fn sismember(&self, key: &str, member: &str) -> bool {
-        self.set.get(key).map_or(false, |v| v.contains(member))
+    pub fn ft_dropindex(&self, name: &str, delete_docs: bool) -> Result<(), SearchError> {
+        let (_, index) = self.indexes.remove(name).ok_or(SearchError::UnknownIndex)?;
+        if delete_docs {
+            for key in index.keys() {
+                let removed = self.hmap.remove(key.as_bytes()).is_some();
+                self.touch(key.as_bytes());
+                for mut other in self.indexes.iter_mut() {
+                    other.remove(key);
+                }
+                if removed {
+                    self.notify_keyspace_event(NOTIFY_GENERIC, "del", key.as_bytes());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn ft_info(&self, name: &str) -> Result<(IndexSchema, usize), SearchError> {
+        self.indexes
+            .get(name)
+            .map(|index| (index.schema.clone(), index.num_docs()))
+            .ok_or(SearchError::UnknownIndex)
+    }
+}
+
+// the textual form of a hash value, as seen by the search indexes
+pub(crate) fn frame_to_string(frame: &RespFrame) -> Option<String> {
+    match frame {
+        RespFrame::BulkString(s) => {
+            s.0.as_ref()
+                .map(|v| String::from_utf8_lossy(v).into_owned())
+        }
+        RespFrame::SimpleString(s) => Some(s.0.clone()),
+        RespFrame::Integer(i) => Some(i.to_string()),
+        RespFrame::Double(d) => Some(d.to_string()),
+        _ => None,
     }
 }
@@ -0,0 +1,187 @@
+use std::{fmt, str::FromStr, sync::atomic::Ordering};
+
+use thiserror::Error;
+
+use super::Backend;
+use crate::BulkString;
+
+// notify-keyspace-events classes, one bit per flag letter
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0;
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1;
+pub const NOTIFY_GENERIC: u32 = 1 << 2;
+pub const NOTIFY_STRING: u32 = 1 << 3;
+pub const NOTIFY_LIST: u32 = 1 << 4;
+pub const NOTIFY_SET: u32 = 1 << 5;
+pub const NOTIFY_HASH: u32 = 1 << 6;
+pub const NOTIFY_ZSET: u32 = 1 << 7;
+pub const NOTIFY_EXPIRED: u32 = 1 << 8;
+pub const NOTIFY_EVICTED: u32 = 1 << 9;
+pub const NOTIFY_STREAM: u32 = 1 << 10;
+pub const NOTIFY_KEY_MISS: u32 = 1 << 11;
+pub const NOTIFY_MODULE: u32 = 1 << 12;
+pub const NOTIFY_NEW: u32 = 1 << 13;
+// `A` is an alias for every class but key miss and new key events
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM
+    | NOTIFY_MODULE;
+
+// flag letters in the order redis prints them
+const FLAGS: [(char, u32); 14] = [
+    ('g', NOTIFY_GENERIC),
+    ('$', NOTIFY_STRING),
+    ('l', NOTIFY_LIST),
+    ('s', NOTIFY_SET),
+    ('h', NOTIFY_HASH),
+    ('z', NOTIFY_ZSET),
+    ('x', NOTIFY_EXPIRED),
+    ('e', NOTIFY_EVICTED),
+    ('t', NOTIFY_STREAM),
+    ('d', NOTIFY_MODULE),
+    ('K', NOTIFY_KEYSPACE),
+    ('E', NOTIFY_KEYEVENT),
+    ('m', NOTIFY_KEY_MISS),
+    ('n', NOTIFY_NEW),
+];
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum NotifyError {
+    #[error("Invalid event class character '{0}'")]
+    InvalidFlag(char),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NotifyFlags(pub u32);
+
+impl Backend {
+    pub fn set_notify_keyspace_events(&self, flags: NotifyFlags) {
+        self.notify_flags.store(flags.0, Ordering::Relaxed);
+    }
+
+    pub fn notify_keyspace_events(&self) -> NotifyFlags {
+        NotifyFlags(self.notify_flags.load(Ordering::Relaxed))
+    }
+
+    // publish the keyspace and keyevent messages of an event, a no-op unless its class is enabled
+    // channel names are textual, a binary key is spelled lossily in its keyspace channel
+    pub(crate) fn notify_keyspace_event(&self, class: u32, event: &str, key: &[u8]) {
+        let flags = self.notify_flags.load(Ordering::Relaxed);
+        if flags & class == 0 {
+            return;
+        }
+        if flags & NOTIFY_KEYSPACE != 0 {
+            self.publish(
+                &format!("__keyspace@0__:{}", String::from_utf8_lossy(key)),
+                event.into(),
+            );
+        }
+        if flags & NOTIFY_KEYEVENT != 0 {
+            self.publish(
+                &format!("__keyevent@0__:{}", event),
+                BulkString::new(key.to_vec()),
+            );
+        }
+    }
+}
+
+impl FromStr for NotifyFlags {
+    type Err = NotifyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut flags = 0;
+        for c in s.chars() {
+            flags |= match c {
+                'A' => NOTIFY_ALL,
+                c => FLAGS
+                    .iter()
+                    .find(|(f, _)| *f == c)
+                    .map(|(_, v)| *v)
+                    .ok_or(NotifyError::InvalidFlag(c))?,
+            };
+        }
+        // classes alone are useless, keep the config disabled like redis does
+        if flags & (NOTIFY_KEYSPACE | NOTIFY_KEYEVENT) == 0 {
+            flags = 0;
+        }
+        Ok(NotifyFlags(flags))
+    }
+}
+
+impl fmt::Display for NotifyFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let all = self.0 & NOTIFY_ALL == NOTIFY_ALL;
+        if all {
+            write!(f, "A")?;
+        }
+        for (c, flag) in FLAGS {
+            if self.0 & flag != 0 && !(all && flag & NOTIFY_ALL != 0) {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notify_flags() {
+        let flags: NotifyFlags = "KEA".parse().unwrap();
+        assert_eq!(flags.0, NOTIFY_KEYSPACE | NOTIFY_KEYEVENT | NOTIFY_ALL);
+        assert_eq!(flags.to_string(), "AKE");
+        let flags: NotifyFlags = "Ex$".parse().unwrap();
+        assert_eq!(flags.to_string(), "$xE");
+        assert_eq!("g$".parse::<NotifyFlags>().unwrap(), NotifyFlags(0));
+        assert_eq!(
+            "Kq".parse::<NotifyFlags>(),
+            Err(NotifyError::InvalidFlag('q'))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keyspace_notifications() {
+        let backend = Backend::new();
+        let (subscriber, mut rx) = backend.subscriber();
+        backend.psubscribe("__key*__:*", &subscriber);
+
+        // disabled by default
+        backend.set(b"a".to_vec(), "1".into());
+        backend.set_notify_keyspace_events("KEA".parse().unwrap());
+        backend.set(b"a".to_vec(), "2".into());
+        backend.sadd(b"s".to_vec(), vec![b"m".to_vec()]);
+        assert_eq!(backend.get(b"missing"), None);
+
+        let frames = |msg: Option<crate::PubSubMessage>| msg.unwrap().into_frames();
+        assert_eq!(
+            frames(rx.recv().await)[2..],
+            [b"__keyspace@0__:a".into(), b"set".into()]
+        );
+        assert_eq!(
+            frames(rx.recv().await)[2..],
+            [b"__keyevent@0__:set".into(), b"a".into()]
+        );
+        assert_eq!(
+            frames(rx.recv().await)[2..],
+            [b"__keyspace@0__:s".into(), b"sadd".into()]
+        );
+        assert_eq!(
+            frames(rx.recv().await)[2..],
+            [b"__keyevent@0__:sadd".into(), b"s".into()]
+        );
+        // key miss is not part of `A`
+        backend.set_notify_keyspace_events("Em".parse().unwrap());
+        assert_eq!(backend.get(b"missing"), None);
+        assert_eq!(
+            frames(rx.recv().await)[2..],
+            [b"__keyevent@0__:keymiss".into(), b"missing".into()]
+        );
+    }
+}
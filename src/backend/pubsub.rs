@@ -0,0 +1,425 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+use dashmap::DashMap;
+use tokio::sync::{mpsc, Notify};
+use tracing::warn;
+
+use crate::{BulkString, ClientClass, OutputBufferLimit, RespArray, RespFrame, RespPush};
+
+use super::{glob::glob_match, Backend};
+
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+#[derive(Debug, Default)]
+pub struct PubSub {
+    channels: DashMap<String, HashMap<u64, Subscriber>>,
+    patterns: DashMap<String, HashMap<u64, Subscriber>>,
+    // sharded channels live apart from the global ones, as in redis 7
+    shard_channels: DashMap<String, HashMap<u64, Subscriber>>,
+    next_id: AtomicU64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PubSubMessage {
+    Message {
+        channel: String,
+        payload: BulkString,
+    },
+    PMessage {
+        pattern: String,
+        channel: String,
+        payload: BulkString,
+    },
+    SMessage {
+        channel: String,
+        payload: BulkString,
+    },
+    // client side caching, None invalidates every key
+    Invalidate {
+        keys: Option<Vec<Vec<u8>>>,
+    },
+    TrackingRedirBroken {
+        id: u64,
+    },
+}
+
+// the sending half of a connection's message queue
+#[derive(Debug, Clone)]
+pub struct Subscriber {
+    id: u64,
+    tx: mpsc::UnboundedSender<PubSubMessage>,
+    // bytes queued but not yet written to the connection
+    pending: Arc<AtomicUsize>,
+    // when the queued bytes went over the soft output buffer limit
+    over_soft_since: Arc<Mutex<Option<Instant>>>,
+    killed: Arc<AtomicBool>,
+    kill: Arc<Notify>,
+}
+
+// the receiving half, owned by the connection
+#[derive(Debug)]
+pub struct SubscriberReceiver {
+    rx: mpsc::UnboundedReceiver<PubSubMessage>,
+    pending: Arc<AtomicUsize>,
+    killed: Arc<AtomicBool>,
+    kill: Arc<Notify>,
+}
+
+impl Backend {
+    pub fn subscriber(&self) -> (Subscriber, SubscriberReceiver) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let pending = Arc::new(AtomicUsize::new(0));
+        let killed = Arc::new(AtomicBool::new(false));
+        let kill = Arc::new(Notify::new());
+        let id = self.pubsub.next_id.fetch_add(1, Ordering::Relaxed);
+        let subscriber = Subscriber {
+            id,
+            tx,
+            pending: pending.clone(),
+            over_soft_since: Arc::default(),
+            killed: killed.clone(),
+            kill: kill.clone(),
+        };
+        let receiver = SubscriberReceiver {
+            rx,
+            pending,
+            killed,
+            kill,
+        };
+        (subscriber, receiver)
+    }
+
+    pub fn subscribe(&self, channel: &str, subscriber: &Subscriber) {
+        self.pubsub
+            .channels
+            .entry(channel.to_string())
+            .or_default()
+            .insert(subscriber.id, subscriber.clone());
+    }
+
+    pub fn unsubscribe(&self, channel: &str, subscriber: &Subscriber) {
+        remove_subscriber(&self.pubsub.channels, channel, subscriber.id);
+    }
+
+    pub fn psubscribe(&self, pattern: &str, subscriber: &Subscriber) {
+        self.pubsub
+            .patterns
+            .entry(pattern.to_string())
+            .or_default()
+            .insert(subscriber.id, subscriber.clone());
+    }
+
+    pub fn punsubscribe(&self, pattern: &str, subscriber: &Subscriber) {
+        remove_subscriber(&self.pubsub.patterns, pattern, subscriber.id);
+    }
+
+    // deliver a message to the channel and pattern subscribers, returning the number of receivers
+    pub fn publish(&self, channel: &str, payload: BulkString) -> usize {
+        let limit = self.output_buffer_limit(ClientClass::PubSub);
+        let mut receivers = 0;
+        if let Some(subscribers) = self.pubsub.channels.get(channel) {
+            for subscriber in subscribers.values() {
+                let msg = PubSubMessage::Message {
+                    channel: channel.to_string(),
+                    payload: payload.clone(),
+                };
+                if subscriber.deliver(msg, limit) {
+                    receivers += 1;
+                }
+            }
+        }
+        for entry in self.pubsub.patterns.iter() {
+            if !glob_match(entry.key(), channel) {
+                continue;
+            }
+            for subscriber in entry.value().values() {
+                let msg = PubSubMessage::PMessage {
+                    pattern: entry.key().clone(),
+                    channel: channel.to_string(),
+                    payload: payload.clone(),
+                };
+                if subscriber.deliver(msg, limit) {
+                    receivers += 1;
+                }
+            }
+        }
+        receivers
+    }
+
+    pub fn ssubscribe(&self, channel: &str, subscriber: &Subscriber) {
+        self.pubsub
+            .shard_channels
+            .entry(channel.to_string())
+            .or_default()
+            .insert(subscriber.id, subscriber.clone());
+    }
+
+    pub fn sunsubscribe(&self, channel: &str, subscriber: &Subscriber) {
+        remove_subscriber(&self.pubsub.shard_channels, channel, subscriber.id);
+    }
+
+    // sharded messages only reach subscribers of the exact shard channel
+    pub fn spublish(&self, channel: &str, payload: BulkString) -> usize {
+        let Some(subscribers) = self.pubsub.shard_channels.get(channel) else {
+            return 0;
+        };
+        let limit = self.output_buffer_limit(ClientClass::PubSub);
+        subscribers
+            .values()
+            .filter(|subscriber| {
+                let msg = PubSubMessage::SMessage {
+                    channel: channel.to_string(),
+                    payload: payload.clone(),
+                };
+                subscriber.deliver(msg, limit)
+            })
+            .count()
+    }
+
+    // active channels, optionally filtered by a glob pattern
+    pub fn pubsub_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let mut ret = self
+            .pubsub
+            .channels
+            .iter()
+            .filter(|v| pattern.is_none_or(|p| glob_match(p, v.key())))
+            .map(|v| v.key().clone())
+            .collect::<Vec<_>>();
+        ret.sort();
+        ret
+    }
+
+    pub fn pubsub_numsub(&self, channel: &str) -> usize {
+        self.pubsub.channels.get(channel).map_or(0, |v| v.len())
+    }
+
+    pub fn pubsub_numpat(&self) -> usize {
+        self.pubsub.patterns.len()
+    }
+
+    pub fn pubsub_shardchannels(&self, pattern: Option<&str>) -> Vec<String> {
+        let mut ret = self
+            .pubsub
+            .shard_channels
+            .iter()
+            .filter(|v| pattern.is_none_or(|p| glob_match(p, v.key())))
+            .map(|v| v.key().clone())
+            .collect::<Vec<_>>();
+        ret.sort();
+        ret
+    }
+
+    pub fn pubsub_shardnumsub(&self, channel: &str) -> usize {
+        self.pubsub
+            .shard_channels
+            .get(channel)
+            .map_or(0, |v| v.len())
+    }
+}
+
+fn remove_subscriber(map: &DashMap<String, HashMap<u64, Subscriber>>, key: &str, id: u64) {
+    map.remove_if_mut(key, |_, subscribers| {
+        subscribers.remove(&id);
+        subscribers.is_empty()
+    });
+}
+
+impl Subscriber {
+    // the id of the connection owning the subscriber
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    // queue a message, a subscriber over its output buffer limit is killed instead
+    pub(crate) fn deliver(&self, msg: PubSubMessage, limit: OutputBufferLimit) -> bool {
+        if self.killed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let size = msg.size();
+        let pending = self.pending.fetch_add(size, Ordering::Relaxed) + size;
+        let mut over_soft_since = self
+            .over_soft_since
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Err(reason) = limit.check(pending, &mut over_soft_since) {
+            warn!(
+                "subscriber {} is over the output buffer limit ({}), disconnecting",
+                self.id, reason
+            );
+            self.killed.store(true, Ordering::Relaxed);
+            self.kill.notify_one();
+            return false;
+        }
+        self.tx.send(msg).is_ok()
+    }
+}
+
+impl SubscriberReceiver {
+    // the next queued message, None once the subscriber has been killed for being too slow
+    pub async fn recv(&mut self) -> Option<PubSubMessage> {
+        if self.killed.load(Ordering::Relaxed) {
+            return None;
+        }
+        tokio::select! {
+            msg = self.rx.recv() => {
+                let msg = msg?;
+                self.pending.fetch_sub(msg.size(), Ordering::Relaxed);
+                Some(msg)
+            }
+            _ = self.kill.notified() => None,
+        }
+    }
+}
+
+impl PubSubMessage {
+    fn size(&self) -> usize {
+        match self {
+            PubSubMessage::Message { channel, payload }
+            | PubSubMessage::SMessage { channel, payload } => channel.len() + payload.len(),
+            PubSubMessage::PMessage {
+                pattern,
+                channel,
+                payload,
+            } => pattern.len() + channel.len() + payload.len(),
+            PubSubMessage::Invalidate { keys } => keys.iter().flatten().map(|key| key.len()).sum(),
+            PubSubMessage::TrackingRedirBroken { .. } => 0,
+        }
+    }
+
+    pub fn into_frames(self) -> Vec<RespFrame> {
+        match self {
+            PubSubMessage::Message { channel, payload } => vec![
+                BulkString::from("message").into(),
+                BulkString::from(channel).into(),
+                payload.into(),
+            ],
+            PubSubMessage::PMessage {
+                pattern,
+                channel,
+                payload,
+            } => vec![
+                BulkString::from("pmessage").into(),
+                BulkString::from(pattern).into(),
+                BulkString::from(channel).into(),
+                payload.into(),
+            ],
+            PubSubMessage::SMessage { channel, payload } => vec![
+                BulkString::from("smessage").into(),
+                BulkString::from(channel).into(),
+                payload.into(),
+            ],
+            // RESP2 clients receive invalidations on the redirect connection's channel
+            PubSubMessage::Invalidate { keys } => vec![
+                BulkString::from("message").into(),
+                BulkString::from(INVALIDATE_CHANNEL).into(),
+                invalidated_keys(keys),
+            ],
+            PubSubMessage::TrackingRedirBroken { id } => vec![
+                BulkString::from("tracking-redir-broken").into(),
+                RespFrame::Integer(id as i64),
+            ],
+        }
+    }
+
+    // the RESP3 push form of the message
+    pub fn into_push(self) -> RespPush {
+        match self {
+            PubSubMessage::Invalidate { keys } => RespPush::new([
+                BulkString::from("invalidate").into(),
+                invalidated_keys(keys),
+            ]),
+            msg => RespPush::new(msg.into_frames()),
+        }
+    }
+}
+
+fn invalidated_keys(keys: Option<Vec<Vec<u8>>>) -> RespFrame {
+    match keys {
+        Some(keys) => RespArray::new(
+            keys.into_iter()
+                .map(|key| BulkString::new(key).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into(),
+        None => RespArray(None).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_to_channels_and_patterns() {
+        let backend = Backend::new();
+        let (subscriber, mut rx) = backend.subscriber();
+        backend.subscribe("news.tech", &subscriber);
+        backend.psubscribe("news.*", &subscriber);
+        assert_eq!(backend.pubsub_channels(Some("news*")), vec!["news.tech"]);
+        assert_eq!(backend.pubsub_numsub("news.tech"), 1);
+        assert_eq!(backend.pubsub_numpat(), 1);
+
+        assert_eq!(backend.publish("news.tech", "hi".into()), 2);
+        assert_eq!(backend.publish("news.sport", "hi".into()), 1);
+        assert_eq!(backend.publish("weather", "hi".into()), 0);
+
+        let msg = rx.recv().await.unwrap();
+        assert_eq!(
+            msg,
+            PubSubMessage::Message {
+                channel: "news.tech".to_string(),
+                payload: "hi".into()
+            }
+        );
+        assert!(matches!(
+            rx.recv().await.unwrap(),
+            PubSubMessage::PMessage { .. }
+        ));
+
+        backend.unsubscribe("news.tech", &subscriber);
+        backend.punsubscribe("news.*", &subscriber);
+        assert!(backend.pubsub_channels(None).is_empty());
+        assert_eq!(backend.pubsub_numpat(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_spublish_to_shard_channels() {
+        let backend = Backend::new();
+        let (subscriber, mut rx) = backend.subscriber();
+        backend.ssubscribe("orders", &subscriber);
+        backend.subscribe("orders", &subscriber);
+        assert_eq!(backend.pubsub_shardchannels(None), vec!["orders"]);
+        assert_eq!(backend.pubsub_shardnumsub("orders"), 1);
+
+        assert_eq!(backend.spublish("orders", "hi".into()), 1);
+        assert_eq!(backend.spublish("other", "hi".into()), 0);
+        assert_eq!(
+            rx.recv().await.unwrap().into_frames(),
+            vec![b"smessage".into(), b"orders".into(), b"hi".into()]
+        );
+
+        backend.sunsubscribe("orders", &subscriber);
+        assert!(backend.pubsub_shardchannels(None).is_empty());
+        assert_eq!(backend.pubsub_channels(None), vec!["orders"]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_killed() {
+        let backend = Backend::new();
+        let (subscriber, mut rx) = backend.subscriber();
+        backend.subscribe("big", &subscriber);
+        let payload = BulkString::new(vec![0u8; 1024 * 1024]);
+        for _ in 0..32 {
+            backend.publish("big", payload.clone());
+        }
+        assert_eq!(backend.publish("big", payload), 0);
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,461 @@
+mod query;
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Bound,
+};
+
+use thiserror::Error;
+
+pub use query::Query;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum SearchError {
+    #[error("Index already exists")]
+    IndexExists,
+    #[error("Unknown index name")]
+    UnknownIndex,
+    #[error("Syntax error: {0}")]
+    Syntax(String),
+    #[error("Property `{0}` not loaded nor in schema")]
+    UnknownField(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Text,
+    Tag(char),
+    Numeric,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: String,
+    pub field_type: FieldType,
+    pub sortable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IndexSchema {
+    pub prefixes: Vec<String>,
+    pub fields: Vec<FieldSchema>,
+}
+
+// term -> document -> term positions
+type Postings = HashMap<String, HashMap<String, Vec<usize>>>;
+
+// an index over the hashes whose key starts with one of the schema prefixes
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    pub schema: IndexSchema,
+    // the indexed field values of each document, used to unindex and to sort
+    docs: HashMap<String, HashMap<String, String>>,
+    // field -> postings
+    text: HashMap<String, Postings>,
+    // field -> tag -> documents
+    tags: HashMap<String, HashMap<String, HashSet<String>>>,
+    // field -> value -> documents
+    numeric: HashMap<String, BTreeMap<NumKey, HashSet<String>>>,
+}
+
+// f64 with a total order so it can be used as a BTreeMap key
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NumKey(f64);
+
+impl SearchIndex {
+    pub fn new(schema: IndexSchema) -> Self {
+        Self {
+            schema,
+            ..Default::default()
+        }
+    }
+
+    pub fn num_docs(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn matches_key(&self, key: &str) -> bool {
+        self.schema.prefixes.is_empty() || self.schema.prefixes.iter().any(|p| key.starts_with(p))
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldSchema> {
+        self.schema.fields.iter().find(|f| f.name == name)
+    }
+
+    // (re)index a document from the current content of its hash
+    pub fn index(&mut self, key: &str, values: &HashMap<String, String>) {
+        self.remove(key);
+        let mut doc = HashMap::new();
+        for field in &self.schema.fields {
+            let Some(value) = values.get(&field.name) else {
+                continue;
+            };
+            match field.field_type {
+                FieldType::Text => {
+                    let postings = self.text.entry(field.name.clone()).or_default();
+                    for (pos, term) in tokenize(value).into_iter().enumerate() {
+                        postings
+                            .entry(term)
+                            .or_default()
+                            .entry(key.to_string())
+                            .or_default()
+                            .push(pos);
+                    }
+                }
+                FieldType::Tag(sep) => {
+                    let tags = self.tags.entry(field.name.clone()).or_default();
+                    for tag in split_tags(value, sep) {
+                        tags.entry(tag).or_default().insert(key.to_string());
+                    }
+                }
+                FieldType::Numeric => {
+                    // documents with a malformed numeric value are not indexed on that field
+                    let Ok(v) = value.parse::<f64>() else {
+                        continue;
+                    };
+                    self.numeric
+                        .entry(field.name.clone())
+                        .or_default()
+                        .entry(NumKey(v))
+                        .or_default()
+                        .insert(key.to_string());
+                }
+            }
+            doc.insert(field.name.clone(), value.clone());
+        }
+        if !doc.is_empty() {
+            self.docs.insert(key.to_string(), doc);
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        let Some(doc) = self.docs.remove(key) else {
+            return;
+        };
+        for field in &self.schema.fields {
+            let Some(value) = doc.get(&field.name) else {
+                continue;
+            };
+            match field.field_type {
+                FieldType::Text => {
+                    if let Some(postings) = self.text.get_mut(&field.name) {
+                        for term in tokenize(value) {
+                            if let Some(docs) = postings.get_mut(&term) {
+                                docs.remove(key);
+                                if docs.is_empty() {
+                                    postings.remove(&term);
+                                }
+                            }
+                        }
+                    }
+                }
+                FieldType::Tag(sep) => {
+                    if let Some(tags) = self.tags.get_mut(&field.name) {
+                        for tag in split_tags(value, sep) {
+                            if let Some(docs) = tags.get_mut(&tag) {
+                                docs.remove(key);
+                                if docs.is_empty() {
+                                    tags.remove(&tag);
+                                }
+                            }
+                        }
+                    }
+                }
+                FieldType::Numeric => {
+                    if let (Some(values), Ok(v)) =
+                        (self.numeric.get_mut(&field.name), value.parse::<f64>())
+                    {
+                        if let Some(docs) = values.get_mut(&NumKey(v)) {
+                            docs.remove(key);
+                            if docs.is_empty() {
+                                values.remove(&NumKey(v));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.docs.keys()
+    }
+
+    pub fn search(&self, query: &Query) -> Result<HashSet<String>, SearchError> {
+        let ret = match query {
+            Query::All => self.docs.keys().cloned().collect(),
+            Query::Term { field, term } => {
+                let mut ret = HashSet::new();
+                for postings in self.text_postings(field.as_deref())? {
+                    if let Some(docs) = postings.get(term) {
+                        ret.extend(docs.keys().cloned());
+                    }
+                }
+                ret
+            }
+            Query::Phrase { field, terms } => {
+                let mut ret = HashSet::new();
+                for postings in self.text_postings(field.as_deref())? {
+                    ret.extend(phrase_matches(postings, terms));
+                }
+                ret
+            }
+            Query::Numeric { field, min, max } => {
+                self.check_field(field, |t| t == FieldType::Numeric)?;
+                let mut ret = HashSet::new();
+                if let Some(values) = self.numeric.get(field) {
+                    if valid_range(min, max) {
+                        let range = (min.map(NumKey), max.map(NumKey));
+                        for (_, docs) in values.range(range) {
+                            ret.extend(docs.iter().cloned());
+                        }
+                    }
+                }
+                ret
+            }
+            Query::Tag { field, tags } => {
+                self.check_field(field, |t| matches!(t, FieldType::Tag(_)))?;
+                let mut ret = HashSet::new();
+                if let Some(index) = self.tags.get(field) {
+                    for tag in tags {
+                        if let Some(docs) = index.get(tag) {
+                            ret.extend(docs.iter().cloned());
+                        }
+                    }
+                }
+                ret
+            }
+            Query::And(items) => {
+                let mut ret: Option<HashSet<String>> = None;
+                for item in items {
+                    let docs = self.search(item)?;
+                    ret = Some(match ret {
+                        Some(ret) => ret.intersection(&docs).cloned().collect(),
+                        None => docs,
+                    });
+                }
+                ret.unwrap_or_default()
+            }
+            Query::Or(items) => {
+                let mut ret = HashSet::new();
+                for item in items {
+                    ret.extend(self.search(item)?);
+                }
+                ret
+            }
+            Query::Not(item) => {
+                let excluded = self.search(item)?;
+                self.docs
+                    .keys()
+                    .filter(|k| !excluded.contains(*k))
+                    .cloned()
+                    .collect()
+            }
+        };
+        Ok(ret)
+    }
+
+    // sort documents by a schema field, numeric fields compare as numbers
+    pub fn sort(&self, keys: &mut [String], field: &str, asc: bool) -> Result<(), SearchError> {
+        let schema = self
+            .field(field)
+            .ok_or_else(|| SearchError::UnknownField(field.to_string()))?;
+        let value = |key: &String| self.docs.get(key).and_then(|doc| doc.get(field));
+        let numeric = schema.field_type == FieldType::Numeric;
+        keys.sort_by(|a, b| {
+            let ord = match (value(a), value(b)) {
+                (Some(a), Some(b)) if numeric => {
+                    let a = a.parse::<f64>().unwrap_or(f64::NAN);
+                    let b = b.parse::<f64>().unwrap_or(f64::NAN);
+                    a.total_cmp(&b)
+                }
+                (Some(a), Some(b)) => a.cmp(b),
+                // documents without the field go last
+                (Some(_), None) => return Ordering::Less,
+                (None, Some(_)) => return Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            };
+            if asc {
+                ord
+            } else {
+                ord.reverse()
+            }
+        });
+        Ok(())
+    }
+
+    fn check_field(
+        &self,
+        name: &str,
+        expected: impl Fn(FieldType) -> bool,
+    ) -> Result<(), SearchError> {
+        match self.field(name) {
+            Some(f) if expected(f.field_type) => Ok(()),
+            _ => Err(SearchError::UnknownField(name.to_string())),
+        }
+    }
+
+    fn text_postings(&self, field: Option<&str>) -> Result<Vec<&Postings>, SearchError> {
+        match field {
+            Some(field) => {
+                self.check_field(field, |t| t == FieldType::Text)?;
+                Ok(self.text.get(field).into_iter().collect())
+            }
+            None => Ok(self.text.values().collect()),
+        }
+    }
+}
+
+// lowercase alphanumeric tokens, the same tokenizer is used for documents and queries
+pub(crate) fn tokenize(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+fn split_tags(s: &str, sep: char) -> Vec<String> {
+    s.split(sep)
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+// documents where the terms appear at consecutive positions
+fn phrase_matches(postings: &Postings, terms: &[String]) -> Vec<String> {
+    let Some(first) = terms.first().and_then(|t| postings.get(t)) else {
+        return vec![];
+    };
+    first
+        .iter()
+        .filter(|(doc, positions)| {
+            positions.iter().any(|start| {
+                terms.iter().enumerate().skip(1).all(|(i, term)| {
+                    postings
+                        .get(term)
+                        .and_then(|docs| docs.get(*doc))
+                        .is_some_and(|p| p.contains(&(start + i)))
+                })
+            })
+        })
+        .map(|(doc, _)| doc.clone())
+        .collect()
+}
+
+// BTreeMap::range panics on inverted or empty exclusive ranges. the bounds are compared the
+// way NumKey orders them, where -0 comes before 0
+fn valid_range(min: &Bound<f64>, max: &Bound<f64>) -> bool {
+    match (min, max) {
+        (Bound::Excluded(a), Bound::Excluded(b)) => a.total_cmp(b).is_lt(),
+        (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => {
+            a.total_cmp(b).is_le()
+        }
+        _ => true,
+    }
+}
+
+impl Eq for NumKey {}
+
+impl PartialOrd for NumKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NumKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::new(IndexSchema {
+            prefixes: vec!["doc:".to_string()],
+            fields: vec![
+                FieldSchema {
+                    name: "title".to_string(),
+                    field_type: FieldType::Text,
+                    sortable: false,
+                },
+                FieldSchema {
+                    name: "tags".to_string(),
+                    field_type: FieldType::Tag(','),
+                    sortable: false,
+                },
+                FieldSchema {
+                    name: "price".to_string(),
+                    field_type: FieldType::Numeric,
+                    sortable: true,
+                },
+            ],
+        });
+        for (key, title, tags, price) in [
+            ("doc:1", "Hello big world", "red,blue", "10"),
+            ("doc:2", "hello world", "blue", "20"),
+            ("doc:3", "goodbye world", "green", "30"),
+        ] {
+            let values = [("title", title), ("tags", tags), ("price", price)]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            index.index(key, &values);
+        }
+        index
+    }
+
+    fn search(index: &SearchIndex, query: &str) -> Vec<String> {
+        let mut ret = index
+            .search(&Query::parse(query).unwrap())
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>();
+        ret.sort();
+        ret
+    }
+
+    #[test]
+    fn test_search_queries() {
+        let index = index();
+        assert!(index.matches_key("doc:4"));
+        assert!(!index.matches_key("user:1"));
+        assert_eq!(search(&index, "hello"), vec!["doc:1", "doc:2"]);
+        assert_eq!(search(&index, "\"hello world\""), vec!["doc:2"]);
+        assert_eq!(search(&index, "world -hello"), vec!["doc:3"]);
+        assert_eq!(search(&index, "goodbye | big"), vec!["doc:1", "doc:3"]);
+        assert_eq!(search(&index, "@tags:{blue}"), vec!["doc:1", "doc:2"]);
+        assert_eq!(search(&index, "@price:[(10 30]"), vec!["doc:2", "doc:3"]);
+        assert_eq!(search(&index, "*").len(), 3);
+        // empty and inverted ranges match nothing
+        assert!(search(&index, "@price:[(10 10]").is_empty());
+        assert!(search(&index, "@price:[(10 (10]").is_empty());
+        assert!(search(&index, "@price:[30 10]").is_empty());
+        assert!(search(&index, "@price:[0 -0]").is_empty());
+        assert!(search(&index, "@price:[(1 1]").is_empty());
+        assert_eq!(
+            index.search(&Query::parse("@missing:foo").unwrap()),
+            Err(SearchError::UnknownField("missing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reindex_and_sort() {
+        let mut index = index();
+        let values = [("title".to_string(), "bye".to_string())].into();
+        index.index("doc:1", &values);
+        assert_eq!(search(&index, "hello"), vec!["doc:2"]);
+        assert!(search(&index, "@tags:{red}").is_empty());
+
+        let mut keys = vec![
+            "doc:1".to_string(),
+            "doc:2".to_string(),
+            "doc:3".to_string(),
+        ];
+        index.sort(&mut keys, "price", false).unwrap();
+        assert_eq!(keys, vec!["doc:3", "doc:2", "doc:1"]);
+    }
+}
@@ -0,0 +1,353 @@
+use std::ops::Bound;
+
+use super::{tokenize, SearchError};
+
+// a parsed FT.SEARCH query, the supported syntax is a subset of the RediSearch dialect:
+// - `hello world` intersection, `hello | world` union, `-hello` negation, `(...)` grouping
+// - `"hello world"` exact phrase
+// - `@title:hello` restrict to a TEXT field
+// - `@price:[10 (20]` NUMERIC range, `-inf`/`+inf` are allowed
+// - `@tags:{red | blue}` TAG match
+// - `*` every document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    All,
+    Term {
+        field: Option<String>,
+        term: String,
+    },
+    Phrase {
+        field: Option<String>,
+        terms: Vec<String>,
+    },
+    Numeric {
+        field: String,
+        min: Bound<f64>,
+        max: Bound<f64>,
+    },
+    Tag {
+        field: String,
+        tags: Vec<String>,
+    },
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+// groups and field prefixes recurse, so nesting is capped to keep the stack bounded
+const MAX_QUERY_DEPTH: usize = 128;
+
+struct QueryParser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, SearchError> {
+        let mut parser = QueryParser {
+            input,
+            pos: 0,
+            depth: 0,
+        };
+        let query = parser.parse_union(None)?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(query),
+            Some(c) => Err(parser.error(format!("unexpected `{}`", c))),
+        }
+    }
+}
+
+impl<'a> QueryParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SearchError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", expected)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn error(&self, msg: impl Into<String>) -> SearchError {
+        SearchError::Syntax(format!("{} at offset {}", msg.into(), self.pos))
+    }
+
+    // read until whitespace or a query operator
+    fn read_word(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "()|{}[]\":@".contains(c) {
+                break;
+            }
+            self.bump();
+        }
+        &self.input[start..self.pos]
+    }
+
+    fn parse_union(&mut self, field: Option<&str>) -> Result<Query, SearchError> {
+        let mut items = vec![self.parse_intersect(field)?];
+        while self.eat('|') {
+            items.push(self.parse_intersect(field)?);
+        }
+        Ok(if items.len() == 1 {
+            items.remove(0)
+        } else {
+            Query::Or(items)
+        })
+    }
+
+    fn parse_intersect(&mut self, field: Option<&str>) -> Result<Query, SearchError> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some(')') | Some('|') => break,
+                _ => items.push(self.parse_factor(field)?),
+            }
+        }
+        match items.len() {
+            0 => Err(self.error("empty expression")),
+            1 => Ok(items.remove(0)),
+            _ => Ok(Query::And(items)),
+        }
+    }
+
+    fn parse_factor(&mut self, field: Option<&str>) -> Result<Query, SearchError> {
+        if self.eat('-') {
+            return Ok(Query::Not(Box::new(self.parse_atom(field)?)));
+        }
+        self.parse_atom(field)
+    }
+
+    fn parse_atom(&mut self, field: Option<&str>) -> Result<Query, SearchError> {
+        self.depth += 1;
+        if self.depth > MAX_QUERY_DEPTH {
+            return Err(self.error("query is nested too deeply"));
+        }
+        let query = self.parse_primary(field);
+        self.depth -= 1;
+        query
+    }
+
+    fn parse_primary(&mut self, field: Option<&str>) -> Result<Query, SearchError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.bump();
+                let query = self.parse_union(field)?;
+                self.expect(')')?;
+                Ok(query)
+            }
+            Some('@') => {
+                self.bump();
+                let name = self.read_word();
+                if name.is_empty() {
+                    return Err(self.error("missing field name"));
+                }
+                self.expect(':')?;
+                self.skip_whitespace();
+                match self.peek() {
+                    Some('[') => self.parse_numeric(name),
+                    Some('{') => self.parse_tags(name),
+                    _ => self.parse_atom(Some(name)),
+                }
+            }
+            Some('"') => {
+                self.bump();
+                let start = self.pos;
+                while !matches!(self.peek(), Some('"') | None) {
+                    self.bump();
+                }
+                let phrase = &self.input[start..self.pos];
+                self.expect('"')?;
+                Ok(text_query(field, phrase))
+            }
+            Some('*') => {
+                self.bump();
+                Ok(Query::All)
+            }
+            _ => {
+                let word = self.read_word();
+                if word.is_empty() {
+                    return Err(self.error("expected a term"));
+                }
+                Ok(text_query(field, word))
+            }
+        }
+    }
+
+    // [min max], each bound may be prefixed by `(` to make it exclusive
+    fn parse_numeric(&mut self, field: &str) -> Result<Query, SearchError> {
+        self.expect('[')?;
+        let min = self.parse_bound()?;
+        let max = self.parse_bound()?;
+        self.expect(']')?;
+        Ok(Query::Numeric {
+            field: field.to_string(),
+            min,
+            max,
+        })
+    }
+
+    fn parse_bound(&mut self) -> Result<Bound<f64>, SearchError> {
+        let exclusive = self.eat('(');
+        self.skip_whitespace();
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if !c.is_whitespace() && c != ']') {
+            self.bump();
+        }
+        let s = &self.input[start..self.pos];
+        let v = match s.to_ascii_lowercase().as_str() {
+            "-inf" => f64::NEG_INFINITY,
+            "inf" | "+inf" => f64::INFINITY,
+            v => v
+                .parse()
+                .map_err(|_| self.error(format!("invalid number `{}`", s)))?,
+        };
+        Ok(if exclusive {
+            Bound::Excluded(v)
+        } else {
+            Bound::Included(v)
+        })
+    }
+
+    // {tag1 | tag2}
+    fn parse_tags(&mut self, field: &str) -> Result<Query, SearchError> {
+        self.expect('{')?;
+        let start = self.pos;
+        while !matches!(self.peek(), Some('}') | None) {
+            self.bump();
+        }
+        let tags = self.input[start..self.pos]
+            .split('|')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+        self.expect('}')?;
+        if tags.is_empty() {
+            return Err(self.error("empty tag list"));
+        }
+        Ok(Query::Tag {
+            field: field.to_string(),
+            tags,
+        })
+    }
+}
+
+// a word is tokenized the same way as indexed text, so `foo-bar` becomes a phrase
+fn text_query(field: Option<&str>, text: &str) -> Query {
+    let field = field.map(|f| f.to_string());
+    let mut terms = tokenize(text);
+    if terms.len() == 1 {
+        Query::Term {
+            field,
+            term: terms.remove(0),
+        }
+    } else {
+        Query::Phrase { field, terms }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_terms() {
+        let q = Query::parse("hello World").unwrap();
+        assert_eq!(
+            q,
+            Query::And(vec![
+                Query::Term {
+                    field: None,
+                    term: "hello".to_string()
+                },
+                Query::Term {
+                    field: None,
+                    term: "world".to_string()
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let q = Query::parse("@title:(foo | \"bar baz\") -@tags:{ Red | blue } @price:[(10 +inf]")
+            .unwrap();
+        assert_eq!(
+            q,
+            Query::And(vec![
+                Query::Or(vec![
+                    Query::Term {
+                        field: Some("title".to_string()),
+                        term: "foo".to_string()
+                    },
+                    Query::Phrase {
+                        field: Some("title".to_string()),
+                        terms: vec!["bar".to_string(), "baz".to_string()]
+                    }
+                ]),
+                Query::Not(Box::new(Query::Tag {
+                    field: "tags".to_string(),
+                    tags: vec!["red".to_string(), "blue".to_string()]
+                })),
+                Query::Numeric {
+                    field: "price".to_string(),
+                    min: Bound::Excluded(10.0),
+                    max: Bound::Included(f64::INFINITY)
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Query::parse("(hello").is_err());
+        assert!(Query::parse("@price:[1 x]").is_err());
+        assert!(Query::parse("").is_err());
+    }
+
+    #[test]
+    fn test_parse_depth_limit() {
+        let nested = |n| format!("{}hello{}", "(".repeat(n), ")".repeat(n));
+        assert!(Query::parse(&nested(MAX_QUERY_DEPTH - 1)).is_ok());
+        assert!(matches!(
+            Query::parse(&nested(MAX_QUERY_DEPTH)),
+            Err(SearchError::Syntax(_))
+        ));
+        assert!(matches!(
+            Query::parse(&nested(100_000)),
+            Err(SearchError::Syntax(_))
+        ));
+        assert!(matches!(
+            Query::parse(&format!("{}hello", "@title:".repeat(100_000))),
+            Err(SearchError::Syntax(_))
+        ));
+    }
+}
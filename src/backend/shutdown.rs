@@ -0,0 +1,86 @@
+use tokio::sync::watch;
+use tracing::{error, warn};
+
+use super::Backend;
+
+// the process exits with this code once the dataset was saved as asked, or nothing had to be
+pub const EXIT_OK: i32 = 0;
+// FORCE shut the server down although saving the dataset failed
+pub const EXIT_SAVE_FAILED: i32 = 1;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownOptions {
+    // SAVE or NOSAVE, without either the dataset is saved when save points are configured
+    pub save: Option<bool>,
+    // don't wait for replicas, there are none to wait for
+    pub now: bool,
+    // shut down even if saving the dataset fails
+    pub force: bool,
+}
+
+impl Backend {
+    // save the dataset if asked to, then tell the listeners and connections to stop.
+    // an error leaves the server running
+    pub fn shutdown(&self, opts: ShutdownOptions) -> Result<(), String> {
+        let save = opts.save.unwrap_or_else(|| !self.config().save.is_empty());
+        let mut code = EXIT_OK;
+        if save {
+            if let Err(e) = self.persist() {
+                if !opts.force {
+                    error!("Error trying to save the DB, can't exit: {}", e);
+                    return Err("Errors trying to SHUTDOWN. Check logs.".to_string());
+                }
+                warn!("Error trying to save the DB, exiting anyway: {}", e);
+                code = EXIT_SAVE_FAILED;
+            }
+        }
+        // the first shutdown decides the exit code
+        self.shutdown.send_if_modified(|state| {
+            let first = state.is_none();
+            if first {
+                *state = Some(code);
+            }
+            first
+        });
+        Ok(())
+    }
+
+    // resolves to the exit code once the server is shutting down
+    pub fn shutdown_signal(&self) -> watch::Receiver<Option<i32>> {
+        self.shutdown.subscribe()
+    }
+
+    // there is no rdb or aof yet, so there is nowhere to save the dataset
+    fn persist(&self) -> Result<(), String> {
+        Err("persistence is not supported".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown() {
+        let backend = Backend::new();
+        let signal = backend.shutdown_signal();
+        assert_eq!(
+            backend.shutdown(ShutdownOptions {
+                save: Some(true),
+                ..Default::default()
+            }),
+            Err("Errors trying to SHUTDOWN. Check logs.".to_string())
+        );
+        assert_eq!(*signal.borrow(), None);
+
+        let force = ShutdownOptions {
+            save: Some(true),
+            force: true,
+            ..Default::default()
+        };
+        assert_eq!(backend.shutdown(force), Ok(()));
+        assert_eq!(*signal.borrow(), Some(EXIT_SAVE_FAILED));
+        assert_eq!(backend.shutdown(ShutdownOptions::default()), Ok(()));
+        assert_eq!(*signal.borrow(), Some(EXIT_SAVE_FAILED));
+    }
+}
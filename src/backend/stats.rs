@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use super::Backend;
+
+// server wide counters, CONFIG RESETSTAT sets them back to zero
+#[derive(Debug, Default)]
+pub struct Stats {
+    connections_received: AtomicU64,
+    commands_processed: AtomicU64,
+    // clients refused because maxclients were connected
+    rejected_connections: AtomicU64,
+    // a gauge rather than a counter, RESETSTAT leaves it alone
+    connected_clients: AtomicUsize,
+}
+
+// a connected client, counted against maxclients until it is dropped
+#[derive(Debug)]
+pub struct ClientSlot(Backend);
+
+impl Stats {
+    pub fn total_connections_received(&self) -> u64 {
+        self.connections_received.load(Ordering::Relaxed)
+    }
+
+    pub fn total_commands_processed(&self) -> u64 {
+        self.commands_processed.load(Ordering::Relaxed)
+    }
+
+    pub fn rejected_connections(&self) -> u64 {
+        self.rejected_connections.load(Ordering::Relaxed)
+    }
+
+    pub fn connected_clients(&self) -> usize {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_connection(&self) {
+        self.connections_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_command(&self) {
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.connections_received.store(0, Ordering::Relaxed);
+        self.commands_processed.store(0, Ordering::Relaxed);
+        self.rejected_connections.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Backend {
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    // take a slot for a new client, none if maxclients are already connected
+    pub fn try_connect(&self) -> Option<ClientSlot> {
+        let maxclients = self
+            .config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .maxclients;
+        let ret =
+            self.stats
+                .connected_clients
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                    (n < maxclients).then_some(n + 1)
+                });
+        match ret {
+            Ok(_) => Some(ClientSlot(self.clone())),
+            Err(_) => {
+                self.stats
+                    .rejected_connections
+                    .fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.0
+            .stats
+            .connected_clients
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maxclients() {
+        let backend = Backend::new();
+        backend
+            .config_set(&[("maxclients".to_string(), "2".to_string())])
+            .unwrap();
+        let first = backend.try_connect().unwrap();
+        let _second = backend.try_connect().unwrap();
+        assert!(backend.try_connect().is_none());
+        assert_eq!(backend.stats().connected_clients(), 2);
+        assert_eq!(backend.stats().rejected_connections(), 1);
+
+        drop(first);
+        assert_eq!(backend.stats().connected_clients(), 1);
+        assert!(backend.try_connect().is_some());
+        backend.reset_stats();
+        assert_eq!(backend.stats().rejected_connections(), 0);
+        assert_eq!(backend.stats().connected_clients(), 1);
+    }
+}
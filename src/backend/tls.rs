@@ -0,0 +1,146 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use tokio_rustls::{
+    rustls::{
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer},
+        server::WebPkiClientVerifier,
+        RootCertStore, ServerConfig,
+    },
+    TlsAcceptor,
+};
+
+use super::Backend;
+use crate::{Config, TlsAuthClients};
+
+impl Backend {
+    // the acceptor for new tls connections, none until the certificates are loaded
+    pub fn tls_acceptor(&self) -> Option<TlsAcceptor> {
+        self.tls
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .map(TlsAcceptor::from)
+    }
+
+    // read the certificates from disk again. connections accepted from now on use them,
+    // the ones already open keep their session
+    pub fn load_tls(&self, config: &Config) -> Result<(), String> {
+        let tls = tls_config(config)?;
+        *self.tls.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(tls));
+        Ok(())
+    }
+}
+
+fn tls_config(config: &Config) -> Result<ServerConfig, String> {
+    let cert_file = config
+        .tls_cert_file
+        .as_deref()
+        .ok_or("tls-cert-file is not set")?;
+    let key_file = config
+        .tls_key_file
+        .as_deref()
+        .ok_or("tls-key-file is not set")?;
+    let certs = load_certs(cert_file)?;
+    let key = load_key(key_file)?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?;
+    let builder = match config.tls_auth_clients {
+        TlsAuthClients::No => builder.with_no_client_auth(),
+        auth => {
+            let ca_file = config
+                .tls_ca_cert_file
+                .as_deref()
+                .ok_or("tls-ca-cert-file is needed to authenticate clients")?;
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_file)? {
+                roots
+                    .add(cert)
+                    .map_err(|e| format!("{}: {}", ca_file.display(), e))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider);
+            let verifier = match auth {
+                TlsAuthClients::Optional => verifier.allow_unauthenticated(),
+                _ => verifier,
+            };
+            builder.with_client_cert_verifier(verifier.build().map_err(|e| e.to_string())?)
+        }
+    };
+    // fails if the key does not belong to the certificate
+    builder
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("{}: {}", key_file.display(), e))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let file = File::open(path).map_err(|e| err(&e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| err(&e))?;
+    if certs.is_empty() {
+        return Err(err(&"no certificate found"));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    let err = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let file = File::open(path).map_err(|e| err(&e))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| err(&e))?
+        .ok_or_else(|| err(&"no private key found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::PathBuf};
+
+    // a self-signed certificate and its key, written to files named after the test
+    fn self_signed(name: &str) -> (PathBuf, PathBuf) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let dir = std::env::temp_dir();
+        let prefix = format!("simple-redis-{}-{}", std::process::id(), name);
+        let cert_file = dir.join(format!("{}.crt", prefix));
+        let key_file = dir.join(format!("{}.key", prefix));
+        fs::write(&cert_file, cert.cert.pem()).unwrap();
+        fs::write(&key_file, cert.key_pair.serialize_pem()).unwrap();
+        (cert_file, key_file)
+    }
+
+    #[test]
+    fn test_load_tls() {
+        let backend = Backend::new();
+        assert!(backend.tls_acceptor().is_none());
+        let (cert_file, key_file) = self_signed("a");
+        let (other_cert, other_key) = self_signed("b");
+        let mut config = Config {
+            tls_cert_file: Some(cert_file.clone()),
+            tls_key_file: Some(key_file.clone()),
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.load_tls(&config),
+            Err("tls-ca-cert-file is needed to authenticate clients".to_string())
+        );
+        config.tls_ca_cert_file = Some(other_cert.clone());
+        assert!(backend.load_tls(&config).is_ok());
+        assert!(backend.tls_acceptor().is_some());
+
+        config.tls_auth_clients = TlsAuthClients::No;
+        config.tls_key_file = Some(other_key.clone());
+        assert!(backend.load_tls(&config).is_err());
+        config.tls_key_file = Some(cert_file.clone());
+        assert_eq!(
+            backend.load_tls(&config),
+            Err(format!("{}: no private key found", cert_file.display()))
+        );
+        for path in [cert_file, key_file, other_cert, other_key] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use std::{cell::Cell, collections::HashSet};
+
+use dashmap::DashMap;
+
+use super::{Backend, PubSubMessage, Subscriber};
+use crate::ClientClass;
+
+// the connection a command runs for, set while the session executes it
+#[derive(Debug, Clone, Copy)]
+pub struct ClientContext {
+    pub id: u64,
+    // record the keys read by the command in the invalidation table
+    pub track_reads: bool,
+}
+
+thread_local! {
+    static CURRENT_CLIENT: Cell<Option<ClientContext>> = const { Cell::new(None) };
+}
+
+// run a command on behalf of a client, commands never await so the context stays on this thread
+pub fn with_client<T>(ctx: ClientContext, f: impl FnOnce() -> T) -> T {
+    let prev = CURRENT_CLIENT.replace(Some(ctx));
+    let ret = f();
+    CURRENT_CLIENT.set(prev);
+    ret
+}
+
+#[derive(Debug, Default)]
+pub struct Tracking {
+    // every connected client, used to resolve REDIRECT targets
+    connections: DashMap<u64, Subscriber>,
+    // clients with tracking enabled
+    clients: DashMap<u64, TrackingClient>,
+    // key -> clients that may have cached it
+    keys: DashMap<Vec<u8>, HashSet<u64>>,
+    // BCAST prefix -> clients, the empty prefix matches every key
+    prefixes: DashMap<String, HashSet<u64>>,
+}
+
+#[derive(Debug)]
+struct TrackingClient {
+    subscriber: Subscriber,
+    redirect: Option<u64>,
+    noloop: bool,
+    // keys this client appears under in the key table, so disabling tracking only visits those
+    keys: HashSet<Vec<u8>>,
+}
+
+impl Backend {
+    pub fn register_client(&self, subscriber: &Subscriber) {
+        self.tracking
+            .connections
+            .insert(subscriber.id(), subscriber.clone());
+    }
+
+    pub fn unregister_client(&self, id: u64) {
+        self.tracking.connections.remove(&id);
+        self.disable_tracking(id);
+    }
+
+    // clients with CLIENT TRACKING on
+    pub fn tracking_clients(&self) -> usize {
+        self.tracking.clients.len()
+    }
+
+    pub fn client_exists(&self, id: u64) -> bool {
+        self.tracking.connections.contains_key(&id)
+    }
+
+    pub fn enable_tracking(
+        &self,
+        subscriber: &Subscriber,
+        redirect: Option<u64>,
+        noloop: bool,
+        prefixes: Option<&[String]>,
+    ) {
+        let id = subscriber.id();
+        // turning tracking on again changes the options, the keys read so far stay tracked
+        self.forget_prefixes(id);
+        if let Some(prefixes) = prefixes {
+            for prefix in prefixes {
+                self.tracking
+                    .prefixes
+                    .entry(prefix.clone())
+                    .or_default()
+                    .insert(id);
+            }
+        }
+        let mut client = self
+            .tracking
+            .clients
+            .entry(id)
+            .or_insert_with(|| TrackingClient {
+                subscriber: subscriber.clone(),
+                redirect,
+                noloop,
+                keys: HashSet::new(),
+            });
+        client.subscriber = subscriber.clone();
+        client.redirect = redirect;
+        client.noloop = noloop;
+    }
+
+    pub fn disable_tracking(&self, id: u64) {
+        let Some((_, client)) = self.tracking.clients.remove(&id) else {
+            return;
+        };
+        self.forget_prefixes(id);
+        // keys read by the client would otherwise stay in the table until they are written
+        for key in client.keys {
+            if let Some(mut clients) = self.tracking.keys.get_mut(&key) {
+                clients.remove(&id);
+            }
+            self.tracking
+                .keys
+                .remove_if(&key, |_, clients| clients.is_empty());
+        }
+    }
+
+    fn forget_prefixes(&self, id: u64) {
+        self.tracking.prefixes.retain(|_, clients| {
+            clients.remove(&id);
+            !clients.is_empty()
+        });
+    }
+
+    // remember that the current client read the key
+    pub(crate) fn track_read(&self, key: &[u8]) {
+        let Some(ctx) = CURRENT_CLIENT.get() else {
+            return;
+        };
+        if !ctx.track_reads {
+            return;
+        }
+        {
+            let Some(mut client) = self.tracking.clients.get_mut(&ctx.id) else {
+                return;
+            };
+            if client.keys.contains(key) {
+                return;
+            }
+            client.keys.insert(key.to_vec());
+        }
+        if let Some(mut clients) = self.tracking.keys.get_mut(key) {
+            clients.insert(ctx.id);
+            return;
+        }
+        self.tracking
+            .keys
+            .entry(key.to_vec())
+            .or_default()
+            .insert(ctx.id);
+    }
+
+    // send an invalidation message to every client caching the modified key
+    pub(crate) fn invalidate(&self, key: &[u8]) {
+        if self.tracking.clients.is_empty() {
+            return;
+        }
+        let mut targets = self
+            .tracking
+            .keys
+            .remove(key)
+            .map(|(_, clients)| clients)
+            .unwrap_or_default();
+        for entry in self.tracking.prefixes.iter() {
+            if key.starts_with(entry.key().as_bytes()) {
+                targets.extend(entry.value().iter().copied());
+            }
+        }
+        let writer = CURRENT_CLIENT.get().map(|ctx| ctx.id);
+        for id in targets {
+            // ids of clients that turned tracking off may linger, they are skipped
+            let (subscriber, redirect, noloop) = {
+                let Some(mut client) = self.tracking.clients.get_mut(&id) else {
+                    continue;
+                };
+                client.keys.remove(key);
+                (client.subscriber.clone(), client.redirect, client.noloop)
+            };
+            if noloop && writer == Some(id) {
+                continue;
+            }
+            let msg = PubSubMessage::Invalidate {
+                keys: Some(vec![key.to_vec()]),
+            };
+            self.deliver_invalidation(&subscriber, redirect, msg);
+        }
+    }
+
+    // pushed to RESP3 clients themselves, or published to the connection they redirect to
+    fn deliver_invalidation(
+        &self,
+        subscriber: &Subscriber,
+        redirect: Option<u64>,
+        msg: PubSubMessage,
+    ) {
+        let normal = self.output_buffer_limit(ClientClass::Normal);
+        let Some(redirect) = redirect else {
+            subscriber.deliver(msg, normal);
+            return;
+        };
+        match self.tracking.connections.get(&redirect) {
+            Some(target) => {
+                target.deliver(msg, self.output_buffer_limit(ClientClass::PubSub));
+            }
+            None => {
+                let msg = PubSubMessage::TrackingRedirBroken { id: redirect };
+                subscriber.deliver(msg, normal);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[tokio::test]
+    async fn test_invalidate_tracked_keys() {
+        let backend = Backend::new();
+        let (reader, mut rx) = backend.subscriber();
+        backend.register_client(&reader);
+        backend.enable_tracking(&reader, None, false, None);
+
+        let ctx = ClientContext {
+            id: reader.id(),
+            track_reads: true,
+        };
+        with_client(ctx, || backend.get(b"a"));
+        backend.set(b"b".to_vec(), RespFrame::Integer(1));
+        backend.set(b"a".to_vec(), RespFrame::Integer(1));
+        assert_eq!(
+            rx.recv().await,
+            Some(PubSubMessage::Invalidate {
+                keys: Some(vec![b"a".to_vec()])
+            })
+        );
+
+        // the key is invalidated once until it is read again
+        backend.set(b"a".to_vec(), RespFrame::Integer(2));
+        backend.disable_tracking(reader.id());
+        assert!(backend.tracking.clients.is_empty());
+    }
+
+    #[test]
+    fn test_keys_are_forgotten_with_the_client() {
+        let backend = Backend::new();
+        let (reader, _rx) = backend.subscriber();
+        let (other, _other_rx) = backend.subscriber();
+        for client in [&reader, &other] {
+            backend.register_client(client);
+            backend.enable_tracking(client, None, false, None);
+        }
+        let read = |client: &Subscriber, key: &[u8]| {
+            let ctx = ClientContext {
+                id: client.id(),
+                track_reads: true,
+            };
+            with_client(ctx, || backend.get(key));
+        };
+        read(&reader, b"a");
+        read(&reader, b"b");
+        read(&other, b"b");
+
+        // the options change, the keys read so far are still tracked
+        backend.enable_tracking(&reader, None, true, None);
+        assert_eq!(backend.tracking.keys.len(), 2);
+
+        // a written key is dropped from the client's set along with the table entry
+        backend.set(b"a".to_vec(), RespFrame::Integer(1));
+        read(&reader, b"c");
+        let keys = |id: u64| backend.tracking.clients.get(&id).unwrap().keys.len();
+        assert_eq!(keys(reader.id()), 2);
+        assert_eq!(keys(other.id()), 1);
+
+        backend.disable_tracking(reader.id());
+        assert_eq!(backend.tracking.keys.len(), 1);
+        assert!(backend.tracking.keys.contains_key(b"b".as_slice()));
+        backend.unregister_client(other.id());
+        assert!(backend.tracking.keys.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bcast_and_noloop() {
+        let backend = Backend::new();
+        let (client, mut rx) = backend.subscriber();
+        backend.register_client(&client);
+        let prefixes = vec!["user:".to_string()];
+        backend.enable_tracking(&client, None, true, Some(&prefixes));
+
+        let ctx = ClientContext {
+            id: client.id(),
+            track_reads: false,
+        };
+        with_client(ctx, || {
+            backend.set(b"user:1".to_vec(), RespFrame::Integer(1))
+        });
+        backend.set(b"other".to_vec(), RespFrame::Integer(1));
+        backend.set(b"user:2".to_vec(), RespFrame::Integer(1));
+        assert_eq!(
+            rx.recv().await,
+            Some(PubSubMessage::Invalidate {
+                keys: Some(vec![b"user:2".to_vec()])
+            })
+        );
+    }
+}
@@ -0,0 +1,399 @@
+use std::{collections::BTreeMap, ops::Bound, str::FromStr};
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum TimeSeriesError {
+    #[error("ERR TSDB: key already exists")]
+    KeyExists,
+    #[error("ERR TSDB: the key does not exist")]
+    KeyNotFound,
+    #[error("ERR TSDB: Timestamp is older than retention")]
+    TooOld,
+    #[error("ERR TSDB: timestamp must be equal to or higher than the maximum existing timestamp")]
+    NotLatest,
+    #[error("ERR TSDB: Error at upsert, update is not supported when DUPLICATE_POLICY is set to BLOCK mode")]
+    DuplicateBlocked,
+    #[error("ERR TSDB: the source key and destination key should be different")]
+    SameKey,
+    #[error("ERR TSDB: the destination key already has a src rule")]
+    DestinationHasSource,
+    #[error("ERR TSDB: the source key is already a destination of a compaction rule")]
+    SourceIsDestination,
+    #[error("ERR TSDB: the destination key has compaction rules")]
+    DestinationHasRules,
+}
+
+pub type Labels = Vec<(String, String)>;
+pub type Samples = Vec<(u64, f64)>;
+
+// duplicate policy decides what happens when a sample is added at an existing timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    #[default]
+    Block,
+    First,
+    Last,
+    Min,
+    Max,
+    Sum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Count,
+    First,
+    Last,
+    StdP,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionRule {
+    pub dest: String,
+    pub aggregation: Aggregation,
+    pub bucket_duration: u64,
+    // start of the bucket that is still open, it is closed by the first sample of a later bucket
+    current_bucket: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TimeSeriesOptions {
+    pub retention: Option<u64>,
+    pub labels: Option<Vec<(String, String)>>,
+    pub duplicate_policy: Option<DuplicatePolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    pub retention: u64,
+    pub labels: Vec<(String, String)>,
+    pub duplicate_policy: DuplicatePolicy,
+    pub(crate) samples: BTreeMap<u64, f64>,
+    pub(crate) rules: Vec<CompactionRule>,
+    pub(crate) source: Option<String>,
+}
+
+// a label filter used by TS.MRANGE: label=value or label!=value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelFilter {
+    Equal(String, String),
+    NotEqual(String, String),
+}
+
+impl TimeSeries {
+    pub fn new(opts: TimeSeriesOptions) -> Self {
+        Self {
+            retention: opts.retention.unwrap_or(0),
+            labels: opts.labels.unwrap_or_default(),
+            duplicate_policy: opts.duplicate_policy.unwrap_or_default(),
+            samples: BTreeMap::new(),
+            rules: Vec::new(),
+            source: None,
+        }
+    }
+
+    pub fn last(&self) -> Option<(u64, f64)> {
+        self.samples.last_key_value().map(|(ts, v)| (*ts, *v))
+    }
+
+    /// Add a sample, returning the samples that closed compaction buckets produced for each rule
+    /// as (destination, bucket start, aggregated value).
+    pub(crate) fn add(
+        &mut self,
+        ts: u64,
+        value: f64,
+        on_duplicate: Option<DuplicatePolicy>,
+    ) -> Result<Vec<(String, u64, f64)>, TimeSeriesError> {
+        if let Some((last_ts, _)) = self.last() {
+            if self.retention > 0 && ts.saturating_add(self.retention) < last_ts {
+                return Err(TimeSeriesError::TooOld);
+            }
+        }
+
+        let policy = on_duplicate.unwrap_or(self.duplicate_policy);
+        match self.samples.get_mut(&ts) {
+            Some(old) => {
+                *old = match policy {
+                    DuplicatePolicy::Block => return Err(TimeSeriesError::DuplicateBlocked),
+                    DuplicatePolicy::First => *old,
+                    DuplicatePolicy::Last => value,
+                    DuplicatePolicy::Min => old.min(value),
+                    DuplicatePolicy::Max => old.max(value),
+                    DuplicatePolicy::Sum => *old + value,
+                };
+            }
+            None => {
+                self.samples.insert(ts, value);
+            }
+        }
+
+        let compacted = self.compact(ts);
+        self.trim();
+        Ok(compacted)
+    }
+
+    // close the open bucket of every rule that the new sample has moved past
+    fn compact(&mut self, ts: u64) -> Vec<(String, u64, f64)> {
+        let mut compacted = Vec::new();
+        for i in 0..self.rules.len() {
+            let rule = &self.rules[i];
+            let bucket = ts - ts % rule.bucket_duration;
+            match rule.current_bucket {
+                Some(current) if bucket > current => {
+                    // the last bucket before u64::MAX has no exclusive end
+                    let end = match current.checked_add(rule.bucket_duration) {
+                        Some(end) => Bound::Excluded(end),
+                        None => Bound::Unbounded,
+                    };
+                    let values: Vec<f64> = self
+                        .samples
+                        .range((Bound::Included(current), end))
+                        .map(|(_, v)| *v)
+                        .collect();
+                    if let Some(v) = rule.aggregation.apply(&values) {
+                        compacted.push((rule.dest.clone(), current, v));
+                    }
+                    self.rules[i].current_bucket = Some(bucket);
+                }
+                Some(_) => {}
+                None => self.rules[i].current_bucket = Some(bucket),
+            }
+        }
+        compacted
+    }
+
+    // drop samples that fell out of the retention window
+    fn trim(&mut self) {
+        if self.retention == 0 {
+            return;
+        }
+        if let Some((last_ts, _)) = self.last() {
+            let min_ts = last_ts.saturating_sub(self.retention);
+            self.samples = self.samples.split_off(&min_ts);
+        }
+    }
+
+    pub fn range(&self, from: u64, to: u64) -> Vec<(u64, f64)> {
+        if from > to {
+            return vec![];
+        }
+        self.samples
+            .range(from..=to)
+            .map(|(k, v)| (*k, *v))
+            .collect()
+    }
+
+    pub fn matches(&self, filters: &[LabelFilter]) -> bool {
+        let label = |name: &str| {
+            self.labels
+                .iter()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.as_str())
+        };
+        filters.iter().all(|f| match f {
+            LabelFilter::Equal(k, v) => label(k) == Some(v.as_str()),
+            LabelFilter::NotEqual(k, v) => label(k) != Some(v.as_str()),
+        })
+    }
+}
+
+impl CompactionRule {
+    pub fn new(dest: String, aggregation: Aggregation, bucket_duration: u64) -> Self {
+        Self {
+            dest,
+            aggregation,
+            bucket_duration,
+            current_bucket: None,
+        }
+    }
+}
+
+impl Aggregation {
+    pub fn apply(&self, values: &[f64]) -> Option<f64> {
+        if values.is_empty() {
+            return None;
+        }
+        let n = values.len() as f64;
+        let sum: f64 = values.iter().sum();
+        let v = match self {
+            Aggregation::Avg => sum / n,
+            Aggregation::Sum => sum,
+            Aggregation::Min => values.iter().copied().fold(f64::INFINITY, f64::min),
+            Aggregation::Max => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            Aggregation::Count => n,
+            Aggregation::First => values[0],
+            Aggregation::Last => values[values.len() - 1],
+            Aggregation::StdP => {
+                let avg = sum / n;
+                (values.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / n).sqrt()
+            }
+        };
+        Some(v)
+    }
+
+    // group samples into buckets aligned to epoch and aggregate each bucket
+    pub fn buckets(&self, samples: &[(u64, f64)], bucket_duration: u64) -> Vec<(u64, f64)> {
+        let mut ret = Vec::new();
+        let mut iter = samples.iter().peekable();
+        while let Some((ts, _)) = iter.peek() {
+            let bucket = ts - ts % bucket_duration;
+            let end = bucket.checked_add(bucket_duration);
+            let mut values = Vec::new();
+            while let Some((ts, v)) = iter.peek() {
+                if end.is_some_and(|end| *ts >= end) {
+                    break;
+                }
+                values.push(*v);
+                iter.next();
+            }
+            if let Some(v) = self.apply(&values) {
+                ret.push((bucket, v));
+            }
+        }
+        ret
+    }
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "block" => Ok(Self::Block),
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            "sum" => Ok(Self::Sum),
+            _ => Err(format!("unknown duplicate policy {}", s)),
+        }
+    }
+}
+
+impl FromStr for Aggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "avg" => Ok(Self::Avg),
+            "sum" => Ok(Self::Sum),
+            "min" => Ok(Self::Min),
+            "max" => Ok(Self::Max),
+            "count" => Ok(Self::Count),
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            "std.p" => Ok(Self::StdP),
+            _ => Err(format!("unknown aggregation type {}", s)),
+        }
+    }
+}
+
+impl FromStr for LabelFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((k, v)) = s.split_once("!=") {
+            return Ok(Self::NotEqual(k.to_string(), v.to_string()));
+        }
+        match s.split_once('=') {
+            Some((k, v)) => Ok(Self::Equal(k.to_string(), v.to_string())),
+            None => Err(format!("invalid filter {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_policy() {
+        let mut ts = TimeSeries::new(TimeSeriesOptions::default());
+        ts.add(1, 1.0, None).unwrap();
+        assert_eq!(ts.add(1, 2.0, None), Err(TimeSeriesError::DuplicateBlocked));
+        ts.add(1, 2.0, Some(DuplicatePolicy::Sum)).unwrap();
+        assert_eq!(ts.last(), Some((1, 3.0)));
+    }
+
+    #[test]
+    fn test_retention() {
+        let mut ts = TimeSeries::new(TimeSeriesOptions {
+            retention: Some(10),
+            ..Default::default()
+        });
+        ts.add(1, 1.0, None).unwrap();
+        ts.add(20, 2.0, None).unwrap();
+        assert_eq!(ts.range(0, u64::MAX), vec![(20, 2.0)]);
+        assert_eq!(ts.add(5, 1.0, None), Err(TimeSeriesError::TooOld));
+    }
+
+    #[test]
+    fn test_aggregation_buckets() {
+        let samples = vec![(1, 1.0), (5, 3.0), (10, 4.0), (25, 8.0)];
+        assert_eq!(
+            Aggregation::Avg.buckets(&samples, 10),
+            vec![(0, 2.0), (10, 4.0), (20, 8.0)]
+        );
+        assert_eq!(Aggregation::Count.buckets(&samples, 10)[0], (0, 2.0));
+        assert_eq!(Aggregation::StdP.apply(&[1.0, 3.0]), Some(1.0));
+    }
+
+    #[test]
+    fn test_buckets_near_max_timestamp() {
+        let samples = vec![(u64::MAX - 1, 1.0), (u64::MAX, 2.0)];
+        assert_eq!(
+            Aggregation::Sum.buckets(&samples, 10),
+            vec![(u64::MAX - u64::MAX % 10, 3.0)]
+        );
+        assert_eq!(
+            Aggregation::Sum.buckets(&samples, u64::MAX),
+            vec![(0, 1.0), (u64::MAX, 2.0)]
+        );
+    }
+
+    #[test]
+    fn test_compaction_closes_buckets() {
+        let mut ts = TimeSeries::new(TimeSeriesOptions::default());
+        ts.rules.push(CompactionRule::new(
+            "dest".to_string(),
+            Aggregation::Sum,
+            10,
+        ));
+        assert!(ts.add(1, 1.0, None).unwrap().is_empty());
+        assert!(ts.add(2, 2.0, None).unwrap().is_empty());
+        assert_eq!(
+            ts.add(12, 5.0, None).unwrap(),
+            vec![("dest".to_string(), 0, 3.0)]
+        );
+    }
+
+    #[test]
+    fn test_compaction_near_max_timestamp() {
+        let mut ts = TimeSeries::new(TimeSeriesOptions::default());
+        ts.rules.push(CompactionRule::new(
+            "dest".to_string(),
+            Aggregation::Sum,
+            u64::MAX,
+        ));
+        assert!(ts.add(u64::MAX - 1, 1.0, None).unwrap().is_empty());
+        assert_eq!(
+            ts.add(u64::MAX, 2.0, None).unwrap(),
+            vec![("dest".to_string(), 0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn test_label_filter() {
+        let ts = TimeSeries::new(TimeSeriesOptions {
+            labels: Some(vec![("area".to_string(), "east".to_string())]),
+            ..Default::default()
+        });
+        assert!(ts.matches(&["area=east".parse().unwrap()]));
+        assert!(!ts.matches(&["area!=east".parse().unwrap()]));
+        assert!(!ts.matches(&["sensor=1".parse().unwrap()]));
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use super::Backend;
+
+impl Backend {
+    // a command run under the shared guard never interleaves with a running EXEC
+    pub fn shared_guard(&self) -> RwLockReadGuard<'_, ()> {
+        self.txn_lock.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn exclusive_guard(&self) -> RwLockWriteGuard<'_, ()> {
+        self.txn_lock.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // start watching a key, returning its current modification count
+    pub fn watch(&self, key: &[u8]) -> u64 {
+        let mut entry = self.watches.entry(key.to_vec()).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1
+    }
+
+    pub fn unwatch(&self, key: &[u8]) {
+        self.watches.remove_if_mut(key, |_, (watchers, _)| {
+            *watchers -= 1;
+            *watchers == 0
+        });
+    }
+
+    pub fn watched_version(&self, key: &[u8]) -> u64 {
+        self.watches.get(key).map(|v| v.1).unwrap_or_default()
+    }
+
+    // record a modification of the key, only watched keys are tracked
+    pub(crate) fn touch(&self, key: &[u8]) {
+        if let Some(mut entry) = self.watches.get_mut(key) {
+            entry.1 += 1;
+        }
+        self.invalidate(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Backend, RespFrame, TimeSeriesOptions};
+
+    #[test]
+    fn test_watch_tracks_modifications() {
+        let backend = Backend::new();
+        let version = backend.watch(b"key");
+        backend.set(b"other".to_vec(), RespFrame::Integer(1));
+        assert_eq!(backend.watched_version(b"key"), version);
+        backend.set(b"key".to_vec(), RespFrame::Integer(1));
+        assert_ne!(backend.watched_version(b"key"), version);
+
+        backend.unwatch(b"key");
+        assert!(backend.watches.is_empty());
+    }
+
+    #[test]
+    fn test_watch_sees_time_series_samples() {
+        let backend = Backend::new();
+        let version = backend.watch(b"temp");
+        let opts = TimeSeriesOptions::default();
+        backend
+            .ts_add("temp".to_string(), 1, 1.0, opts, None)
+            .unwrap();
+        assert_ne!(backend.watched_version(b"temp"), version);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{Backend, CommandError, CommandExecutor, RespFrame, SimpleError};
+
+use super::{extract_strings, parse_arg, validate_dyn_command};
+
+// CLIENT subcommands act on the connection and are handled by the session
+#[derive(Debug)]
+pub enum Client {
+    Id,
+    Tracking(Option<TrackingOptions>),
+    Caching(bool),
+    GetRedir,
+    SetName(String),
+    GetName,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TrackingOptions {
+    pub redirect: Option<u64>,
+    pub bcast: bool,
+    pub prefixes: Vec<String>,
+    pub optin: bool,
+    pub optout: bool,
+    pub noloop: bool,
+}
+
+impl CommandExecutor for Client {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        SimpleError::new("ERR CLIENT is not allowed in this context").into()
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Client {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["client"], 1)?;
+        let args = extract_strings(value, 1)?;
+        let sub = args[0].to_ascii_lowercase();
+        match (sub.as_str(), args.len()) {
+            ("id", 1) => Ok(Client::Id),
+            ("getredir", 1) => Ok(Client::GetRedir),
+            ("getname", 1) => Ok(Client::GetName),
+            ("setname", 2) => Ok(Client::SetName(args[1].clone())),
+            ("caching", 2) => match args[1].to_ascii_lowercase().as_str() {
+                "yes" => Ok(Client::Caching(true)),
+                "no" => Ok(Client::Caching(false)),
+                _ => Err(CommandError::InvalidArgument(
+                    "CLIENT CACHING argument must be yes or no".to_string(),
+                )),
+            },
+            ("tracking", n) if n >= 2 => match args[1].to_ascii_lowercase().as_str() {
+                "on" => Ok(Client::Tracking(Some(TrackingOptions::parse(&args[2..])?))),
+                "off" => Ok(Client::Tracking(None)),
+                _ => Err(CommandError::InvalidArgument(
+                    "CLIENT TRACKING argument must be on or off".to_string(),
+                )),
+            },
+            _ => Err(CommandError::InvalidArgument(format!(
+                "unknown CLIENT subcommand or wrong number of arguments for '{}'",
+                args[0]
+            ))),
+        }
+    }
+}
+
+impl TrackingOptions {
+    fn parse(args: &[String]) -> Result<Self, CommandError> {
+        let mut opts = TrackingOptions::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "redirect" => opts.redirect = Some(parse_arg(iter.next(), "REDIRECT")?),
+                "prefix" => opts.prefixes.push(parse_arg(iter.next(), "PREFIX")?),
+                "bcast" => opts.bcast = true,
+                "optin" => opts.optin = true,
+                "optout" => opts.optout = true,
+                "noloop" => opts.noloop = true,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "unknown CLIENT TRACKING option '{}'",
+                        arg
+                    )))
+                }
+            }
+        }
+        if !opts.bcast && !opts.prefixes.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "PREFIX option requires BCAST mode to be enabled".to_string(),
+            ));
+        }
+        if opts.optin && opts.optout {
+            return Err(CommandError::InvalidArgument(
+                "You can't use both OPTIN and OPTOUT".to_string(),
+            ));
+        }
+        if opts.bcast && (opts.optin || opts.optout) {
+            return Err(CommandError::InvalidArgument(
+                "OPTIN and OPTOUT are not compatible with BCAST".to_string(),
+            ));
+        }
+        Ok(opts)
+    }
+
+    // overlapping prefixes would send the same invalidation twice
+    pub fn overlapping_prefixes(&self) -> Option<(&str, &str)> {
+        for (i, a) in self.prefixes.iter().enumerate() {
+            for b in &self.prefixes[i + 1..] {
+                if a.starts_with(b.as_str()) || b.starts_with(a.as_str()) {
+                    return Some((a, b));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespArray, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn client(args: &[&str]) -> Result<Client, CommandError> {
+        let frames = std::iter::once("client")
+            .chain(args.iter().copied())
+            .map(|a| BulkString::from(a).into())
+            .collect::<Vec<RespFrame>>();
+        Client::try_from(frames)
+    }
+
+    #[test]
+    fn test_client_tracking_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$6\r\nclient\r\n$8\r\ntracking\r\n$2\r\non\r\n$8\r\nREDIRECT\r\n$1\r\n7\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Client = frame.0.unwrap().try_into()?;
+        let Client::Tracking(Some(opts)) = result else {
+            panic!("expected CLIENT TRACKING ON");
+        };
+        assert_eq!(opts.redirect, Some(7));
+
+        let Client::Tracking(Some(opts)) = client(&[
+            "tracking", "on", "bcast", "prefix", "a", "prefix", "ab", "noloop",
+        ])?
+        else {
+            panic!("expected CLIENT TRACKING ON");
+        };
+        assert!(opts.bcast && opts.noloop);
+        assert_eq!(opts.overlapping_prefixes(), Some(("a", "ab")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_tracking_invalid_options() {
+        assert!(client(&["tracking", "on", "prefix", "a"]).is_err());
+        assert!(client(&["tracking", "on", "optin", "optout"]).is_err());
+        assert!(client(&["tracking", "on", "bcast", "optin"]).is_err());
+        assert!(client(&["caching", "maybe"]).is_err());
+        assert!(matches!(
+            client(&["caching", "YES"]),
+            Ok(Client::Caching(true))
+        ));
+        assert!(matches!(
+            client(&["tracking", "off"]),
+            Ok(Client::Tracking(None))
+        ));
+    }
+}
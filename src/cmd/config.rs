@@ -0,0 +1,157 @@
+use crate::{Backend, BulkString, CommandError, CommandExecutor, RespFrame, RespMap, SimpleError};
+
+use super::{extract_strings, validate_dyn_command, RESP_OK};
+
+// CONFIG GET pattern [pattern ...] | SET parameter value [parameter value ...] | REWRITE | RESETSTAT
+#[derive(Debug)]
+pub enum ConfigCommand {
+    Get(Vec<String>),
+    Set(Vec<(String, String)>),
+    Rewrite,
+    ResetStat,
+}
+
+impl CommandExecutor for ConfigCommand {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self {
+            ConfigCommand::Get(patterns) => {
+                let mut map = RespMap::new();
+                for (name, value) in backend.config_get(&patterns) {
+                    map.insert(name.as_bytes().to_vec(), BulkString::from(value).into());
+                }
+                return map.into();
+            }
+            ConfigCommand::Set(pairs) => backend.config_set(&pairs),
+            ConfigCommand::Rewrite => backend.config_rewrite(),
+            ConfigCommand::ResetStat => {
+                backend.reset_stats();
+                Ok(())
+            }
+        };
+        match ret {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => SimpleError::new(format!("ERR {}", e)).into(),
+        }
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for ConfigCommand {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["config"], 1)?;
+        let mut args = extract_strings(value, 1)?;
+        let sub = args.remove(0).to_ascii_lowercase();
+        match (sub.as_str(), args.len()) {
+            ("get", n) if n >= 1 => Ok(ConfigCommand::Get(args)),
+            ("set", n) if n >= 2 && n % 2 == 0 => {
+                let mut iter = args.into_iter();
+                let mut pairs = Vec::new();
+                while let (Some(name), Some(value)) = (iter.next(), iter.next()) {
+                    pairs.push((name, value));
+                }
+                Ok(ConfigCommand::Set(pairs))
+            }
+            ("rewrite", 0) => Ok(ConfigCommand::Rewrite),
+            ("resetstat", 0) => Ok(ConfigCommand::ResetStat),
+            _ => Err(CommandError::InvalidArgument(format!(
+                "unknown CONFIG subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn config(args: &[&str]) -> Result<ConfigCommand, CommandError> {
+        let frames = std::iter::once("config")
+            .chain(args.iter().copied())
+            .map(|a| BulkString::from(a).into())
+            .collect::<Vec<RespFrame>>();
+        ConfigCommand::try_from(frames)
+    }
+
+    fn get(backend: &Backend, pattern: &str) -> RespFrame {
+        config(&["get", pattern]).unwrap().execute(backend)
+    }
+
+    #[test]
+    fn test_config_get_and_set() -> Result<()> {
+        let backend = Backend::new();
+        let mut expected = RespMap::new();
+        expected.insert(b"maxclients".to_vec(), BulkString::from("10000").into());
+        expected.insert(b"maxmemory".to_vec(), BulkString::from("0").into());
+        assert_eq!(get(&backend, "MAX*"), expected.into());
+
+        let ret = config(&["set", "maxclients", "50", "save", "900 1 300 10"])?.execute(&backend);
+        assert_eq!(ret, RESP_OK.clone());
+        assert_eq!(backend.config().maxclients, 50);
+        assert_eq!(backend.config().save, vec![(900, 1), (300, 10)]);
+
+        // limits and event classes take effect right away
+        config(&["set", "proto-max-bulk-len", "1mb"])?.execute(&backend);
+        assert_eq!(backend.resp_limits().max_bulk_len, 1024 * 1024);
+        config(&["set", "notify-keyspace-events", "KEA"])?.execute(&backend);
+        assert_eq!(backend.notify_keyspace_events(), "KEA".parse().unwrap());
+        let mut expected = RespMap::new();
+        expected.insert(
+            b"notify-keyspace-events".to_vec(),
+            BulkString::from("AKE").into(),
+        );
+        assert_eq!(get(&backend, "notify-keyspace-events"), expected.into());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_set_is_atomic() -> Result<()> {
+        let backend = Backend::new();
+        let ret = config(&["set", "maxclients", "50", "timeout", "soon"])?.execute(&backend);
+        assert_eq!(
+            ret,
+            SimpleError::new(
+                "ERR CONFIG SET failed (possibly related to argument 'timeout') - Invalid timeout value"
+            )
+            .into()
+        );
+        assert_eq!(backend.config().maxclients, 10000);
+
+        let ret = config(&["set", "port", "7000"])?.execute(&backend);
+        assert_eq!(
+            ret,
+            SimpleError::new(
+                "ERR CONFIG SET failed (possibly related to argument 'port') - can't set immutable config"
+            )
+            .into()
+        );
+        let ret = config(&["set", "timeout", "1", "TIMEOUT", "2"])?.execute(&backend);
+        assert_eq!(
+            ret,
+            SimpleError::new("ERR Duplicate parameter - TIMEOUT").into()
+        );
+        let ret = config(&["set", "no-such-param", "1"])?.execute(&backend);
+        assert_eq!(
+            ret,
+            SimpleError::new(
+                "ERR Unknown option or number of arguments for CONFIG SET - 'no-such-param'"
+            )
+            .into()
+        );
+        assert!(config(&["set", "timeout"]).is_err());
+        assert!(config(&["rewrite", "now"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_resetstat() -> Result<()> {
+        let backend = Backend::new();
+        backend.stats().record_command();
+        assert_eq!(backend.stats().total_commands_processed(), 1);
+        assert_eq!(config(&["resetstat"])?.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.stats().total_commands_processed(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{BulkString, CommandError, CommandExecutor, RespFrame, ShutdownOptions, SimpleError};
+
+use super::{extract_args, extract_strings, validate_dyn_command, RESP_OK};
+
+#[derive(Debug)]
+pub struct Ping {
+    pub(crate) message: Option<BulkString>,
+}
+
+// QUIT replies OK, the connection is closed once the reply is written
+#[derive(Debug)]
+pub struct Quit;
+
+// SHUTDOWN [NOSAVE|SAVE] [NOW] [FORCE] [ABORT], the session closes the connection once the
+// server is stopping
+#[derive(Debug)]
+pub struct Shutdown {
+    pub(crate) options: ShutdownOptions,
+    // cancel a shutdown in progress
+    pub(crate) abort: bool,
+}
+
+// HELLO switches the connection protocol and is handled by the session
+#[derive(Debug)]
+pub struct Hello {
+    pub(crate) protover: Option<String>,
+    pub(crate) auth: Option<(String, String)>,
+    pub(crate) setname: Option<String>,
+}
+
+// AUTH [username] password, checked by the session against requirepass
+#[derive(Debug)]
+pub struct Auth {
+    pub(crate) username: Option<String>,
+    pub(crate) password: String,
+}
+
+impl CommandExecutor for Ping {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        match self.message {
+            Some(message) => message.into(),
+            None => "PONG".into(),
+        }
+    }
+}
+
+impl CommandExecutor for Quit {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for Shutdown {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR SHUTDOWN is not allowed in this context").into()
+    }
+}
+
+impl CommandExecutor for Hello {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR HELLO is not allowed in this context").into()
+    }
+}
+
+impl CommandExecutor for Auth {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR AUTH is not allowed in this context").into()
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Auth {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["auth"], 1)?;
+        let mut args = extract_strings(value, 1)?.into_iter();
+        match (args.next(), args.next(), args.next()) {
+            (Some(password), None, None) => Ok(Auth {
+                username: None,
+                password,
+            }),
+            (Some(username), Some(password), None) => Ok(Auth {
+                username: Some(username),
+                password,
+            }),
+            _ => Err(CommandError::InvalidArgument(
+                "AUTH command must have at most 2 arguments".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Hello {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["hello"], 0)?;
+        let args = extract_strings(value, 1)?;
+        let mut iter = args.into_iter();
+        let mut hello = Hello {
+            protover: iter.next(),
+            auth: None,
+            setname: None,
+        };
+        while let Some(arg) = iter.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "auth" => match (iter.next(), iter.next()) {
+                    (Some(user), Some(pass)) => hello.auth = Some((user, pass)),
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "HELLO AUTH requires a username and a password".to_string(),
+                        ))
+                    }
+                },
+                "setname" => match iter.next() {
+                    Some(name) => hello.setname = Some(name),
+                    None => {
+                        return Err(CommandError::InvalidArgument(
+                            "HELLO SETNAME requires a name".to_string(),
+                        ))
+                    }
+                },
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "syntax error in HELLO option '{}'",
+                        arg
+                    )))
+                }
+            }
+        }
+        Ok(hello)
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Ping {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ping"], 0)?;
+        if value.len() > 2 {
+            return Err(CommandError::InvalidArgument(
+                "PING command must have at most 1 argument".to_string(),
+            ));
+        }
+        let mut args = extract_args(value, 1)?.into_iter();
+
+        match args.next() {
+            Some(RespFrame::BulkString(message)) => Ok(Self {
+                message: Some(message),
+            }),
+            None => Ok(Self { message: None }),
+            _ => Err(CommandError::InvalidArgument(
+                "PING command must have a BulkString argument".to_string(),
+            )),
+        }
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Shutdown {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["shutdown"], 0)?;
+        let mut shutdown = Shutdown {
+            options: ShutdownOptions::default(),
+            abort: false,
+        };
+        for arg in extract_strings(value, 1)? {
+            match arg.to_ascii_lowercase().as_str() {
+                "save" if shutdown.options.save.is_none() => shutdown.options.save = Some(true),
+                "nosave" if shutdown.options.save.is_none() => shutdown.options.save = Some(false),
+                "now" => shutdown.options.now = true,
+                "force" => shutdown.options.force = true,
+                "abort" => shutdown.abort = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        // ABORT takes no other option
+        if shutdown.abort && shutdown.options != ShutdownOptions::default() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(shutdown)
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Quit {
+    type Error = CommandError;
+
+    fn try_from(_value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        // like redis, QUIT ignores its arguments
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ping_execute() {
+        let backend = crate::Backend::new();
+        let ping = Ping { message: None };
+        assert_eq!(ping.execute(&backend), "PONG".into());
+        let ping = Ping {
+            message: Some("hello".into()),
+        };
+        assert_eq!(ping.execute(&backend), b"hello".into());
+    }
+
+    #[test]
+    fn test_hello_from_resp_array() -> anyhow::Result<()> {
+        let frames = ["hello", "3", "AUTH", "default", "secret", "SETNAME", "app"]
+            .into_iter()
+            .map(|a| BulkString::from(a).into())
+            .collect::<Vec<RespFrame>>();
+        let hello = Hello::try_from(frames)?;
+        assert_eq!(hello.protover.as_deref(), Some("3"));
+        assert_eq!(
+            hello.auth,
+            Some(("default".to_string(), "secret".to_string()))
+        );
+        assert_eq!(hello.setname.as_deref(), Some("app"));
+
+        let frames = ["hello", "3", "AUTH", "default"]
+            .into_iter()
+            .map(|a| BulkString::from(a).into())
+            .collect::<Vec<RespFrame>>();
+        assert!(Hello::try_from(frames).is_err());
+        Ok(())
+    }
+}
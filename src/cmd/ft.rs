@@ -0,0 +1,647 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    frame_to_string, Backend, BulkString, CommandError, CommandExecutor, FieldSchema, FieldType,
+    IndexSchema, Query, RespArray, RespFrame, SearchError, SimpleError,
+};
+
+use super::{extract_strings, parse_arg, validate_command, validate_dyn_command, RESP_OK};
+
+const DEFAULT_COUNT_ALIAS: &str = "__generated_aliascount";
+
+#[derive(Debug)]
+pub struct FtCreate {
+    index: String,
+    schema: IndexSchema,
+}
+
+#[derive(Debug)]
+pub struct FtSearch {
+    index: String,
+    query: Query,
+    no_content: bool,
+    return_fields: Option<Vec<String>>,
+    sort_by: Option<(String, bool)>,
+    offset: usize,
+    num: usize,
+}
+
+#[derive(Debug)]
+pub struct FtAggregate {
+    index: String,
+    query: Query,
+    group_by: Vec<String>,
+    // aliases of the COUNT reducers
+    counts: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct FtDropIndex {
+    index: String,
+    delete_docs: bool,
+}
+
+#[derive(Debug)]
+pub struct FtInfo {
+    index: String,
+}
+
+impl CommandExecutor for FtCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ft_create(self.index, self.schema) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => search_error(e),
+        }
+    }
+}
+
+impl CommandExecutor for FtSearch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let sort_by = self.sort_by.as_ref().map(|(f, asc)| (f.as_str(), *asc));
+        let keys = match backend.ft_search(&self.index, &self.query, sort_by) {
+            Ok(keys) => keys,
+            Err(e) => return search_error(e),
+        };
+
+        let mut ret = vec![RespFrame::Integer(keys.len() as i64)];
+        for key in keys.into_iter().skip(self.offset).take(self.num) {
+            let content = if self.no_content {
+                None
+            } else {
+                Some(self.content(backend, &key))
+            };
+            ret.push(BulkString::from(key).into());
+            if let Some(content) = content {
+                ret.push(content);
+            }
+        }
+        RespArray::new(ret).into()
+    }
+}
+
+impl FtSearch {
+    // the hash fields of a document, sorted by name or in RETURN order
+    fn content(&self, backend: &Backend, key: &str) -> RespFrame {
+        let mut fields = match backend.hgetall(key.as_bytes()) {
+            Some(hmap) => hmap.into_iter().collect::<BTreeMap<_, _>>(),
+            None => BTreeMap::new(),
+        };
+        let fields = match &self.return_fields {
+            Some(names) => names
+                .iter()
+                .filter_map(|name| {
+                    fields
+                        .remove(name.as_bytes())
+                        .map(|v| (name.clone().into_bytes(), v))
+                })
+                .collect::<Vec<_>>(),
+            None => fields.into_iter().collect(),
+        };
+        let ret = fields
+            .into_iter()
+            .flat_map(|(k, v)| [BulkString::new(k).into(), v])
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for FtAggregate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let keys = match backend.ft_search(&self.index, &self.query, None) {
+            Ok(keys) => keys,
+            Err(e) => return search_error(e),
+        };
+
+        let mut groups: BTreeMap<Vec<Option<String>>, usize> = BTreeMap::new();
+        for key in keys {
+            let group = self
+                .group_by
+                .iter()
+                .map(|field| {
+                    backend
+                        .hget(key.as_bytes(), field.as_bytes())
+                        .as_ref()
+                        .and_then(frame_to_string)
+                })
+                .collect();
+            *groups.entry(group).or_default() += 1;
+        }
+
+        let mut ret = vec![RespFrame::Integer(groups.len() as i64)];
+        for (values, count) in groups {
+            let mut row = Vec::new();
+            for (field, value) in self.group_by.iter().zip(values) {
+                row.push(BulkString::from(field.as_str()).into());
+                row.push(match value {
+                    Some(v) => BulkString::from(v).into(),
+                    None => BulkString(None).into(),
+                });
+            }
+            for alias in &self.counts {
+                row.push(BulkString::from(alias.as_str()).into());
+                row.push(BulkString::from(count.to_string()).into());
+            }
+            ret.push(RespArray::new(row).into());
+        }
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for FtDropIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ft_dropindex(&self.index, self.delete_docs) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => search_error(e),
+        }
+    }
+}
+
+impl CommandExecutor for FtInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (schema, num_docs) = match backend.ft_info(&self.index) {
+            Ok(info) => info,
+            Err(e) => return search_error(e),
+        };
+        let prefixes = schema
+            .prefixes
+            .into_iter()
+            .map(|p| BulkString::from(p).into())
+            .collect::<Vec<RespFrame>>();
+        let attributes = schema
+            .fields
+            .into_iter()
+            .map(|f| {
+                let mut attr: Vec<RespFrame> = vec![
+                    BulkString::from("identifier").into(),
+                    BulkString::from(f.name.as_str()).into(),
+                    BulkString::from("attribute").into(),
+                    BulkString::from(f.name).into(),
+                    BulkString::from("type").into(),
+                ];
+                match f.field_type {
+                    FieldType::Text => attr.push(BulkString::from("TEXT").into()),
+                    FieldType::Numeric => attr.push(BulkString::from("NUMERIC").into()),
+                    FieldType::Tag(sep) => {
+                        attr.push(BulkString::from("TAG").into());
+                        attr.push(BulkString::from("SEPARATOR").into());
+                        attr.push(BulkString::from(sep.to_string()).into());
+                    }
+                }
+                if f.sortable {
+                    attr.push(BulkString::from("SORTABLE").into());
+                }
+                RespArray::new(attr).into()
+            })
+            .collect::<Vec<RespFrame>>();
+
+        RespArray::new([
+            BulkString::from("index_name").into(),
+            BulkString::from(self.index).into(),
+            BulkString::from("index_definition").into(),
+            RespArray::new([
+                BulkString::from("key_type").into(),
+                BulkString::from("HASH").into(),
+                BulkString::from("prefixes").into(),
+                RespArray::new(prefixes).into(),
+            ])
+            .into(),
+            BulkString::from("attributes").into(),
+            RespArray::new(attributes).into(),
+            BulkString::from("num_docs").into(),
+            RespFrame::Integer(num_docs as i64),
+        ])
+        .into()
+    }
+}
+
+// FT.CREATE index [ON HASH] [PREFIX count prefix ...] SCHEMA field type [options] ...
+impl TryFrom<Vec<RespFrame>> for FtCreate {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ft.create"], 4)?;
+        let args = extract_strings(value, 1)?;
+        let mut schema = IndexSchema::default();
+        let mut iter = args[1..].iter().peekable();
+        while let Some(arg) = iter.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "on" => {
+                    let kind = parse_arg::<String>(iter.next(), "key type")?;
+                    if !kind.eq_ignore_ascii_case("hash") {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "unsupported key type {}",
+                            kind
+                        )));
+                    }
+                }
+                "prefix" => {
+                    let n: usize = parse_arg(iter.next(), "prefix count")?;
+                    for _ in 0..n {
+                        schema.prefixes.push(parse_arg(iter.next(), "prefix")?);
+                    }
+                }
+                "schema" => break,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "unknown argument {}",
+                        arg
+                    )))
+                }
+            }
+        }
+
+        while let Some(name) = iter.next() {
+            let kind = parse_arg::<String>(iter.next(), "field type")?;
+            let mut field_type = match kind.to_ascii_lowercase().as_str() {
+                "text" => FieldType::Text,
+                "tag" => FieldType::Tag(','),
+                "numeric" => FieldType::Numeric,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "unknown field type {}",
+                        kind
+                    )))
+                }
+            };
+            let mut sortable = false;
+            while let Some(opt) = iter.peek() {
+                match opt.to_ascii_lowercase().as_str() {
+                    "sortable" => sortable = true,
+                    "nostem" => {}
+                    "weight" => {
+                        iter.next();
+                        parse_arg::<f64>(iter.peek().copied(), "weight")?;
+                    }
+                    "separator" => {
+                        if !matches!(field_type, FieldType::Tag(_)) {
+                            return Err(CommandError::InvalidArgument(format!(
+                                "SEPARATOR is only valid for TAG fields, got {}",
+                                kind
+                            )));
+                        }
+                        iter.next();
+                        let sep = parse_arg::<char>(iter.peek().copied(), "separator")?;
+                        field_type = FieldType::Tag(sep);
+                    }
+                    _ => break,
+                }
+                iter.next();
+            }
+            schema.fields.push(FieldSchema {
+                name: name.clone(),
+                field_type,
+                sortable,
+            });
+        }
+
+        if schema.fields.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "FT.CREATE command must have a SCHEMA with at least one field".to_string(),
+            ));
+        }
+        Ok(Self {
+            index: args[0].clone(),
+            schema,
+        })
+    }
+}
+
+// FT.SEARCH index query [NOCONTENT] [RETURN count field ...] [SORTBY field [ASC|DESC]] [LIMIT offset num]
+impl TryFrom<Vec<RespFrame>> for FtSearch {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ft.search"], 2)?;
+        let args = extract_strings(value, 1)?;
+        let mut cmd = Self {
+            index: args[0].clone(),
+            query: parse_query(&args[1])?,
+            no_content: false,
+            return_fields: None,
+            sort_by: None,
+            offset: 0,
+            num: 10,
+        };
+        let mut iter = args[2..].iter().peekable();
+        while let Some(arg) = iter.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "nocontent" => cmd.no_content = true,
+                "return" => {
+                    let n: usize = parse_arg(iter.next(), "return count")?;
+                    // the count comes from the client, only the fields actually sent are kept
+                    let mut fields = Vec::new();
+                    for _ in 0..n {
+                        fields.push(parse_arg(iter.next(), "return field")?);
+                    }
+                    cmd.return_fields = Some(fields);
+                }
+                "sortby" => {
+                    let field = parse_arg::<String>(iter.next(), "sort field")?;
+                    let mut asc = true;
+                    if let Some(order) = iter.peek() {
+                        if order.eq_ignore_ascii_case("desc") {
+                            asc = false;
+                            iter.next();
+                        } else if order.eq_ignore_ascii_case("asc") {
+                            iter.next();
+                        }
+                    }
+                    cmd.sort_by = Some((field.trim_start_matches('@').to_string(), asc));
+                }
+                "limit" => {
+                    cmd.offset = parse_arg(iter.next(), "limit offset")?;
+                    cmd.num = parse_arg(iter.next(), "limit num")?;
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "unknown argument {}",
+                        arg
+                    )))
+                }
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+// FT.AGGREGATE index query [GROUPBY count @field ... [REDUCE COUNT 0 [AS name]] ...]
+impl TryFrom<Vec<RespFrame>> for FtAggregate {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ft.aggregate"], 2)?;
+        let args = extract_strings(value, 1)?;
+        let mut group_by = Vec::new();
+        let mut counts = Vec::new();
+        let mut iter = args[2..].iter().peekable();
+        while let Some(arg) = iter.next() {
+            match arg.to_ascii_lowercase().as_str() {
+                "groupby" => {
+                    let n: usize = parse_arg(iter.next(), "groupby count")?;
+                    for _ in 0..n {
+                        let field = parse_arg::<String>(iter.next(), "groupby field")?;
+                        group_by.push(field.trim_start_matches('@').to_string());
+                    }
+                }
+                "reduce" => {
+                    let reducer = parse_arg::<String>(iter.next(), "reducer")?;
+                    if !reducer.eq_ignore_ascii_case("count") {
+                        return Err(CommandError::InvalidArgument(format!(
+                            "unsupported reducer {}",
+                            reducer
+                        )));
+                    }
+                    if parse_arg::<usize>(iter.next(), "reducer argument count")? != 0 {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT reducer takes no arguments".to_string(),
+                        ));
+                    }
+                    let mut alias = DEFAULT_COUNT_ALIAS.to_string();
+                    if iter.peek().is_some_and(|v| v.eq_ignore_ascii_case("as")) {
+                        iter.next();
+                        alias = parse_arg(iter.next(), "alias")?;
+                    }
+                    counts.push(alias);
+                }
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "unknown argument {}",
+                        arg
+                    )))
+                }
+            }
+        }
+        Ok(Self {
+            index: args[0].clone(),
+            query: parse_query(&args[1])?,
+            group_by,
+            counts,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for FtDropIndex {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ft.dropindex"], 1)?;
+        let args = extract_strings(value, 1)?;
+        let delete_docs = match args.get(1) {
+            Some(v) if v.eq_ignore_ascii_case("dd") && args.len() == 2 => true,
+            None => false,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "FT.DROPINDEX command only accepts the DD option".to_string(),
+                ))
+            }
+        };
+        Ok(Self {
+            index: args[0].clone(),
+            delete_docs,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for FtInfo {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.info"], 1)?;
+        let mut args = extract_strings(value, 1)?;
+        Ok(Self {
+            index: args.remove(0),
+        })
+    }
+}
+
+fn parse_query(query: &str) -> Result<Query, CommandError> {
+    Query::parse(query).map_err(|e| CommandError::InvalidArgument(e.to_string()))
+}
+
+fn search_error(e: SearchError) -> RespFrame {
+    SimpleError::new(e.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cmd::hset::HSet, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn setup() -> Backend {
+        let backend = Backend::new();
+        for (key, title, tags, price) in [
+            ("doc:1", "red shoes", "sale,new", "10"),
+            ("doc:2", "blue shoes", "new", "30"),
+            ("doc:3", "red hat", "sale", "20"),
+            ("other:1", "red shoes", "sale", "5"),
+        ] {
+            for (field, value) in [("title", title), ("tags", tags), ("price", price)] {
+                HSet {
+                    key: key.as_bytes().to_vec(),
+                    field: field.as_bytes().to_vec(),
+                    value: BulkString::from(value).into(),
+                }
+                .execute(&backend);
+            }
+        }
+        backend
+    }
+
+    #[test]
+    fn test_ft_create_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*14\r\n$9\r\nft.create\r\n$3\r\nidx\r\n$2\r\nON\r\n$4\r\nHASH\r\n$6\r\nPREFIX\r\n$1\r\n1\r\n$4\r\ndoc:\r\n$6\r\nSCHEMA\r\n$5\r\ntitle\r\n$4\r\nTEXT\r\n$4\r\ntags\r\n$3\r\nTAG\r\n$9\r\nSEPARATOR\r\n$1\r\n;\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: FtCreate = frame.0.unwrap().try_into()?;
+        assert_eq!(result.index, "idx");
+        assert_eq!(result.schema.prefixes, vec!["doc:"]);
+        assert_eq!(result.schema.fields[0].field_type, FieldType::Text);
+        assert_eq!(result.schema.fields[1].field_type, FieldType::Tag(';'));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ft_create_separator_only_on_tag_fields() {
+        for kind in ["TEXT", "NUMERIC"] {
+            let frames = ["ft.create", "idx", "SCHEMA", "f", kind, "SEPARATOR", ";"]
+                .into_iter()
+                .map(|arg| BulkString::from(arg).into())
+                .collect::<Vec<RespFrame>>();
+            assert!(FtCreate::try_from(frames).is_err(), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn test_ft_search_return_count_past_the_arguments() {
+        for count in ["2", "1000000000000", "18446744073709551615"] {
+            let frames = ["ft.search", "idx", "*", "RETURN", count, "title"]
+                .into_iter()
+                .map(|arg| BulkString::from(arg).into())
+                .collect::<Vec<RespFrame>>();
+            assert!(FtSearch::try_from(frames).is_err(), "RETURN {}", count);
+        }
+    }
+
+    #[test]
+    fn test_ft_search_command() -> Result<()> {
+        let backend = setup();
+        let cmd = FtCreate {
+            index: "idx".to_string(),
+            schema: IndexSchema {
+                prefixes: vec!["doc:".to_string()],
+                fields: vec![
+                    FieldSchema {
+                        name: "title".to_string(),
+                        field_type: FieldType::Text,
+                        sortable: false,
+                    },
+                    FieldSchema {
+                        name: "tags".to_string(),
+                        field_type: FieldType::Tag(','),
+                        sortable: false,
+                    },
+                    FieldSchema {
+                        name: "price".to_string(),
+                        field_type: FieldType::Numeric,
+                        sortable: true,
+                    },
+                ],
+            },
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        // documents written after the index was created are indexed too
+        HSet {
+            key: b"doc:4".to_vec(),
+            field: b"title".to_vec(),
+            value: BulkString::from("red scarf").into(),
+        }
+        .execute(&backend);
+
+        let cmd = FtSearch {
+            index: "idx".to_string(),
+            query: Query::parse("red @price:[15 +inf] | scarf")?,
+            no_content: false,
+            return_fields: Some(vec!["price".to_string()]),
+            sort_by: None,
+            offset: 0,
+            num: 10,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(2),
+                b"doc:3".into(),
+                RespArray::new([b"price".into(), b"20".into()]).into(),
+                b"doc:4".into(),
+                RespArray::new([]).into(),
+            ])
+            .into()
+        );
+
+        let cmd = FtSearch {
+            index: "idx".to_string(),
+            query: Query::parse("@tags:{sale | new}")?,
+            no_content: true,
+            return_fields: None,
+            sort_by: Some(("price".to_string(), false)),
+            offset: 1,
+            num: 1,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(3), b"doc:3".into()]).into()
+        );
+
+        let cmd = FtAggregate {
+            index: "idx".to_string(),
+            query: Query::parse("shoes | hat")?,
+            group_by: vec!["title".to_string()],
+            counts: vec!["n".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(3),
+                RespArray::new([
+                    b"title".into(),
+                    b"blue shoes".into(),
+                    b"n".into(),
+                    b"1".into()
+                ])
+                .into(),
+                RespArray::new([b"title".into(), b"red hat".into(), b"n".into(), b"1".into()])
+                    .into(),
+                RespArray::new([
+                    b"title".into(),
+                    b"red shoes".into(),
+                    b"n".into(),
+                    b"1".into()
+                ])
+                .into(),
+            ])
+            .into()
+        );
+
+        let cmd = FtDropIndex {
+            index: "idx".to_string(),
+            delete_docs: true,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert!(backend.hgetall(b"doc:1").is_none());
+        assert!(backend.hgetall(b"other:1").is_some());
+        let cmd = FtInfo {
+            index: "idx".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("Unknown index name").into()
+        );
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use super::{extract_args, validate_command};
 
 #[derive(Debug)]
 pub struct Get {
-    pub(crate) key: String,
+    pub(crate) key: Vec<u8>,
 }
 
 impl CommandExecutor for Get {
@@ -26,9 +26,7 @@ impl TryFrom<Vec<RespFrame>> for Get {
         // test if the first element is a bulk string
         match args.next() {
             Some(RespFrame::BulkString(key)) => match key.0 {
-                Some(k) => Ok(Self {
-                    key: String::from_utf8(k)?,
-                }),
+                Some(key) => Ok(Self { key: key.to_vec() }),
                 None => Err(CommandError::InvalidArgument(
                     "GET command must have a BulkString argument".to_string(),
                 )),
@@ -57,7 +55,7 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: Get = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
 
         Ok(())
     }
@@ -1,11 +1,11 @@
-use crate::{CommandError, CommandExecutor, RespFrame};
+use crate::{BulkString, CommandError, CommandExecutor, RespFrame};
 
 use super::{extract_args, validate_command};
 
 #[derive(Debug)]
 pub struct HGet {
-    pub(crate) key: String,
-    pub(crate) field: String,
+    pub(crate) key: Vec<u8>,
+    pub(crate) field: Vec<u8>,
 }
 
 impl CommandExecutor for HGet {
@@ -26,9 +26,12 @@ impl TryFrom<Vec<RespFrame>> for HGet {
 
         // test if the first element is a bulk string
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field))) => Ok(Self {
-                key: String::from_utf8(key.0.expect("not null"))?,
-                field: String::from_utf8(field.0.expect("not null"))?,
+            (
+                Some(RespFrame::BulkString(BulkString(Some(key)))),
+                Some(RespFrame::BulkString(BulkString(Some(field)))),
+            ) => Ok(Self {
+                key: key.to_vec(),
+                field: field.to_vec(),
             }),
             _ => Err(CommandError::InvalidArgument(
                 "HGET command must have two BulkString arguments".to_string(),
@@ -54,8 +57,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HGet = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.field, b"hello");
 
         Ok(())
     }
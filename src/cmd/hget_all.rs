@@ -4,7 +4,7 @@ use super::{extract_args, validate_command};
 
 #[derive(Debug)]
 pub struct HGetAll {
-    key: String,
+    key: Vec<u8>,
     sort: bool,
 }
 
@@ -24,7 +24,7 @@ impl CommandExecutor for HGetAll {
                 }
                 let ret = data
                     .into_iter()
-                    .flat_map(|(k, v)| vec![BulkString::from(k).into(), v])
+                    .flat_map(|(k, v)| vec![BulkString::new(k).into(), v])
                     .collect::<Vec<RespFrame>>();
 
                 RespArray::new(ret).into()
@@ -42,8 +42,8 @@ impl TryFrom<Vec<RespFrame>> for HGetAll {
         let mut args = extract_args(value, 1)?.into_iter();
 
         match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(Self {
-                key: String::from_utf8(key.0.expect("not null"))?,
+            Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(Self {
+                key: key.to_vec(),
                 sort: false,
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -72,7 +72,7 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HGetAll = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "map");
+        assert_eq!(result.key, b"map");
 
         Ok(())
     }
@@ -81,29 +81,29 @@ mod tests {
     fn test_hset_hget_hgetall_commands() -> Result<()> {
         let backend = crate::Backend::new();
         let cmd = HSet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: b"map".to_vec(),
+            field: b"hello".to_vec(),
             value: RespFrame::BulkString(b"world".into()),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = HSet {
-            key: "map".to_string(),
-            field: "hello1".to_string(),
+            key: b"map".to_vec(),
+            field: b"hello1".to_vec(),
             value: RespFrame::BulkString(b"world1".into()),
         };
         cmd.execute(&backend);
 
         let cmd = HGet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: b"map".to_vec(),
+            field: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         let cmd = HGetAll {
-            key: "map".to_string(),
+            key: b"map".to_vec(),
             sort: true,
         };
         let result = cmd.execute(&backend);
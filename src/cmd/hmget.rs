@@ -1,11 +1,11 @@
 use super::{extract_args, validate_dyn_command};
-use crate::{CommandError, CommandExecutor, RespArray, RespFrame};
+use crate::{BulkString, CommandError, CommandExecutor, RespArray, RespFrame};
 use anyhow::Result;
 
 #[derive(Debug)]
 pub struct HMGet {
-    key: String,
-    fields: Vec<String>,
+    key: Vec<u8>,
+    fields: Vec<Vec<u8>>,
 }
 
 impl CommandExecutor for HMGet {
@@ -36,7 +36,7 @@ impl TryFrom<Vec<RespFrame>> for HMGet {
         let mut args = extract_args(value, 1)?.into_iter();
 
         let key = match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(String::from_utf8(key.0.expect("not null"))?),
+            Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(key.to_vec()),
             _ => Err(CommandError::InvalidArgument(
                 "HMGET command must have a BulkString key argument".to_string(),
             )),
@@ -46,9 +46,7 @@ impl TryFrom<Vec<RespFrame>> for HMGet {
 
         for arg in args {
             match arg {
-                RespFrame::BulkString(field) => {
-                    fields.push(String::from_utf8(field.0.expect("not null"))?)
-                }
+                RespFrame::BulkString(BulkString(Some(field))) => fields.push(field.to_vec()),
                 _ => {
                     return Err(CommandError::InvalidArgument(
                         "HMGET command arguments must be BulkString".to_string(),
@@ -77,8 +75,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HMGet = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.fields, vec!["hello", "world"]);
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.fields, vec![b"hello".to_vec(), b"world".to_vec()]);
 
         Ok(())
     }
@@ -87,15 +85,15 @@ mod tests {
     fn test_hmget_command() -> Result<()> {
         let backend = crate::Backend::new();
         HSet {
-            key: "map".to_string(),
-            field: "hello".to_string(),
+            key: b"map".to_vec(),
+            field: b"hello".to_vec(),
             value: RespFrame::BulkString(b"world".into()),
         }
         .execute(&backend);
 
         let cmd = HMGet {
-            key: "map".to_string(),
-            fields: vec!["hello".to_string(), "rust".to_string()],
+            key: b"map".to_vec(),
+            fields: vec![b"hello".to_vec(), b"rust".to_vec()],
         };
         let result = cmd.execute(&backend);
         assert_eq!(
@@ -1,11 +1,11 @@
-use crate::{CommandError, CommandExecutor, RespFrame};
+use crate::{BulkString, CommandError, CommandExecutor, RespFrame};
 
 use super::{extract_args, validate_command, RESP_OK};
 
 #[derive(Debug)]
 pub struct HSet {
-    pub(crate) key: String,
-    pub(crate) field: String,
+    pub(crate) key: Vec<u8>,
+    pub(crate) field: Vec<u8>,
     pub(crate) value: RespFrame,
 }
 
@@ -24,13 +24,15 @@ impl TryFrom<Vec<RespFrame>> for HSet {
         let mut args = extract_args(value, 1)?.into_iter();
 
         match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(Self {
-                    key: String::from_utf8(key.0.expect("not null"))?,
-                    field: String::from_utf8(field.0.expect("not null"))?,
-                    value,
-                })
-            }
+            (
+                Some(RespFrame::BulkString(BulkString(Some(key)))),
+                Some(RespFrame::BulkString(BulkString(Some(field)))),
+                Some(value),
+            ) => Ok(Self {
+                key: key.to_vec(),
+                field: field.to_vec(),
+                value,
+            }),
             _ => Err(CommandError::InvalidArgument(
                 "HSET command must have three BulkString arguments".to_string(),
             )),
@@ -55,8 +57,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: HSet = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
+        assert_eq!(result.key, b"map");
+        assert_eq!(result.field, b"hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
 
         Ok(())
@@ -0,0 +1,48 @@
+use crate::{Backend, BulkString, CommandError, CommandExecutor, RespFrame};
+
+use super::{extract_strings, validate_dyn_command};
+
+// INFO [section ...]
+#[derive(Debug)]
+pub struct Info {
+    sections: Vec<String>,
+}
+
+impl CommandExecutor for Info {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        BulkString::from(backend.info(&self.sections)).into()
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Info {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["info"], 0)?;
+        let sections = extract_strings(value, 1)?;
+        Ok(Info { sections })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_info_execute() -> Result<()> {
+        let frames = vec![
+            BulkString::from("info").into(),
+            BulkString::from("stats").into(),
+        ];
+        let ret = Info::try_from(frames)?.execute(&Backend::new());
+        assert_eq!(
+            ret,
+            BulkString::from(
+                "# Stats\r\ntotal_connections_received:0\r\ntotal_commands_processed:0\r\nrejected_connections:0\r\n"
+            )
+            .into()
+        );
+        Ok(())
+    }
+}
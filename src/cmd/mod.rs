@@ -1,12 +1,20 @@
-use crate::{Backend, RespFrame};
+use crate::{Backend, BulkString, RespArray, RespFrame};
+mod client;
+mod config;
+mod connection;
 mod echo;
+mod ft;
 mod get;
 mod hget;
 mod hget_all;
 mod hmget;
 mod hset;
+mod info;
+mod pubsub;
 mod sadd;
 mod sismember;
+mod transaction;
+mod ts;
 
 mod set;
 use enum_dispatch::enum_dispatch;
@@ -17,9 +25,30 @@ lazy_static! {
 }
 use thiserror::Error;
 
+pub use self::{
+    client::{Client, TrackingOptions},
+    config::ConfigCommand,
+    connection::{Auth, Hello, Shutdown},
+};
 use self::{
-    echo::Echo, get::Get, hget::HGet, hget_all::HGetAll, hmget::HMGet, hset::HSet, sadd::SAdd,
-    set::Set, sismember::SIsMember,
+    connection::{Ping, Quit},
+    echo::Echo,
+    ft::{FtAggregate, FtCreate, FtDropIndex, FtInfo, FtSearch},
+    get::Get,
+    hget::HGet,
+    hget_all::HGetAll,
+    hmget::HMGet,
+    hset::HSet,
+    info::Info,
+    pubsub::{
+        PSubscribe, PUnsubscribe, PubSubInfo, Publish, SPublish, SSubscribe, SUnsubscribe,
+        Subscribe, Unsubscribe,
+    },
+    sadd::SAdd,
+    set::Set,
+    sismember::SIsMember,
+    transaction::{Discard, Exec, Multi, Unwatch, Watch},
+    ts::{TsAdd, TsCreate, TsCreateRule, TsIncrBy, TsMAdd, TsMRange, TsRange},
 };
 #[enum_dispatch]
 pub trait CommandExecutor {
@@ -51,6 +80,40 @@ pub enum Command {
     HMGet(HMGet),
     SAdd(SAdd),
     SIsMember(SIsMember),
+    TsCreate(TsCreate),
+    TsAdd(TsAdd),
+    TsMAdd(TsMAdd),
+    TsIncrBy(TsIncrBy),
+    TsRange(TsRange),
+    TsMRange(TsMRange),
+    TsCreateRule(TsCreateRule),
+    FtCreate(FtCreate),
+    FtSearch(FtSearch),
+    FtAggregate(FtAggregate),
+    FtDropIndex(FtDropIndex),
+    FtInfo(FtInfo),
+    Multi(Multi),
+    Exec(Exec),
+    Discard(Discard),
+    Watch(Watch),
+    Unwatch(Unwatch),
+    Subscribe(Subscribe),
+    Unsubscribe(Unsubscribe),
+    PSubscribe(PSubscribe),
+    PUnsubscribe(PUnsubscribe),
+    Publish(Publish),
+    PubSubInfo(PubSubInfo),
+    SSubscribe(SSubscribe),
+    SUnsubscribe(SUnsubscribe),
+    SPublish(SPublish),
+    Ping(Ping),
+    Quit(Quit),
+    Client(Client),
+    Hello(Hello),
+    Auth(Auth),
+    Config(ConfigCommand),
+    Shutdown(Shutdown),
+    Info(Info),
     // unrecognized command
     Unrecognized(Unrecognized),
 }
@@ -63,7 +126,10 @@ impl TryFrom<RespFrame> for Command {
 
     fn try_from(value: RespFrame) -> Result<Self, Self::Error> {
         match value {
-            RespFrame::Array(array) => array.0.expect("array should not null").try_into(),
+            RespFrame::Array(RespArray(Some(frames))) => frames.try_into(),
+            RespFrame::Array(RespArray(None)) => Err(CommandError::InvalidCommand(
+                "Command must not be a null array".to_string(),
+            )),
             _ => Err(CommandError::InvalidCommand(
                 "Command must be an array".to_string(),
             )),
@@ -76,7 +142,8 @@ impl TryFrom<Vec<RespFrame>> for Command {
 
     fn try_from(v: Vec<RespFrame>) -> Result<Self, Self::Error> {
         match v.first() {
-            Some(RespFrame::BulkString(ref cmd)) => match cmd.as_ref() {
+            // command names are case insensitive
+            Some(RespFrame::BulkString(ref cmd)) => match cmd.to_ascii_lowercase().as_slice() {
                 b"get" => Ok(Get::try_from(v)?.into()),
                 b"set" => Ok(Set::try_from(v)?.into()),
                 b"hget" => Ok(HGet::try_from(v)?.into()),
@@ -86,6 +153,40 @@ impl TryFrom<Vec<RespFrame>> for Command {
                 b"hmget" => Ok(HMGet::try_from(v)?.into()),
                 b"sadd" => Ok(SAdd::try_from(v)?.into()),
                 b"sismember" => Ok(SIsMember::try_from(v)?.into()),
+                b"ts.create" => Ok(TsCreate::try_from(v)?.into()),
+                b"ts.add" => Ok(TsAdd::try_from(v)?.into()),
+                b"ts.madd" => Ok(TsMAdd::try_from(v)?.into()),
+                b"ts.incrby" | b"ts.decrby" => Ok(TsIncrBy::try_from(v)?.into()),
+                b"ts.range" | b"ts.revrange" => Ok(TsRange::try_from(v)?.into()),
+                b"ts.mrange" => Ok(TsMRange::try_from(v)?.into()),
+                b"ts.createrule" => Ok(TsCreateRule::try_from(v)?.into()),
+                b"ft.create" => Ok(FtCreate::try_from(v)?.into()),
+                b"ft.search" => Ok(FtSearch::try_from(v)?.into()),
+                b"ft.aggregate" => Ok(FtAggregate::try_from(v)?.into()),
+                b"ft.dropindex" => Ok(FtDropIndex::try_from(v)?.into()),
+                b"ft.info" => Ok(FtInfo::try_from(v)?.into()),
+                b"multi" => Ok(Multi::try_from(v)?.into()),
+                b"exec" => Ok(Exec::try_from(v)?.into()),
+                b"discard" => Ok(Discard::try_from(v)?.into()),
+                b"watch" => Ok(Watch::try_from(v)?.into()),
+                b"unwatch" => Ok(Unwatch::try_from(v)?.into()),
+                b"subscribe" => Ok(Subscribe::try_from(v)?.into()),
+                b"unsubscribe" => Ok(Unsubscribe::try_from(v)?.into()),
+                b"psubscribe" => Ok(PSubscribe::try_from(v)?.into()),
+                b"punsubscribe" => Ok(PUnsubscribe::try_from(v)?.into()),
+                b"publish" => Ok(Publish::try_from(v)?.into()),
+                b"pubsub" => Ok(PubSubInfo::try_from(v)?.into()),
+                b"ssubscribe" => Ok(SSubscribe::try_from(v)?.into()),
+                b"sunsubscribe" => Ok(SUnsubscribe::try_from(v)?.into()),
+                b"spublish" => Ok(SPublish::try_from(v)?.into()),
+                b"ping" => Ok(Ping::try_from(v)?.into()),
+                b"quit" => Ok(Quit::try_from(v)?.into()),
+                b"client" => Ok(Client::try_from(v)?.into()),
+                b"hello" => Ok(Hello::try_from(v)?.into()),
+                b"auth" => Ok(Auth::try_from(v)?.into()),
+                b"config" => Ok(ConfigCommand::try_from(v)?.into()),
+                b"shutdown" => Ok(Shutdown::try_from(v)?.into()),
+                b"info" => Ok(Info::try_from(v)?.into()),
                 _ => Ok(Unrecognized.into()),
             },
             _ => Err(CommandError::InvalidCommand(
@@ -171,3 +272,41 @@ fn validate_dyn_command(
 fn extract_args(value: Vec<RespFrame>, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
+
+// extract the remaining arguments as strings, used by commands that only take textual arguments
+fn extract_strings(value: Vec<RespFrame>, start: usize) -> Result<Vec<String>, CommandError> {
+    value
+        .into_iter()
+        .skip(start)
+        .map(|v| match v {
+            RespFrame::BulkString(BulkString(Some(s))) => Ok(String::from_utf8(s.to_vec())?),
+            _ => Err(CommandError::InvalidArgument(
+                "arguments must be BulkString".to_string(),
+            )),
+        })
+        .collect()
+}
+
+// extract the remaining arguments as binary safe byte strings, used for keys.
+// keys are copied out of the read buffer, only values keep sharing it
+fn extract_bytes(value: Vec<RespFrame>, start: usize) -> Result<Vec<Vec<u8>>, CommandError> {
+    value
+        .into_iter()
+        .skip(start)
+        .map(|v| match v {
+            RespFrame::BulkString(BulkString(Some(s))) => Ok(s.to_vec()),
+            _ => Err(CommandError::InvalidArgument(
+                "arguments must be BulkString".to_string(),
+            )),
+        })
+        .collect()
+}
+
+fn parse_arg<T: std::str::FromStr>(arg: Option<&String>, name: &str) -> Result<T, CommandError> {
+    match arg {
+        Some(s) => s
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument(format!("invalid {}: {}", name, s))),
+        None => Err(CommandError::InvalidArgument(format!("missing {}", name))),
+    }
+}
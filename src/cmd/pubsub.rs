@@ -0,0 +1,336 @@
+use crate::{
+    Backend, BulkString, CommandError, CommandExecutor, RespArray, RespFrame, SimpleError,
+};
+
+use super::{extract_args, extract_strings, validate_command, validate_dyn_command};
+
+// SUBSCRIBE and friends change the connection state and are handled by the connection
+#[derive(Debug)]
+pub struct Subscribe {
+    pub(crate) channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Unsubscribe {
+    pub(crate) channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PSubscribe {
+    pub(crate) patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PUnsubscribe {
+    pub(crate) patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SSubscribe {
+    pub(crate) channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SUnsubscribe {
+    pub(crate) channels: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SPublish {
+    channel: String,
+    message: BulkString,
+}
+
+#[derive(Debug)]
+pub struct Publish {
+    channel: String,
+    message: BulkString,
+}
+
+#[derive(Debug)]
+pub enum PubSubInfo {
+    Channels(Option<String>),
+    NumSub(Vec<String>),
+    NumPat,
+    ShardChannels(Option<String>),
+    ShardNumSub(Vec<String>),
+}
+
+impl CommandExecutor for Subscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        not_allowed("SUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for Unsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        not_allowed("UNSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for PSubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        not_allowed("PSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for PUnsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        not_allowed("PUNSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for SSubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        not_allowed("SSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for SUnsubscribe {
+    fn execute(self, _backend: &Backend) -> RespFrame {
+        not_allowed("SUNSUBSCRIBE")
+    }
+}
+
+impl CommandExecutor for SPublish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.spublish(&self.channel, self.message) as i64)
+    }
+}
+
+impl CommandExecutor for Publish {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.publish(&self.channel, self.message) as i64)
+    }
+}
+
+impl CommandExecutor for PubSubInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match self {
+            PubSubInfo::Channels(pattern) => {
+                channels_frame(backend.pubsub_channels(pattern.as_deref()))
+            }
+            PubSubInfo::NumSub(channels) => numsub_frame(channels, |c| backend.pubsub_numsub(c)),
+            PubSubInfo::NumPat => RespFrame::Integer(backend.pubsub_numpat() as i64),
+            PubSubInfo::ShardChannels(pattern) => {
+                channels_frame(backend.pubsub_shardchannels(pattern.as_deref()))
+            }
+            PubSubInfo::ShardNumSub(channels) => {
+                numsub_frame(channels, |c| backend.pubsub_shardnumsub(c))
+            }
+        }
+    }
+}
+
+fn channels_frame(channels: Vec<String>) -> RespFrame {
+    let ret = channels
+        .into_iter()
+        .map(|c| BulkString::from(c).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(ret).into()
+}
+
+fn numsub_frame(channels: Vec<String>, numsub: impl Fn(&str) -> usize) -> RespFrame {
+    let ret = channels
+        .into_iter()
+        .flat_map(|c| {
+            let n = numsub(&c) as i64;
+            [BulkString::from(c).into(), RespFrame::Integer(n)]
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(ret).into()
+}
+
+impl TryFrom<Vec<RespFrame>> for Subscribe {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["subscribe"], 1)?;
+        Ok(Self {
+            channels: extract_strings(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Unsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["unsubscribe"], 0)?;
+        Ok(Self {
+            channels: extract_strings(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for PSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["psubscribe"], 1)?;
+        Ok(Self {
+            patterns: extract_strings(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for PUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["punsubscribe"], 0)?;
+        Ok(Self {
+            patterns: extract_strings(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for SSubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ssubscribe"], 1)?;
+        Ok(Self {
+            channels: extract_strings(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for SUnsubscribe {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["sunsubscribe"], 0)?;
+        Ok(Self {
+            channels: extract_strings(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Publish {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["publish"], 2)?;
+        let (channel, message) = extract_message(value, "PUBLISH")?;
+        Ok(Self { channel, message })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for SPublish {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["spublish"], 2)?;
+        let (channel, message) = extract_message(value, "SPUBLISH")?;
+        Ok(Self { channel, message })
+    }
+}
+
+fn extract_message(
+    value: Vec<RespFrame>,
+    name: &str,
+) -> Result<(String, BulkString), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+
+    match (args.next(), args.next()) {
+        (Some(RespFrame::BulkString(channel)), Some(RespFrame::BulkString(message))) => Ok((
+            String::from_utf8(channel.0.unwrap_or_default().to_vec())?,
+            message,
+        )),
+        _ => Err(CommandError::InvalidArgument(format!(
+            "{} command must have two BulkString arguments",
+            name
+        ))),
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for PubSubInfo {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["pubsub"], 1)?;
+        let mut args = extract_strings(value, 1)?;
+        let sub = args.remove(0);
+        match sub.to_ascii_lowercase().as_str() {
+            "channels" if args.len() <= 1 => Ok(PubSubInfo::Channels(args.pop())),
+            "numsub" => Ok(PubSubInfo::NumSub(args)),
+            "numpat" if args.is_empty() => Ok(PubSubInfo::NumPat),
+            "shardchannels" if args.len() <= 1 => Ok(PubSubInfo::ShardChannels(args.pop())),
+            "shardnumsub" => Ok(PubSubInfo::ShardNumSub(args)),
+            _ => Err(CommandError::InvalidArgument(format!(
+                "unknown PUBSUB subcommand or wrong number of arguments for '{}'",
+                sub
+            ))),
+        }
+    }
+}
+
+fn not_allowed(name: &str) -> RespFrame {
+    SimpleError::new(format!("ERR {} is not allowed in this context", name)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_publish_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$7\r\npublish\r\n$4\r\nnews\r\n$5\r\nhello\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Publish = frame.0.unwrap().try_into()?;
+        assert_eq!(result.channel, "news");
+        assert_eq!(result.message, "hello".into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pubsub_command() -> Result<()> {
+        let backend = Backend::new();
+        let (subscriber, _rx) = backend.subscriber();
+        backend.subscribe("news", &subscriber);
+
+        let cmd = Publish {
+            channel: "news".to_string(),
+            message: "hello".into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd = PubSubInfo::NumSub(vec!["news".to_string(), "other".to_string()]);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                b"news".into(),
+                RespFrame::Integer(1),
+                b"other".into(),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+        let cmd = PubSubInfo::Channels(None);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([b"news".into()]).into()
+        );
+
+        backend.ssubscribe("orders", &subscriber);
+        let cmd = SPublish {
+            channel: "orders".to_string(),
+            message: "hello".into(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd = PubSubInfo::ShardNumSub(vec!["orders".to_string()]);
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([b"orders".into(), RespFrame::Integer(1)]).into()
+        );
+        Ok(())
+    }
+}
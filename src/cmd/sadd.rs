@@ -1,10 +1,10 @@
 use super::{extract_args, validate_dyn_command};
-use crate::{CommandError, CommandExecutor, RespFrame};
+use crate::{BulkString, CommandError, CommandExecutor, RespFrame};
 
 #[derive(Debug)]
 pub struct SAdd {
-    pub(crate) key: String,
-    pub(crate) members: Vec<String>,
+    pub(crate) key: Vec<u8>,
+    pub(crate) members: Vec<Vec<u8>>,
 }
 
 impl CommandExecutor for SAdd {
@@ -22,7 +22,7 @@ impl TryFrom<Vec<RespFrame>> for SAdd {
         let mut args = extract_args(value, 1)?.into_iter();
 
         let key = match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(String::from_utf8(key.0.expect("not null"))?),
+            Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(key.to_vec()),
             _ => Err(CommandError::InvalidArgument(
                 "SADD command must have a BulkString key argument".to_string(),
             )),
@@ -32,9 +32,7 @@ impl TryFrom<Vec<RespFrame>> for SAdd {
 
         for arg in args {
             match arg {
-                RespFrame::BulkString(member) => {
-                    members.push(String::from_utf8(member.0.expect("not null"))?)
-                }
+                RespFrame::BulkString(BulkString(Some(member))) => members.push(member.to_vec()),
                 _ => {
                     return Err(CommandError::InvalidArgument(
                         "SADD command arguments must be BulkString".to_string(),
@@ -62,8 +60,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: SAdd = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "hello");
-        assert_eq!(result.members, vec!["world"]);
+        assert_eq!(result.key, b"hello");
+        assert_eq!(result.members, vec![b"world".to_vec()]);
 
         Ok(())
     }
@@ -72,12 +70,8 @@ mod tests {
     fn test_sadd_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = SAdd {
-            key: "hello".to_string(),
-            members: vec![
-                "world".to_string(),
-                "world2".to_string(),
-                "world".to_string(),
-            ],
+            key: b"hello".to_vec(),
+            members: vec![b"world".to_vec(), b"world2".to_vec(), b"world".to_vec()],
         };
 
         let resp = cmd.execute(&backend);
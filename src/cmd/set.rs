@@ -1,10 +1,10 @@
-use crate::{CommandError, CommandExecutor, RespFrame};
+use crate::{BulkString, CommandError, CommandExecutor, RespFrame};
 
 use super::{extract_args, validate_command, RESP_OK};
 
 #[derive(Debug)]
 pub struct Set {
-    key: String,
+    key: Vec<u8>,
     value: RespFrame,
 }
 
@@ -23,8 +23,8 @@ impl TryFrom<Vec<RespFrame>> for Set {
         let mut args = extract_args(value, 1)?.into_iter();
 
         match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(value)) => Ok(Self {
-                key: String::from_utf8(key.0.expect("not null"))?,
+            (Some(RespFrame::BulkString(BulkString(Some(key)))), Some(value)) => Ok(Self {
+                key: key.to_vec(),
                 value,
             }),
             _ => Err(CommandError::InvalidArgument(
@@ -49,7 +49,7 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: Set = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "hello");
+        assert_eq!(result.key, b"hello");
         assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
 
         Ok(())
@@ -59,18 +59,42 @@ mod tests {
     fn test_set_get_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = Set {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
             value: RespFrame::BulkString(b"world".into()),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RESP_OK.clone());
 
         let cmd = Get {
-            key: "hello".to_string(),
+            key: b"hello".to_vec(),
         };
         let result = cmd.execute(&backend);
         assert_eq!(result, RespFrame::BulkString(b"world".into()));
 
         Ok(())
     }
+
+    #[test]
+    fn test_binary_and_null_keys() -> Result<()> {
+        let backend = Backend::new();
+        let key = vec![0xff, 0x00, b'\r', b'\n'];
+        let cmd: Set = vec![
+            BulkString::from("set").into(),
+            BulkString::new(key.clone()).into(),
+            BulkString::from("v").into(),
+        ]
+        .try_into()?;
+        cmd.execute(&backend);
+        assert_eq!(backend.get(&key), Some(BulkString::from("v").into()));
+
+        let ret: Result<Set, _> = vec![
+            BulkString::from("set").into(),
+            BulkString(None).into(),
+            BulkString::from("v").into(),
+        ]
+        .try_into();
+        assert!(matches!(ret, Err(CommandError::InvalidArgument(_))));
+
+        Ok(())
+    }
 }
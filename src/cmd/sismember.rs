@@ -1,10 +1,10 @@
 use super::{extract_args, validate_command};
-use crate::{CommandError, CommandExecutor, RespFrame};
+use crate::{BulkString, CommandError, CommandExecutor, RespFrame};
 
 #[derive(Debug)]
 pub struct SIsMember {
-    pub(crate) key: String,
-    pub(crate) member: String,
+    pub(crate) key: Vec<u8>,
+    pub(crate) member: Vec<u8>,
 }
 
 impl CommandExecutor for SIsMember {
@@ -22,16 +22,14 @@ impl TryFrom<Vec<RespFrame>> for SIsMember {
         let mut args = extract_args(value, 1)?.into_iter();
 
         let key = match args.next() {
-            Some(RespFrame::BulkString(key)) => Ok(String::from_utf8(key.0.expect("not null"))?),
+            Some(RespFrame::BulkString(BulkString(Some(key)))) => Ok(key.to_vec()),
             _ => Err(CommandError::InvalidArgument(
                 "SISMEMBER command must have a BulkString key argument".to_string(),
             )),
         }?;
 
         let member = match args.next() {
-            Some(RespFrame::BulkString(member)) => {
-                Ok(String::from_utf8(member.0.expect("not null"))?)
-            }
+            Some(RespFrame::BulkString(BulkString(Some(member)))) => Ok(member.to_vec()),
             _ => Err(CommandError::InvalidArgument(
                 "SISMEMBER command must have a BulkString member argument".to_string(),
             )),
@@ -56,8 +54,8 @@ mod tests {
         let frame = RespArray::decode(&mut buf)?;
 
         let result: SIsMember = frame.0.unwrap().try_into()?;
-        assert_eq!(result.key, "myset");
-        assert_eq!(result.member, "world");
+        assert_eq!(result.key, b"myset");
+        assert_eq!(result.member, b"world");
 
         Ok(())
     }
@@ -66,21 +64,21 @@ mod tests {
     fn test_sismember_command() -> Result<()> {
         let backend = Backend::new();
         let cmd = SIsMember {
-            key: "myset".to_string(),
-            member: "world".to_string(),
+            key: b"myset".to_vec(),
+            member: b"world".to_vec(),
         };
         let result = cmd.execute(&backend);
 
         assert_eq!(result, RespFrame::Integer(0));
 
         SAdd {
-            key: "myset".to_string(),
-            members: vec!["world".to_string()],
+            key: b"myset".to_vec(),
+            members: vec![b"world".to_vec()],
         }
         .execute(&backend);
         let cmd = SIsMember {
-            key: "myset".to_string(),
-            member: "world".to_string(),
+            key: b"myset".to_vec(),
+            member: b"world".to_vec(),
         };
         let result = cmd.execute(&backend);
 
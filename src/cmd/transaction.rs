@@ -0,0 +1,126 @@
+use crate::{CommandError, CommandExecutor, RespFrame, SimpleError};
+
+use super::{extract_bytes, validate_command, validate_dyn_command, RESP_OK};
+
+// MULTI, EXEC, DISCARD and WATCH depend on the connection state and are handled by the
+// connection, executing them directly means they were sent in the wrong state.
+#[derive(Debug)]
+pub struct Multi;
+
+#[derive(Debug)]
+pub struct Exec;
+
+#[derive(Debug)]
+pub struct Discard;
+
+#[derive(Debug)]
+pub struct Watch {
+    pub(crate) keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct Unwatch;
+
+impl CommandExecutor for Multi {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR MULTI calls can not be nested").into()
+    }
+}
+
+impl CommandExecutor for Exec {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR EXEC without MULTI").into()
+    }
+}
+
+impl CommandExecutor for Discard {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR DISCARD without MULTI").into()
+    }
+}
+
+impl CommandExecutor for Watch {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        SimpleError::new("ERR WATCH inside MULTI is not allowed").into()
+    }
+}
+
+impl CommandExecutor for Unwatch {
+    fn execute(self, _backend: &crate::Backend) -> RespFrame {
+        RESP_OK.clone()
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Multi {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["multi"], 0)?;
+        Ok(Self)
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Exec {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["exec"], 0)?;
+        Ok(Self)
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Discard {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["discard"], 0)?;
+        Ok(Self)
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Watch {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["watch"], 1)?;
+        Ok(Self {
+            keys: extract_bytes(value, 1)?,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for Unwatch {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["unwatch"], 0)?;
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RespArray, RespDecode};
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    #[test]
+    fn test_watch_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$5\r\nwatch\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Watch = frame.0.unwrap().try_into()?;
+        assert_eq!(result.keys, vec![b"a".to_vec(), b"b".to_vec()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exec_without_multi() {
+        let frame = Exec.execute(&crate::Backend::new());
+        assert_eq!(frame, SimpleError::new("ERR EXEC without MULTI").into());
+    }
+}
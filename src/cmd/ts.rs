@@ -0,0 +1,722 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Aggregation, Backend, BulkString, CommandError, CommandExecutor, DuplicatePolicy, LabelFilter,
+    RespArray, RespFrame, SimpleError, TimeSeriesError, TimeSeriesOptions,
+};
+
+use super::{extract_strings, parse_arg, validate_command, validate_dyn_command, RESP_OK};
+
+#[derive(Debug)]
+pub struct TsCreate {
+    key: String,
+    opts: TimeSeriesOptions,
+}
+
+#[derive(Debug)]
+pub struct TsAdd {
+    key: String,
+    // None means `*`, the server time
+    timestamp: Option<u64>,
+    value: f64,
+    opts: TimeSeriesOptions,
+    on_duplicate: Option<DuplicatePolicy>,
+}
+
+#[derive(Debug)]
+pub struct TsMAdd {
+    samples: Vec<(String, Option<u64>, f64)>,
+}
+
+#[derive(Debug)]
+pub struct TsIncrBy {
+    key: String,
+    delta: f64,
+    timestamp: Option<u64>,
+    opts: TimeSeriesOptions,
+    decrement: bool,
+}
+
+#[derive(Debug)]
+pub struct TsRange {
+    key: String,
+    range: RangeOptions,
+    reverse: bool,
+}
+
+#[derive(Debug)]
+pub struct TsMRange {
+    range: RangeOptions,
+    with_labels: bool,
+    filters: Vec<LabelFilter>,
+}
+
+#[derive(Debug)]
+pub struct TsCreateRule {
+    src: String,
+    dest: String,
+    aggregation: Aggregation,
+    bucket_duration: u64,
+}
+
+#[derive(Debug, Default)]
+struct RangeOptions {
+    from: u64,
+    to: u64,
+    aggregation: Option<(Aggregation, u64)>,
+    count: Option<usize>,
+}
+
+impl CommandExecutor for TsCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_create(self.key, self.opts) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => ts_error(e),
+        }
+    }
+}
+
+impl CommandExecutor for TsAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ts = self.timestamp.unwrap_or_else(now);
+        match backend.ts_add(self.key, ts, self.value, self.opts, self.on_duplicate) {
+            Ok(ts) => RespFrame::Integer(ts as i64),
+            Err(e) => ts_error(e),
+        }
+    }
+}
+
+impl CommandExecutor for TsMAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = self
+            .samples
+            .into_iter()
+            .map(|(key, ts, value)| {
+                let ts = ts.unwrap_or_else(now);
+                match backend.ts_append(&key, ts, value, None) {
+                    Ok(ts) => RespFrame::Integer(ts as i64),
+                    Err(e) => ts_error(e),
+                }
+            })
+            .collect::<Vec<_>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for TsIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ts = self.timestamp.unwrap_or_else(now);
+        let ret = if self.decrement {
+            backend.ts_decrby(self.key, self.delta, ts, self.opts)
+        } else {
+            backend.ts_incrby(self.key, self.delta, ts, self.opts)
+        };
+        match ret {
+            Ok(ts) => RespFrame::Integer(ts as i64),
+            Err(e) => ts_error(e),
+        }
+    }
+}
+
+impl CommandExecutor for TsRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_range(&self.key, self.range.from, self.range.to) {
+            Ok(samples) => samples_frame(self.range.apply(samples, self.reverse)),
+            Err(e) => ts_error(e),
+        }
+    }
+}
+
+impl CommandExecutor for TsMRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .ts_mrange(self.range.from, self.range.to, &self.filters)
+            .into_iter()
+            .map(|(key, labels, samples)| {
+                let labels = if self.with_labels {
+                    labels
+                        .into_iter()
+                        .map(|(k, v)| {
+                            RespArray::new([BulkString::from(k).into(), BulkString::from(v).into()])
+                                .into()
+                        })
+                        .collect::<Vec<RespFrame>>()
+                } else {
+                    vec![]
+                };
+                RespArray::new([
+                    BulkString::from(key).into(),
+                    RespArray::new(labels).into(),
+                    samples_frame(self.range.apply(samples, false)),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for TsCreateRule {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_create_rule(
+            &self.src,
+            &self.dest,
+            self.aggregation,
+            self.bucket_duration,
+        ) {
+            Ok(_) => RESP_OK.clone(),
+            Err(e) => ts_error(e),
+        }
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsCreate {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ts.create"], 1)?;
+        let args = extract_strings(value, 1)?;
+        let (opts, _) = parse_options(&args[1..], false)?;
+        Ok(Self {
+            key: args[0].clone(),
+            opts,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsAdd {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ts.add"], 3)?;
+        let args = extract_strings(value, 1)?;
+        let (opts, on_duplicate) = parse_options(&args[3..], true)?;
+        Ok(Self {
+            key: args[0].clone(),
+            timestamp: parse_timestamp(&args[1])?,
+            value: parse_arg(args.get(2), "value")?,
+            opts,
+            on_duplicate,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsMAdd {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ts.madd"], 3)?;
+        let args = extract_strings(value, 1)?;
+        if args.len() % 3 != 0 {
+            return Err(CommandError::InvalidArgument(
+                "TS.MADD command must have key timestamp value triplets".to_string(),
+            ));
+        }
+        let samples = args
+            .chunks(3)
+            .map(|c| {
+                Ok((
+                    c[0].clone(),
+                    parse_timestamp(&c[1])?,
+                    parse_arg(c.get(2), "value")?,
+                ))
+            })
+            .collect::<Result<_, CommandError>>()?;
+        Ok(Self { samples })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsIncrBy {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        let decrement = matches!(value.first(), Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(b"ts.decrby"));
+        let name = if decrement { "ts.decrby" } else { "ts.incrby" };
+        validate_dyn_command(&value, &[name], 2)?;
+        let args = extract_strings(value, 1)?;
+        let mut rest = Vec::new();
+        let mut timestamp = None;
+        let mut iter = args[2..].iter();
+        while let Some(arg) = iter.next() {
+            if arg.eq_ignore_ascii_case("timestamp") {
+                timestamp = parse_timestamp(&parse_arg::<String>(iter.next(), "timestamp")?)?;
+            } else {
+                rest.push(arg.clone());
+            }
+        }
+        let (opts, _) = parse_options(&rest, false)?;
+        Ok(Self {
+            key: args[0].clone(),
+            delta: parse_arg(args.get(1), "value")?,
+            timestamp,
+            opts,
+            decrement,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsRange {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        let reverse = matches!(value.first(), Some(RespFrame::BulkString(cmd)) if cmd.eq_ignore_ascii_case(b"ts.revrange"));
+        let name = if reverse { "ts.revrange" } else { "ts.range" };
+        validate_dyn_command(&value, &[name], 3)?;
+        let args = extract_strings(value, 1)?;
+        let mut range = RangeOptions::new(&args[1], &args[2])?;
+        let mut iter = args[3..].iter();
+        while let Some(arg) = iter.next() {
+            if !range.parse_option(arg, &mut iter)? {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown argument {}",
+                    arg
+                )));
+            }
+        }
+        Ok(Self {
+            key: args[0].clone(),
+            range,
+            reverse,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsMRange {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_dyn_command(&value, &["ts.mrange"], 4)?;
+        let args = extract_strings(value, 1)?;
+        let mut range = RangeOptions::new(&args[0], &args[1])?;
+        let mut with_labels = false;
+        let mut filters = Vec::new();
+        let mut iter = args[2..].iter();
+        while let Some(arg) = iter.next() {
+            if range.parse_option(arg, &mut iter)? {
+                continue;
+            }
+            if arg.eq_ignore_ascii_case("withlabels") {
+                with_labels = true;
+            } else if arg.eq_ignore_ascii_case("filter") {
+                for f in iter.by_ref() {
+                    filters.push(f.parse().map_err(CommandError::InvalidArgument)?);
+                }
+            } else {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown argument {}",
+                    arg
+                )));
+            }
+        }
+        if filters.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "TS.MRANGE command must have a FILTER argument".to_string(),
+            ));
+        }
+        Ok(Self {
+            range,
+            with_labels,
+            filters,
+        })
+    }
+}
+
+impl TryFrom<Vec<RespFrame>> for TsCreateRule {
+    type Error = CommandError;
+
+    fn try_from(value: Vec<RespFrame>) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.createrule"], 5)?;
+        let args = extract_strings(value, 1)?;
+        if !args[2].eq_ignore_ascii_case("aggregation") {
+            return Err(CommandError::InvalidArgument(
+                "TS.CREATERULE command must have an AGGREGATION argument".to_string(),
+            ));
+        }
+        Ok(Self {
+            src: args[0].clone(),
+            dest: args[1].clone(),
+            aggregation: args[3].parse().map_err(CommandError::InvalidArgument)?,
+            bucket_duration: parse_bucket(args.get(4))?,
+        })
+    }
+}
+
+impl RangeOptions {
+    fn new(from: &str, to: &str) -> Result<Self, CommandError> {
+        let from = match from {
+            "-" => 0,
+            v => parse_arg(Some(&v.to_string()), "from timestamp")?,
+        };
+        let to = match to {
+            "+" => u64::MAX,
+            v => parse_arg(Some(&v.to_string()), "to timestamp")?,
+        };
+        Ok(Self {
+            from,
+            to,
+            ..Default::default()
+        })
+    }
+
+    // returns false if the argument is not a range option
+    fn parse_option<'a>(
+        &mut self,
+        arg: &str,
+        iter: &mut impl Iterator<Item = &'a String>,
+    ) -> Result<bool, CommandError> {
+        if arg.eq_ignore_ascii_case("count") {
+            self.count = Some(parse_arg(iter.next(), "count")?);
+        } else if arg.eq_ignore_ascii_case("aggregation") {
+            let aggregation = parse_arg::<String>(iter.next(), "aggregation")?
+                .parse()
+                .map_err(CommandError::InvalidArgument)?;
+            self.aggregation = Some((aggregation, parse_bucket(iter.next())?));
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn apply(&self, samples: Vec<(u64, f64)>, reverse: bool) -> Vec<(u64, f64)> {
+        let mut samples = match self.aggregation {
+            Some((aggregation, bucket)) => aggregation.buckets(&samples, bucket),
+            None => samples,
+        };
+        if reverse {
+            samples.reverse();
+        }
+        if let Some(count) = self.count {
+            samples.truncate(count);
+        }
+        samples
+    }
+}
+
+// parse RETENTION, LABELS, DUPLICATE_POLICY and, for TS.ADD, ON_DUPLICATE
+fn parse_options(
+    args: &[String],
+    allow_on_duplicate: bool,
+) -> Result<(TimeSeriesOptions, Option<DuplicatePolicy>), CommandError> {
+    let mut opts = TimeSeriesOptions::default();
+    let mut on_duplicate = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.to_ascii_lowercase().as_str() {
+            "retention" => opts.retention = Some(parse_arg(iter.next(), "retention")?),
+            "duplicate_policy" => {
+                opts.duplicate_policy = Some(parse_policy(iter.next())?);
+            }
+            "on_duplicate" if allow_on_duplicate => on_duplicate = Some(parse_policy(iter.next())?),
+            "labels" => {
+                let rest = iter.by_ref().collect::<Vec<_>>();
+                if rest.len() % 2 != 0 {
+                    return Err(CommandError::InvalidArgument(
+                        "LABELS must be label value pairs".to_string(),
+                    ));
+                }
+                opts.labels = Some(
+                    rest.chunks(2)
+                        .map(|c| (c[0].clone(), c[1].clone()))
+                        .collect(),
+                );
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "unknown argument {}",
+                    arg
+                )))
+            }
+        }
+    }
+    Ok((opts, on_duplicate))
+}
+
+fn parse_policy(arg: Option<&String>) -> Result<DuplicatePolicy, CommandError> {
+    parse_arg::<String>(arg, "duplicate policy")?
+        .parse()
+        .map_err(CommandError::InvalidArgument)
+}
+
+fn parse_bucket(arg: Option<&String>) -> Result<u64, CommandError> {
+    match parse_arg(arg, "bucket duration")? {
+        0 => Err(CommandError::InvalidArgument(
+            "bucket duration must be positive".to_string(),
+        )),
+        v => Ok(v),
+    }
+}
+
+fn parse_timestamp(arg: &str) -> Result<Option<u64>, CommandError> {
+    match arg {
+        "*" => Ok(None),
+        v => match parse_arg::<u64>(Some(&v.to_string()), "timestamp")? {
+            // timestamps are signed 64-bit milliseconds, as in RedisTimeSeries
+            ts if ts > i64::MAX as u64 => Err(CommandError::InvalidArgument(format!(
+                "invalid timestamp: {}",
+                v
+            ))),
+            ts => Ok(Some(ts)),
+        },
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn samples_frame(samples: Vec<(u64, f64)>) -> RespFrame {
+    let ret = samples
+        .into_iter()
+        .map(|(ts, v)| RespArray::new([RespFrame::Integer(ts as i64), RespFrame::Double(v)]).into())
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(ret).into()
+}
+
+fn ts_error(e: TimeSeriesError) -> RespFrame {
+    SimpleError::new(e.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespDecode;
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    fn sample(ts: i64, v: f64) -> RespFrame {
+        RespArray::new([RespFrame::Integer(ts), RespFrame::Double(v)]).into()
+    }
+
+    #[test]
+    fn test_ts_create_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*9\r\n$9\r\nts.create\r\n$4\r\ntemp\r\n$9\r\nRETENTION\r\n$2\r\n60\r\n$16\r\nDUPLICATE_POLICY\r\n$3\r\nmax\r\n$6\r\nLABELS\r\n$4\r\narea\r\n$4\r\neast\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: TsCreate = frame.0.unwrap().try_into()?;
+        assert_eq!(result.key, "temp");
+        assert_eq!(result.opts.retention, Some(60));
+        assert_eq!(result.opts.duplicate_policy, Some(DuplicatePolicy::Max));
+        assert_eq!(
+            result.opts.labels,
+            Some(vec![("area".to_string(), "east".to_string())])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_range_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*7\r\n$11\r\nts.revrange\r\n$4\r\ntemp\r\n$1\r\n-\r\n$1\r\n+\r\n$11\r\nAGGREGATION\r\n$5\r\nstd.p\r\n$2\r\n10\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: TsRange = frame.0.unwrap().try_into()?;
+        assert_eq!(result.key, "temp");
+        assert!(result.reverse);
+        assert_eq!(result.range.from, 0);
+        assert_eq!(result.range.to, u64::MAX);
+        assert_eq!(result.range.aggregation, Some((Aggregation::StdP, 10)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_add_rejects_timestamp_above_i64_max() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nts.add\r\n$4\r\ntemp\r\n$19\r\n9223372036854775808\r\n$1\r\n1\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<TsAdd, _> = frame.0.unwrap().try_into();
+        assert!(result.is_err());
+
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$6\r\nts.add\r\n$4\r\ntemp\r\n$19\r\n9223372036854775807\r\n$1\r\n1\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+        let result: TsAdd = frame.0.unwrap().try_into()?;
+        assert_eq!(result.timestamp, Some(i64::MAX as u64));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_add_range_commands() -> Result<()> {
+        let backend = Backend::new();
+        for (ts, v) in [(1, 1.0), (5, 3.0), (12, 4.0)] {
+            let cmd = TsAdd {
+                key: "temp".to_string(),
+                timestamp: Some(ts),
+                value: v,
+                opts: TimeSeriesOptions::default(),
+                on_duplicate: None,
+            };
+            assert_eq!(cmd.execute(&backend), RespFrame::Integer(ts as i64));
+        }
+
+        let cmd = TsIncrBy {
+            key: "temp".to_string(),
+            delta: 2.0,
+            timestamp: Some(12),
+            opts: TimeSeriesOptions::default(),
+            decrement: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(12));
+
+        let cmd = TsRange {
+            key: "temp".to_string(),
+            range: RangeOptions {
+                from: 0,
+                to: u64::MAX,
+                aggregation: Some((Aggregation::Avg, 10)),
+                count: None,
+            },
+            reverse: true,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([sample(10, 6.0), sample(0, 2.0)]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_decrby_command() -> Result<()> {
+        let backend = Backend::new();
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$9\r\nTS.DECRBY\r\n$4\r\ntemp\r\n$3\r\n1.5\r\n$9\r\ntimestamp\r\n$1\r\n5\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let cmd: crate::cmd::Command = frame.0.unwrap().try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+
+        let cmd = TsIncrBy {
+            key: "temp".to_string(),
+            delta: 2.0,
+            timestamp: Some(7),
+            opts: TimeSeriesOptions::default(),
+            decrement: true,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(7));
+        assert_eq!(
+            backend.ts_range("temp", 0, u64::MAX).unwrap(),
+            vec![(5, -1.5), (7, -3.5)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_incrby_defaults_to_the_server_clock() {
+        let backend = Backend::new();
+        let incr = |delta| TsIncrBy {
+            key: "hits".to_string(),
+            delta,
+            timestamp: None,
+            opts: TimeSeriesOptions::default(),
+            decrement: false,
+        };
+        let start = now();
+        let RespFrame::Integer(first) = incr(1.0).execute(&backend) else {
+            panic!("TS.INCRBY should reply with the timestamp");
+        };
+        assert!(first as u64 >= start);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let RespFrame::Integer(second) = incr(2.0).execute(&backend) else {
+            panic!("TS.INCRBY should reply with the timestamp");
+        };
+        assert!(second > first);
+        assert_eq!(
+            backend.ts_range("hits", 0, u64::MAX).unwrap(),
+            vec![(first as u64, 1.0), (second as u64, 3.0)]
+        );
+    }
+
+    #[test]
+    fn test_concurrent_ts_incrby_keeps_every_increment() {
+        let backend = Backend::new();
+        let threads = (0..8)
+            .map(|_| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..20000 {
+                        let opts = TimeSeriesOptions::default();
+                        backend.ts_incrby("hits".to_string(), 1.0, 1, opts).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(backend.ts_range("hits", 0, 1).unwrap(), vec![(1, 160000.0)]);
+    }
+
+    #[test]
+    fn test_ts_create_rule_and_mrange_commands() -> Result<()> {
+        let backend = Backend::new();
+        for key in ["raw", "avg"] {
+            let cmd = TsCreate {
+                key: key.to_string(),
+                opts: TimeSeriesOptions {
+                    labels: Some(vec![("kind".to_string(), key.to_string())]),
+                    ..Default::default()
+                },
+            };
+            assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        }
+        let cmd = TsCreateRule {
+            src: "raw".to_string(),
+            dest: "avg".to_string(),
+            aggregation: Aggregation::Avg,
+            bucket_duration: 10,
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd = TsMAdd {
+            samples: vec![
+                ("raw".to_string(), Some(1), 1.0),
+                ("raw".to_string(), Some(3), 3.0),
+                ("raw".to_string(), Some(11), 5.0),
+                ("missing".to_string(), Some(1), 1.0),
+            ],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(1),
+                RespFrame::Integer(3),
+                RespFrame::Integer(11),
+                SimpleError::new(TimeSeriesError::KeyNotFound.to_string()).into(),
+            ])
+            .into()
+        );
+
+        let cmd = TsMRange {
+            range: RangeOptions::new("-", "+")?,
+            with_labels: true,
+            filters: vec!["kind=avg".parse().unwrap()],
+        };
+        let labels: RespFrame =
+            RespArray::new([RespArray::new([b"kind".into(), b"avg".into()]).into()]).into();
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespArray::new([
+                b"avg".into(),
+                labels,
+                RespArray::new([sample(0, 2.0)]).into()
+            ])
+            .into()])
+            .into()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1360 @@
+use std::{
+    collections::HashSet,
+    fmt, fs, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::PathBuf,
+    str::FromStr,
+    sync::OnceLock,
+    time::Instant,
+};
+
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{backend::glob_match, split_inline_args, Backend, NotifyFlags, RespLimits};
+
+pub const DEFAULT_PORT: u16 = 6379;
+pub const DEFAULT_MAXCLIENTS: usize = 10000;
+pub const DEFAULT_DATABASES: usize = 16;
+pub const DEFAULT_TCP_KEEPALIVE: u64 = 300;
+pub const DEFAULT_TCP_BACKLOG: u32 = 511;
+
+// fds kept for listeners, logs and the like on top of one per client, like redis
+pub const RESERVED_FDS: u64 = 32;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("can't open config file '{}': {source}", path.display())]
+    Open {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("bad config {location}\n>>> '{text}'\n{reason}")]
+    Invalid {
+        location: Location,
+        text: String,
+        reason: String,
+    },
+    #[error("{0}")]
+    Usage(String),
+}
+
+// where an offending directive was given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    File { path: PathBuf, line: usize },
+    Arg(String),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::File { path, line } => write!(f, "at line {} of {}", line, path.display()),
+            Location::Arg(name) => write!(f, "in option --{}", name),
+        }
+    }
+}
+
+// what the binary was started with: `simple-redis [/path/to/redis.conf] [--name value ...]`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub config_file: Option<PathBuf>,
+    // directives given as `--port 7000`, they override the config file
+    pub overrides: Vec<(String, Vec<String>)>,
+    // check the config and exit
+    pub test_config: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Debug,
+    Verbose,
+    Notice,
+    Warning,
+    Nothing,
+}
+
+// whether tls clients must present a certificate signed by tls-ca-cert-file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsAuthClients {
+    Yes,
+    No,
+    // a certificate is checked if the client sends one
+    Optional,
+}
+
+// the classes client-output-buffer-limit is set for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientClass {
+    Normal,
+    // kept for redis.conf compatibility, there is no replication yet
+    Replica,
+    // clients subscribed to a channel or pattern
+    PubSub,
+}
+
+// bytes of replies a client has not read yet. over hard it is disconnected right away, over
+// soft once it stays there for soft_seconds. 0 turns a limit off
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimit {
+    pub hard: u64,
+    pub soft: u64,
+    pub soft_seconds: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputBufferLimits {
+    pub normal: OutputBufferLimit,
+    pub replica: OutputBufferLimit,
+    pub pubsub: OutputBufferLimit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    // the file the config was loaded from, CONFIG REWRITE writes to it
+    pub file: Option<PathBuf>,
+    pub bind: Vec<IpAddr>,
+    // 0 does not listen on tcp
+    pub port: u16,
+    // listen on this unix socket as well
+    pub unixsocket: Option<PathBuf>,
+    // mode of the socket file, 0 leaves it to the umask
+    pub unixsocketperm: u32,
+    // clients connect over tls on this port, 0 does not listen for them
+    pub tls_port: u16,
+    pub tls_cert_file: Option<PathBuf>,
+    pub tls_key_file: Option<PathBuf>,
+    // the CA client certificates are checked against
+    pub tls_ca_cert_file: Option<PathBuf>,
+    pub tls_auth_clients: TlsAuthClients,
+    pub maxclients: usize,
+    // seconds a client may stay idle before it is closed, 0 never closes it
+    pub timeout: u64,
+    // seconds between keepalive probes on idle tcp connections, 0 turns them off
+    pub tcp_keepalive: u64,
+    // connections the kernel queues before they are accepted
+    pub tcp_backlog: u32,
+    pub requirepass: Option<String>,
+    pub maxmemory: u64,
+    // snapshot after this many seconds if at least this many changes were made
+    pub save: Vec<(u64, u64)>,
+    pub appendonly: bool,
+    pub loglevel: LogLevel,
+    pub databases: usize,
+    pub notify_keyspace_events: NotifyFlags,
+    pub resp_limits: RespLimits,
+    pub client_output_buffer_limit: OutputBufferLimits,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            file: None,
+            bind: vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED)],
+            port: DEFAULT_PORT,
+            unixsocket: None,
+            unixsocketperm: 0,
+            tls_port: 0,
+            tls_cert_file: None,
+            tls_key_file: None,
+            tls_ca_cert_file: None,
+            tls_auth_clients: TlsAuthClients::Yes,
+            maxclients: DEFAULT_MAXCLIENTS,
+            timeout: 0,
+            tcp_keepalive: DEFAULT_TCP_KEEPALIVE,
+            tcp_backlog: DEFAULT_TCP_BACKLOG,
+            requirepass: None,
+            maxmemory: 0,
+            save: Vec::new(),
+            appendonly: false,
+            loglevel: LogLevel::Notice,
+            databases: DEFAULT_DATABASES,
+            notify_keyspace_events: NotifyFlags::default(),
+            resp_limits: RespLimits::default(),
+            client_output_buffer_limit: OutputBufferLimits::default(),
+        }
+    }
+}
+
+impl Options {
+    // like redis-server, a bare argument is the config file, and every argument after
+    // `--name` up to the next option is a value of that directive
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut opts = Options::default();
+        // the directive values are added to, flags end it
+        let mut directive = None;
+        for arg in args {
+            match arg.as_str() {
+                "--test-config" => opts.test_config = true,
+                "-h" | "--help" => opts.help = true,
+                "-v" | "--version" => opts.version = true,
+                _ => match arg.strip_prefix("--") {
+                    Some(name) => {
+                        opts.overrides.push((name.to_string(), Vec::new()));
+                        directive = opts.overrides.len().checked_sub(1);
+                        continue;
+                    }
+                    None => match directive {
+                        Some(i) => {
+                            opts.overrides[i].1.push(arg);
+                            continue;
+                        }
+                        None if opts.config_file.is_none() => opts.config_file = Some(arg.into()),
+                        None => {
+                            return Err(ConfigError::Usage(format!(
+                                "unexpected argument '{}', options are given as --name value",
+                                arg
+                            )))
+                        }
+                    },
+                },
+            }
+            directive = None;
+        }
+        Ok(opts)
+    }
+}
+
+impl Config {
+    // the config file if any, then the command line options on top of it
+    pub fn load(opts: &Options) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        if let Some(path) = &opts.config_file {
+            let text = fs::read_to_string(path).map_err(|source| ConfigError::Open {
+                path: path.clone(),
+                source,
+            })?;
+            config.parse_file(&text, path.clone())?;
+            config.file = Some(path.clone());
+        }
+        // save points on the command line replace the ones from the file
+        if opts
+            .overrides
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("save"))
+        {
+            config.save.clear();
+        }
+        for (name, values) in &opts.overrides {
+            config
+                .set(name, values)
+                .map_err(|reason| ConfigError::Invalid {
+                    location: Location::Arg(name.clone()),
+                    text: format!("{} {}", name, values.join(" ")),
+                    reason,
+                })?;
+        }
+        Ok(config)
+    }
+
+    // redis.conf format: one directive per line, arguments split and quoted like inline commands
+    pub fn parse_file(&mut self, text: &str, path: PathBuf) -> Result<(), ConfigError> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| ConfigError::Invalid {
+                location: Location::File {
+                    path: path.clone(),
+                    line: i + 1,
+                },
+                text: line.to_string(),
+                reason: reason.to_string(),
+            };
+            let args = split_inline_args(line.as_bytes())
+                .ok_or_else(|| invalid("Unbalanced quotes in configuration line"))?;
+            let mut args = args
+                .into_iter()
+                .map(|a| String::from_utf8_lossy(&a).into_owned());
+            let Some(name) = args.next() else {
+                continue;
+            };
+            self.set(&name, &args.collect::<Vec<_>>())
+                .map_err(|reason| invalid(&reason))?;
+        }
+        Ok(())
+    }
+
+    // apply a single directive, the error says what is wrong with it
+    pub fn set(&mut self, name: &str, args: &[String]) -> Result<(), String> {
+        let param =
+            param(name).ok_or_else(|| "Bad directive or wrong number of arguments".to_string())?;
+        (param.set)(self, args)
+    }
+
+    // the directive's values as they are written in a config file
+    pub fn get(&self, name: &str) -> Option<Vec<String>> {
+        param(name).map(|param| (param.get)(self))
+    }
+
+    // put every setting into effect on the backend, which keeps the config for CONFIG GET
+    pub fn apply(&self, backend: &Backend) {
+        for param in PARAMS {
+            (param.apply)(self, backend);
+        }
+        *backend.config.write().unwrap_or_else(|e| e.into_inner()) = self.clone();
+    }
+
+    // the config file with every directive set to its current value. comments and the order
+    // of lines are kept, directives not in the file are appended if they are not the default
+    pub fn rewrite(&self, text: &str) -> String {
+        let mut lines = Vec::new();
+        let mut written = HashSet::new();
+        for line in text.lines() {
+            let name = split_inline_args(line.trim().as_bytes())
+                .and_then(|args| args.into_iter().next())
+                .filter(|_| !line.trim_start().starts_with('#'));
+            match name.and_then(|name| param(&String::from_utf8_lossy(&name))) {
+                // repeated directives like save are folded into their first line
+                Some(param) => {
+                    if written.insert(param.name) {
+                        lines.push(self.line(param));
+                    }
+                }
+                None => lines.push(line.to_string()),
+            }
+        }
+
+        let default = Config::default();
+        let missing = PARAMS
+            .iter()
+            .filter(|p| !written.contains(p.name) && (p.get)(self) != (p.get)(&default))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            if lines.last().is_some_and(|l| !l.is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push("# Generated by CONFIG REWRITE".to_string());
+            lines.extend(missing.into_iter().map(|p| self.line(p)));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn line(&self, param: &Param) -> String {
+        std::iter::once(param.name.to_string())
+            .chain((param.get)(self).iter().map(|arg| quote(arg)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+// a parameter of the configuration: how it is read, written and put into effect
+pub struct Param {
+    pub name: &'static str,
+    // CONFIG SET can change it while the server runs
+    pub mutable: bool,
+    // a list of values, CONFIG SET splits its value on spaces
+    multi: bool,
+    get: fn(&Config) -> Vec<String>,
+    set: fn(&mut Config, &[String]) -> Result<(), String>,
+    // called with the new config when the value changed
+    apply: fn(&Config, &Backend),
+}
+
+pub static PARAMS: &[Param] = &[
+    Param {
+        name: "bind",
+        mutable: false,
+        multi: true,
+        get: |c| c.bind.iter().map(|ip| ip.to_string()).collect(),
+        set: |c, args| {
+            if args.is_empty() {
+                return Err("wrong number of arguments".to_string());
+            }
+            c.bind = args
+                .iter()
+                .map(|addr| {
+                    parse_bind(addr).ok_or_else(|| format!("Invalid bind address '{}'", addr))
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "port",
+        mutable: false,
+        multi: false,
+        get: |c| vec![c.port.to_string()],
+        set: |c, args| {
+            c.port = one(args)?.parse().map_err(|_| "Invalid port")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "unixsocket",
+        mutable: false,
+        multi: false,
+        get: |c| path_value(&c.unixsocket),
+        set: |c, args| {
+            c.unixsocket = optional_path(one(args)?);
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "unixsocketperm",
+        mutable: false,
+        multi: false,
+        // octal like chmod
+        get: |c| vec![format!("{:o}", c.unixsocketperm)],
+        set: |c, args| {
+            c.unixsocketperm = u32::from_str_radix(one(args)?, 8)
+                .ok()
+                .filter(|perm| *perm <= 0o777)
+                .ok_or("Invalid socket file permissions")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "tls-port",
+        mutable: false,
+        multi: false,
+        get: |c| vec![c.tls_port.to_string()],
+        set: |c, args| {
+            c.tls_port = one(args)?.parse().map_err(|_| "Invalid tls-port")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    // CONFIG SET of any tls parameter reloads the certificates, see Backend::config_set
+    Param {
+        name: "tls-cert-file",
+        mutable: true,
+        multi: false,
+        get: |c| path_value(&c.tls_cert_file),
+        set: |c, args| {
+            c.tls_cert_file = optional_path(one(args)?);
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "tls-key-file",
+        mutable: true,
+        multi: false,
+        get: |c| path_value(&c.tls_key_file),
+        set: |c, args| {
+            c.tls_key_file = optional_path(one(args)?);
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "tls-ca-cert-file",
+        mutable: true,
+        multi: false,
+        get: |c| path_value(&c.tls_ca_cert_file),
+        set: |c, args| {
+            c.tls_ca_cert_file = optional_path(one(args)?);
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "tls-auth-clients",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.tls_auth_clients.to_string()],
+        set: |c, args| {
+            c.tls_auth_clients = one(args)?.parse()?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "maxclients",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.maxclients.to_string()],
+        set: |c, args| {
+            c.maxclients = positive(one(args)?, "Invalid max clients limit")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "timeout",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.timeout.to_string()],
+        set: |c, args| {
+            c.timeout = one(args)?.parse().map_err(|_| "Invalid timeout value")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    // applies to connections accepted from now on
+    Param {
+        name: "tcp-keepalive",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.tcp_keepalive.to_string()],
+        set: |c, args| {
+            c.tcp_keepalive = one(args)?.parse().map_err(|_| "Invalid tcp-keepalive")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "tcp-backlog",
+        mutable: false,
+        multi: false,
+        get: |c| vec![c.tcp_backlog.to_string()],
+        set: |c, args| {
+            c.tcp_backlog = one(args)?.parse().map_err(|_| "Invalid backlog value")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "requirepass",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.requirepass.clone().unwrap_or_default()],
+        // an empty password turns authentication off
+        set: |c, args| {
+            c.requirepass = Some(one(args)?.clone()).filter(|p| !p.is_empty());
+            Ok(())
+        },
+        apply: |c, backend| backend.set_requirepass(c.requirepass.clone()),
+    },
+    Param {
+        name: "maxmemory",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.maxmemory.to_string()],
+        set: |c, args| {
+            c.maxmemory = parse_memory(one(args)?)?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "save",
+        mutable: true,
+        multi: true,
+        get: |c| match c.save.is_empty() {
+            true => vec![String::new()],
+            false => c
+                .save
+                .iter()
+                .flat_map(|(seconds, changes)| [seconds.to_string(), changes.to_string()])
+                .collect(),
+        },
+        // `save ""` turns snapshots off, every other save line adds to them
+        set: |c, args| match args {
+            [arg] if arg.is_empty() => {
+                c.save.clear();
+                Ok(())
+            }
+            _ if args.is_empty() || !args.len().is_multiple_of(2) => {
+                Err("Invalid save parameters".to_string())
+            }
+            _ => {
+                for pair in args.chunks(2) {
+                    match (pair[0].parse(), pair[1].parse()) {
+                        (Ok(seconds), Ok(changes)) => c.save.push((seconds, changes)),
+                        _ => return Err("Invalid save parameters".to_string()),
+                    }
+                }
+                Ok(())
+            }
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "appendonly",
+        mutable: true,
+        multi: false,
+        get: |c| vec![yes_or_no(c.appendonly)],
+        set: |c, args| {
+            c.appendonly = yes_no(one(args)?)?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "loglevel",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.loglevel.to_string()],
+        set: |c, args| {
+            c.loglevel = one(args)?.parse()?;
+            Ok(())
+        },
+        apply: |c, _| {
+            if let Some(hook) = LOGLEVEL_HOOK.get() {
+                hook(c.loglevel);
+            }
+        },
+    },
+    Param {
+        name: "databases",
+        mutable: false,
+        multi: false,
+        get: |c| vec![c.databases.to_string()],
+        set: |c, args| {
+            c.databases = positive(one(args)?, "Invalid number of databases")?;
+            Ok(())
+        },
+        apply: |_, _| {},
+    },
+    Param {
+        name: "notify-keyspace-events",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.notify_keyspace_events.to_string()],
+        set: |c, args| {
+            c.notify_keyspace_events = one(args)?
+                .parse()
+                .map_err(|_| "Invalid event class character. Use 'Ag$lshzxeKEtmdn'.")?;
+            Ok(())
+        },
+        apply: |c, backend| backend.set_notify_keyspace_events(c.notify_keyspace_events),
+    },
+    Param {
+        name: "proto-max-bulk-len",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.resp_limits.max_bulk_len.to_string()],
+        set: |c, args| {
+            c.resp_limits.max_bulk_len = parse_memory(one(args)?)? as usize;
+            Ok(())
+        },
+        // connections pick the new limits up before their next request
+        apply: |c, backend| backend.set_resp_limits(c.resp_limits),
+    },
+    Param {
+        name: "client-query-buffer-limit",
+        mutable: true,
+        multi: false,
+        get: |c| vec![c.resp_limits.max_query_buffer.to_string()],
+        set: |c, args| {
+            c.resp_limits.max_query_buffer = parse_memory(one(args)?)? as usize;
+            Ok(())
+        },
+        apply: |c, backend| backend.set_resp_limits(c.resp_limits),
+    },
+    // `class hard soft soft_seconds`, repeated for as many classes as are set
+    Param {
+        name: "client-output-buffer-limit",
+        mutable: true,
+        multi: true,
+        get: |c| {
+            [
+                ClientClass::Normal,
+                ClientClass::Replica,
+                ClientClass::PubSub,
+            ]
+            .into_iter()
+            .flat_map(|class| {
+                let limit = c.client_output_buffer_limit.get(class);
+                [
+                    class.to_string(),
+                    limit.hard.to_string(),
+                    limit.soft.to_string(),
+                    limit.soft_seconds.to_string(),
+                ]
+            })
+            .collect()
+        },
+        set: |c, args| {
+            if args.is_empty() || !args.len().is_multiple_of(4) {
+                return Err("Wrong number of arguments in buffer limit configuration.".to_string());
+            }
+            let mut limits = c.client_output_buffer_limit;
+            for arg in args.chunks(4) {
+                let class = arg[0].parse()?;
+                let invalid =
+                    "Error in hard, soft or soft_seconds setting in buffer limit configuration.";
+                *limits.get_mut(class) = OutputBufferLimit {
+                    hard: parse_memory(&arg[1]).map_err(|_| invalid)?,
+                    soft: parse_memory(&arg[2]).map_err(|_| invalid)?,
+                    soft_seconds: arg[3].parse().map_err(|_| invalid)?,
+                };
+            }
+            c.client_output_buffer_limit = limits;
+            Ok(())
+        },
+        // connections and publishers read the limits for every reply
+        apply: |_, _| {},
+    },
+];
+
+// set by the binary, so a new loglevel changes what is logged
+static LOGLEVEL_HOOK: OnceLock<Box<dyn Fn(LogLevel) + Send + Sync>> = OnceLock::new();
+
+pub fn on_loglevel_change(hook: impl Fn(LogLevel) + Send + Sync + 'static) {
+    let _ = LOGLEVEL_HOOK.set(Box::new(hook));
+}
+
+fn param(name: &str) -> Option<&'static Param> {
+    PARAMS.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+impl Backend {
+    pub fn config(&self) -> Config {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn output_buffer_limit(&self, class: ClientClass) -> OutputBufferLimit {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .client_output_buffer_limit
+            .get(class)
+    }
+
+    // seconds a client may stay idle, read by every connection
+    pub fn client_timeout(&self) -> u64 {
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .timeout
+    }
+
+    // the parameters matching any of the glob patterns, with their values
+    pub fn config_get(&self, patterns: &[String]) -> Vec<(&'static str, String)> {
+        let config = self.config();
+        PARAMS
+            .iter()
+            .filter(|p| {
+                patterns
+                    .iter()
+                    .any(|pattern| glob_match(&pattern.to_ascii_lowercase(), p.name))
+            })
+            .map(|p| (p.name, (p.get)(&config).join(" ")))
+            .collect()
+    }
+
+    // set every parameter or none of them, then put the changed ones into effect
+    pub fn config_set(&self, pairs: &[(String, String)]) -> Result<(), String> {
+        let mut config = self.config.write().unwrap_or_else(|e| e.into_inner());
+        let mut new = config.clone();
+        let mut seen = HashSet::new();
+        for (name, value) in pairs {
+            let param = param(name).ok_or_else(|| {
+                format!(
+                    "Unknown option or number of arguments for CONFIG SET - '{}'",
+                    name
+                )
+            })?;
+            if !seen.insert(param.name) {
+                return Err(format!("Duplicate parameter - {}", name));
+            }
+            let failed = |reason: &str| {
+                format!(
+                    "CONFIG SET failed (possibly related to argument '{}') - {}",
+                    name, reason
+                )
+            };
+            if !param.mutable {
+                return Err(failed("can't set immutable config"));
+            }
+            let args = match param.multi {
+                true if !value.trim().is_empty() => {
+                    value.split_whitespace().map(String::from).collect()
+                }
+                _ => vec![value.clone()],
+            };
+            // the save points are replaced, not added to like in a config file
+            if param.name == "save" {
+                new.save.clear();
+            }
+            (param.set)(&mut new, &args).map_err(|reason| failed(&reason))?;
+        }
+        // setting a tls parameter reloads the certificates even if their paths are the same, so
+        // rotated files are picked up. if they can't be loaded nothing changes
+        if new.tls_port != 0 {
+            if let Some(name) = seen.iter().find(|name| name.starts_with("tls-")) {
+                self.load_tls(&new).map_err(|e| {
+                    format!(
+                        "CONFIG SET failed (possibly related to argument '{}') - Unable to update TLS configuration: {}",
+                        name, e
+                    )
+                })?;
+            }
+        }
+        // like redis, a higher maxclients fails if the open files limit can't be raised to fit it
+        if new.maxclients > config.maxclients {
+            let failed = |reason: String| {
+                format!(
+                    "CONFIG SET failed (possibly related to argument 'maxclients') - {}",
+                    reason
+                )
+            };
+            match raise_open_files_limit(new.maxclients) {
+                Ok(limit) if limit >= new.maxclients as u64 + RESERVED_FDS => {}
+                Ok(limit) => {
+                    return Err(failed(format!(
+                        "The operating system is not able to handle the specified number of clients, try with {}",
+                        limit.saturating_sub(RESERVED_FDS)
+                    )))
+                }
+                Err(e) => {
+                    return Err(failed(format!(
+                        "Unable to obtain the current NOFILE limit: {}",
+                        e
+                    )))
+                }
+            }
+        }
+        for param in PARAMS {
+            if (param.get)(&new) != (param.get)(&config) {
+                (param.apply)(&new, self);
+            }
+        }
+        *config = new;
+        Ok(())
+    }
+
+    // write the running config back to the file the server was started with
+    pub fn config_rewrite(&self) -> Result<(), String> {
+        let config = self.config();
+        let Some(path) = &config.file else {
+            return Err("The server is running without a config file".to_string());
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Rewriting config file: {}", e)),
+        };
+        // written next to the file and renamed over it, so a crash never leaves half of it
+        let tmp = path.with_file_name(format!(
+            ".{}.rewrite",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        fs::write(&tmp, config.rewrite(&text))
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| format!("Rewriting config file: {}", e))
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Debug => "debug",
+            LogLevel::Verbose => "verbose",
+            LogLevel::Notice => "notice",
+            LogLevel::Warning => "warning",
+            LogLevel::Nothing => "nothing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ClientClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ClientClass::Normal => "normal",
+            ClientClass::Replica => "replica",
+            ClientClass::PubSub => "pubsub",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ClientClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "normal" => Ok(ClientClass::Normal),
+            "replica" | "slave" => Ok(ClientClass::Replica),
+            "pubsub" => Ok(ClientClass::PubSub),
+            _ => Err("Invalid client class specified in buffer limit configuration.".to_string()),
+        }
+    }
+}
+
+impl Default for OutputBufferLimits {
+    // like redis, normal clients are not limited
+    fn default() -> Self {
+        Self {
+            normal: OutputBufferLimit::default(),
+            replica: OutputBufferLimit {
+                hard: 256 * 1024 * 1024,
+                soft: 64 * 1024 * 1024,
+                soft_seconds: 60,
+            },
+            pubsub: OutputBufferLimit {
+                hard: 32 * 1024 * 1024,
+                soft: 8 * 1024 * 1024,
+                soft_seconds: 60,
+            },
+        }
+    }
+}
+
+impl OutputBufferLimits {
+    pub fn get(&self, class: ClientClass) -> OutputBufferLimit {
+        match class {
+            ClientClass::Normal => self.normal,
+            ClientClass::Replica => self.replica,
+            ClientClass::PubSub => self.pubsub,
+        }
+    }
+
+    fn get_mut(&mut self, class: ClientClass) -> &mut OutputBufferLimit {
+        match class {
+            ClientClass::Normal => &mut self.normal,
+            ClientClass::Replica => &mut self.replica,
+            ClientClass::PubSub => &mut self.pubsub,
+        }
+    }
+}
+
+impl OutputBufferLimit {
+    // whether `pending` unread bytes break the limit, `over_soft_since` keeps when they went over
+    // the soft one
+    pub fn check(
+        &self,
+        pending: usize,
+        over_soft_since: &mut Option<Instant>,
+    ) -> Result<(), String> {
+        let pending = pending as u64;
+        if self.hard > 0 && pending > self.hard {
+            return Err(format!(
+                "{} bytes over the hard limit of {}",
+                pending, self.hard
+            ));
+        }
+        if self.soft == 0 || pending <= self.soft {
+            *over_soft_since = None;
+            return Ok(());
+        }
+        let since = *over_soft_since.get_or_insert_with(Instant::now);
+        if since.elapsed().as_secs() >= self.soft_seconds {
+            return Err(format!(
+                "{} bytes over the soft limit of {} for {} seconds",
+                pending, self.soft, self.soft_seconds
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TlsAuthClients {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TlsAuthClients::Yes => "yes",
+            TlsAuthClients::No => "no",
+            TlsAuthClients::Optional => "optional",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for TlsAuthClients {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "yes" => Ok(TlsAuthClients::Yes),
+            "no" => Ok(TlsAuthClients::No),
+            "optional" => Ok(TlsAuthClients::Optional),
+            _ => Err("argument must be 'yes', 'no' or 'optional'".to_string()),
+        }
+    }
+}
+
+impl LogLevel {
+    // the tracing filter a level maps to, per frame logs only show up at verbose
+    pub fn filter(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "trace",
+            LogLevel::Verbose => "debug",
+            LogLevel::Notice => "info",
+            LogLevel::Warning => "warn",
+            LogLevel::Nothing => "off",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "debug" => Ok(LogLevel::Debug),
+            "verbose" => Ok(LogLevel::Verbose),
+            "notice" => Ok(LogLevel::Notice),
+            "warning" => Ok(LogLevel::Warning),
+            "nothing" => Ok(LogLevel::Nothing),
+            _ => Err(
+                "Invalid log level. Must be one of debug, verbose, notice, warning, nothing"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+fn one(args: &[String]) -> Result<&String, String> {
+    match args {
+        [arg] => Ok(arg),
+        _ => Err("wrong number of arguments".to_string()),
+    }
+}
+
+fn positive(arg: &str, err: &str) -> Result<usize, String> {
+    match arg.parse() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(err.to_string()),
+    }
+}
+
+fn yes_or_no(b: bool) -> String {
+    if b { "yes" } else { "no" }.to_string()
+}
+
+fn yes_no(arg: &str) -> Result<bool, String> {
+    match arg.to_ascii_lowercase().as_str() {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err("argument must be 'yes' or 'no'".to_string()),
+    }
+}
+
+// an empty path unsets it
+fn optional_path(arg: &str) -> Option<PathBuf> {
+    (!arg.is_empty()).then(|| arg.into())
+}
+
+fn path_value(path: &Option<PathBuf>) -> Vec<String> {
+    vec![path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default()]
+}
+
+// `*` and `::*` listen on every address, a leading `-` marks an address as optional in redis
+fn parse_bind(addr: &str) -> Option<IpAddr> {
+    match addr.strip_prefix('-').unwrap_or(addr) {
+        "*" => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        "::*" => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+        addr => addr.parse().ok(),
+    }
+}
+
+// quote an argument for a config line when splitting it would not give it back
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !matches!(c, '"' | '\'' | '\\'));
+    if plain {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => quoted.push_str(&format!("\\x{:02x}", c as u8)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// a byte count like 100mb, k/m/g are powers of 1000, kb/mb/gb powers of 1024 as in redis
+pub fn parse_memory(arg: &str) -> Result<u64, String> {
+    let lower = arg.to_ascii_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(lower.len());
+    let (digits, unit) = lower.split_at(split);
+    let unit = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return Err(format!("Invalid memory amount '{}'", arg)),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("Invalid memory amount '{}'", arg))
+}
+
+// raise the open files limit as far as maxclients needs and the hard limit allows, the soft
+// limit in effect afterwards is returned
+pub fn raise_open_files_limit(maxclients: usize) -> io::Result<u64> {
+    let wanted = maxclients as u64 + RESERVED_FDS;
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the struct it is given
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if limit.rlim_cur >= wanted {
+        return Ok(limit.rlim_cur);
+    }
+    let raised = libc::rlimit {
+        rlim_cur: wanted.min(limit.rlim_max),
+        rlim_max: limit.rlim_max,
+    };
+    // SAFETY: setrlimit only reads the struct it is given
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raised) } != 0 {
+        warn!(
+            "Server can't set maximum open files to {} because of OS error: {}.",
+            raised.rlim_cur,
+            io::Error::last_os_error()
+        );
+        return Ok(limit.rlim_cur);
+    }
+    if raised.rlim_cur >= wanted {
+        info!("Increased maximum number of open files to {}", wanted);
+    }
+    Ok(raised.rlim_cur)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_config_file() {
+        let text = r#"
+# a comment
+bind 127.0.0.1 -::1
+port 7000
+unixsocket /tmp/redis.sock
+unixsocketperm 770
+tls-port 6380
+tls-cert-file redis.crt
+tls-key-file redis.key
+tls-auth-clients optional
+maxclients 100
+timeout 300
+tcp-keepalive 60
+tcp-backlog 1024
+requirepass "s3cret pass"
+maxmemory 100mb
+save 900 1
+save 300 10
+appendonly yes
+loglevel warning
+databases 4
+notify-keyspace-events KEA
+proto-max-bulk-len 1gb
+client-output-buffer-limit normal 1mb 512kb 10
+client-output-buffer-limit slave 0 0 0 pubsub 64mb 16mb 120
+"#;
+        let mut config = Config::default();
+        config.parse_file(text, "redis.conf".into()).unwrap();
+        assert_eq!(
+            config.bind,
+            vec![
+                "127.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(config.port, 7000);
+        assert_eq!(config.unixsocket, Some("/tmp/redis.sock".into()));
+        assert_eq!(config.unixsocketperm, 0o770);
+        assert_eq!(config.tls_port, 6380);
+        assert_eq!(config.tls_cert_file, Some("redis.crt".into()));
+        assert_eq!(config.tls_key_file, Some("redis.key".into()));
+        assert_eq!(config.tls_ca_cert_file, None);
+        assert_eq!(config.tls_auth_clients, TlsAuthClients::Optional);
+        assert_eq!(config.maxclients, 100);
+        assert_eq!(config.timeout, 300);
+        assert_eq!(config.tcp_keepalive, 60);
+        assert_eq!(config.tcp_backlog, 1024);
+        assert_eq!(config.requirepass.as_deref(), Some("s3cret pass"));
+        assert_eq!(config.maxmemory, 100 * 1024 * 1024);
+        assert_eq!(config.save, vec![(900, 1), (300, 10)]);
+        assert!(config.appendonly);
+        assert_eq!(config.loglevel, LogLevel::Warning);
+        assert_eq!(config.databases, 4);
+        assert_eq!(config.notify_keyspace_events, "KEA".parse().unwrap());
+        assert_eq!(config.resp_limits.max_bulk_len, 1024 * 1024 * 1024);
+        assert_eq!(
+            config.client_output_buffer_limit,
+            OutputBufferLimits {
+                normal: OutputBufferLimit {
+                    hard: 1024 * 1024,
+                    soft: 512 * 1024,
+                    soft_seconds: 10,
+                },
+                replica: OutputBufferLimit::default(),
+                pubsub: OutputBufferLimit {
+                    hard: 64 * 1024 * 1024,
+                    soft: 16 * 1024 * 1024,
+                    soft_seconds: 120,
+                },
+            }
+        );
+
+        config.parse_file("save \"\"", "redis.conf".into()).unwrap();
+        assert!(config.save.is_empty());
+    }
+
+    #[test]
+    fn test_config_errors_name_the_line() {
+        let mut config = Config::default();
+        let err = config
+            .parse_file("port 7000\n\nport 99999\n", "redis.conf".into())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bad config at line 3 of redis.conf\n>>> 'port 99999'\nInvalid port"
+        );
+
+        for line in [
+            "bind 300.0.0.1",
+            "unixsocketperm 800",
+            "tls-auth-clients maybe",
+            "maxclients 0",
+            "maxmemory 10xb",
+            "save 900",
+            "appendonly maybe",
+            "loglevel loud",
+            "databases",
+            "requirepass \"a",
+            "client-output-buffer-limit normal 1mb 512kb",
+            "client-output-buffer-limit master 0 0 0",
+            "client-output-buffer-limit pubsub 1mb 512kb soon",
+            "no-such-directive yes",
+        ] {
+            let ret = Config::default().parse_file(line, "redis.conf".into());
+            assert!(
+                matches!(ret, Err(ConfigError::Invalid { .. })),
+                "{} should be rejected",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_command_line_overrides_config_file() {
+        let path = std::env::temp_dir().join(format!("simple-redis-{}.conf", std::process::id()));
+        fs::write(&path, "port 7000\nloglevel debug\nsave 900 1\n").unwrap();
+
+        let opts = Options::parse(args(&[
+            path.to_str().unwrap(),
+            "--port",
+            "7001",
+            "--save",
+            "60",
+            "100",
+            "--test-config",
+        ]))
+        .unwrap();
+        assert!(opts.test_config);
+        let config = Config::load(&opts).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.port, 7001);
+        assert_eq!(config.loglevel, LogLevel::Debug);
+        assert_eq!(config.save, vec![(60, 100)]);
+
+        let opts = Options::parse(args(&["--port", "x"])).unwrap();
+        let err = Config::load(&opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bad config in option --port\n>>> 'port x'\nInvalid port"
+        );
+
+        let opts = Options::parse(args(&["--test-config", "redis.conf", "--port", "1"])).unwrap();
+        assert_eq!(opts.config_file, Some("redis.conf".into()));
+        assert_eq!(opts.overrides, vec![("port".to_string(), args(&["1"]))]);
+        assert!(Options::parse(args(&["a.conf", "b.conf"])).is_err());
+        assert!(matches!(
+            Config::load(&Options::parse(args(&["/no/such/redis.conf"])).unwrap()),
+            Err(ConfigError::Open { .. })
+        ));
+    }
+
+    #[test]
+    fn test_config_rewrite_keeps_comments() {
+        let text =
+            "# the port\nport 7000\n\n# snapshots\nsave 900 1\nsave 300 10\nloglevel notice\n";
+        let mut config = Config::default();
+        config.parse_file(text, "redis.conf".into()).unwrap();
+        config.set("save", &args(&[""])).unwrap();
+        config.set("requirepass", &args(&["a \"b\""])).unwrap();
+        config.set("maxclients", &args(&["64"])).unwrap();
+
+        let rewritten = config.rewrite(text);
+        assert_eq!(
+            rewritten,
+            "# the port\nport 7000\n\n# snapshots\nsave \"\"\nloglevel notice\n\n# Generated by CONFIG REWRITE\nmaxclients 64\nrequirepass \"a \\\"b\\\"\"\n"
+        );
+
+        let mut reloaded = Config::default();
+        reloaded
+            .parse_file(&rewritten, "redis.conf".into())
+            .unwrap();
+        assert_eq!(reloaded, config);
+        assert_eq!(config.rewrite(&rewritten), rewritten);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("100"), Ok(100));
+        assert_eq!(parse_memory("1k"), Ok(1000));
+        assert_eq!(parse_memory("1KB"), Ok(1024));
+        assert_eq!(parse_memory("2gb"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_memory("-1").is_err());
+        assert!(parse_memory("mb").is_err());
+        assert!(parse_memory("99999999999gb").is_err());
+    }
+
+    #[test]
+    fn test_output_buffer_limit() {
+        let limit = OutputBufferLimit {
+            hard: 100,
+            soft: 50,
+            soft_seconds: 60,
+        };
+        let mut since = None;
+        assert!(limit.check(50, &mut since).is_ok());
+        assert_eq!(since, None);
+        // over soft, the client has soft_seconds to catch up
+        assert!(limit.check(80, &mut since).is_ok());
+        let over = since.unwrap();
+        assert!(limit.check(90, &mut since).is_ok());
+        assert_eq!(since, Some(over));
+        assert_eq!(
+            limit.check(101, &mut since),
+            Err("101 bytes over the hard limit of 100".to_string())
+        );
+        assert!(limit.check(10, &mut since).is_ok());
+        assert_eq!(since, None);
+
+        since = Some(Instant::now() - Duration::from_secs(60));
+        assert_eq!(
+            limit.check(80, &mut since),
+            Err("80 bytes over the soft limit of 50 for 60 seconds".to_string())
+        );
+        // no limits at all
+        assert!(OutputBufferLimit::default()
+            .check(usize::MAX, &mut None)
+            .is_ok());
+    }
+}
@@ -1,10 +1,12 @@
 mod backend;
 mod cmd;
+mod config;
 pub mod network;
 mod resp;
 mod respv2;
 pub use backend::*;
 pub use cmd::*;
+pub use config::*;
 
 pub use resp::*;
 pub use respv2::*;
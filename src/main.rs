@@ -1,28 +1,378 @@
+use std::{
+    env, fs, io, net::SocketAddr, os::unix::fs::PermissionsExt, path::Path, process, time::Duration,
+};
+
 use anyhow::Result;
-use simple_redis::{network, Backend};
-use tokio::net::TcpListener;
-use tracing::{info, warn};
+use simple_redis::{
+    network, on_loglevel_change, raise_open_files_limit, Backend, ClientSlot, Config, Options,
+    ShutdownOptions, EXIT_OK, RESERVED_FDS,
+};
+use socket2::{Domain, SockAddr, SockRef, Socket, TcpKeepalive, Type};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpSocket, TcpStream, UnixListener},
+    signal::unix::{signal, SignalKind},
+    task::JoinSet,
+    time::timeout,
+};
+use tokio_util::task::TaskTracker;
+use tracing::{debug, info, warn};
+use tracing_subscriber::{fmt, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter};
+
+const USAGE: &str = "Usage: simple-redis [/path/to/redis.conf] [options] [--test-config]
+       simple-redis -v or --version
+       simple-redis -h or --help
+
+Any redis.conf directive can be given as an option, it overrides the config file:
+       simple-redis /etc/redis/redis.conf --port 7777 --loglevel verbose
+       simple-redis --bind 127.0.0.1 ::1 --requirepass secret";
+
+// how long connections get to finish the commands they are serving once the server stops
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// a tls client that has not finished its handshake by then is dropped
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// a failed accept is retried after this long, out of fds it would fail again right away
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::fmt::init();
-    let addr = "0.0.0.0:6379";
-    info!("Simple-Redis-Server is listening on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
+    let opts = Options::parse(env::args().skip(1)).unwrap_or_else(|e| exit_with(e));
+    if opts.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if opts.version {
+        println!("simple-redis v{}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let mut config = Config::load(&opts).unwrap_or_else(|e| exit_with(e));
+    if opts.test_config {
+        match &opts.config_file {
+            Some(path) => println!("configuration file {} is valid", path.display()),
+            None => println!("configuration is valid"),
+        }
+        return Ok(());
+    }
+
+    // RUST_LOG takes precedence over loglevel, also when it is changed with CONFIG SET
+    let filter = EnvFilter::try_from_default_env();
+    let from_env = filter.is_ok();
+    let filter = filter.unwrap_or_else(|_| EnvFilter::new(config.loglevel.filter()));
+    let (filter, reload) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
+    if !from_env {
+        on_loglevel_change(move |level| {
+            let _ = reload.reload(EnvFilter::new(level.filter()));
+        });
+    }
+    if !config.save.is_empty() || config.appendonly {
+        warn!("persistence is not supported, the dataset can't be saved on shutdown");
+    }
+    check_somaxconn(config.tcp_backlog);
+    config.maxclients = adjust_open_files_limit(config.maxclients).unwrap_or_else(|e| exit_with(e));
+    if config.maxmemory > 0 {
+        warn!("maxmemory is not enforced, keys are never evicted");
+    }
+
     let backend = Backend::default();
+    config.apply(&backend);
+    let mut servers = JoinSet::new();
+    let connections = TaskTracker::new();
+    // subscribe before accepting, a client may ask to shut down right away
+    let mut shutdown = backend.shutdown_signal();
+    // like redis, port 0 turns tcp off
+    let bind = if config.port == 0 {
+        &[][..]
+    } else {
+        &config.bind
+    };
+    for ip in bind {
+        let addr = SocketAddr::new(*ip, config.port);
+        let listener = bind_tcp(addr, config.tcp_backlog)?;
+        info!(
+            "Simple-Redis-Server is listening on {}",
+            listener.local_addr()?
+        );
+        servers.spawn(serve(listener, backend.clone(), connections.clone()));
+    }
+    if config.tls_port != 0 {
+        backend
+            .load_tls(&config)
+            .unwrap_or_else(|e| exit_with(format!("Failed to configure TLS: {}", e)));
+        for ip in &config.bind {
+            let addr = SocketAddr::new(*ip, config.tls_port);
+            let listener = bind_tcp(addr, config.tcp_backlog)?;
+            info!(
+                "Simple-Redis-Server is listening on {} (tls)",
+                listener.local_addr()?
+            );
+            servers.spawn(serve_tls(listener, backend.clone(), connections.clone()));
+        }
+    }
+    if let Some(path) = &config.unixsocket {
+        let listener = bind_unix(path, config.unixsocketperm, config.tcp_backlog)?;
+        info!("Simple-Redis-Server is listening on {}", path.display());
+        servers.spawn(serve_unix(listener, backend.clone(), connections.clone()));
+    }
+    if servers.is_empty() {
+        exit_with("Configured to not listen anywhere, exiting.");
+    }
+
+    tokio::select! {
+        // accept loops only return on errors
+        Some(ret) = servers.join_next() => return ret?,
+        ret = terminate() => {
+            ret?;
+            info!("Received a termination signal, shutting down");
+            let force = ShutdownOptions {
+                force: true,
+                ..Default::default()
+            };
+            if let Err(e) = backend.shutdown(force) {
+                warn!("{}", e);
+            }
+        }
+        _ = shutdown.wait_for(Option::is_some) => {}
+    }
+    let code = shutdown.borrow().unwrap_or(EXIT_OK);
+
+    // stop accepting, then give the connections time to finish what they are serving
+    servers.abort_all();
+    connections.close();
+    tokio::select! {
+        ret = tokio::time::timeout(SHUTDOWN_TIMEOUT, connections.wait()) => {
+            if ret.is_err() {
+                warn!("{} connections are still open, exiting anyway", connections.len());
+            }
+        }
+        _ = terminate() => warn!("Received a second signal, exiting now"),
+    }
+    if let Some(path) = &config.unixsocket {
+        if let Err(e) = fs::remove_file(path) {
+            warn!(
+                "Error removing the unix socket file {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    info!("Simple-Redis-Server is now ready to exit, bye bye...");
+    process::exit(code)
+}
+
+async fn serve(listener: TcpListener, backend: Backend, connections: TaskTracker) -> Result<()> {
+    loop {
+        let (stream, raddr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                accept_failed(err).await;
+                continue;
+            }
+        };
+        // a peer that is already gone only costs its own connection
+        if let Err(err) = set_tcp_options(&stream, &backend) {
+            warn!("Error configuring the connection from {}: {}", raddr, err);
+            continue;
+        }
+        connections.spawn(handle_connection(
+            stream,
+            raddr.to_string(),
+            backend.clone(),
+            backend.try_connect(),
+        ));
+    }
+}
+
+// the handshake runs on the connection's task, so a slow client does not hold up the others.
+// it uses the certificates loaded when the connection is accepted. the client is counted
+// against maxclients from then on, and is only given so long to finish it
+async fn serve_tls(
+    listener: TcpListener,
+    backend: Backend,
+    connections: TaskTracker,
+) -> Result<()> {
     loop {
-        let backend_cloned = backend.clone();
-        let (stream, raddr) = listener.accept().await?;
-        info!("Accepted connection from {}", raddr);
-        tokio::spawn(async move {
-            match network::stream_handler(stream, backend_cloned).await {
-                Ok(_) => {
-                    info!("Connection from {} closed", raddr);
-                }
-                Err(err) => {
-                    warn!("handle error for {}: {:?}", raddr, err);
-                }
+        let (stream, raddr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                accept_failed(err).await;
+                continue;
+            }
+        };
+        // a peer that is already gone only costs its own connection
+        if let Err(err) = set_tcp_options(&stream, &backend) {
+            warn!("Error configuring the connection from {}: {}", raddr, err);
+            continue;
+        }
+        let Some(acceptor) = backend.tls_acceptor() else {
+            continue;
+        };
+        let slot = backend.try_connect();
+        let backend = backend.clone();
+        connections.spawn(async move {
+            match timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => handle_connection(stream, raddr.to_string(), backend, slot).await,
+                Ok(Err(err)) => debug!("TLS handshake with {} failed: {}", raddr, err),
+                Err(_) => debug!("TLS handshake with {} timed out", raddr),
             }
         });
     }
 }
+
+async fn serve_unix(
+    listener: UnixListener,
+    backend: Backend,
+    connections: TaskTracker,
+) -> Result<()> {
+    // unix clients have no address of their own, they are logged by the socket path
+    let path = listener.local_addr()?;
+    let raddr = match path.as_pathname() {
+        Some(path) => path.display().to_string(),
+        None => "unix socket".to_string(),
+    };
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                accept_failed(err).await;
+                continue;
+            }
+        };
+        connections.spawn(handle_connection(
+            stream,
+            raddr.clone(),
+            backend.clone(),
+            backend.try_connect(),
+        ));
+    }
+}
+
+async fn handle_connection<S>(stream: S, raddr: String, backend: Backend, slot: Option<ClientSlot>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    debug!("Accepted connection from {}", raddr);
+    match network::slot_handler(stream, backend, slot).await {
+        Ok(_) => {
+            debug!("Connection from {} closed", raddr);
+        }
+        Err(err) => {
+            warn!("handle error for {}: {:?}", raddr, err);
+        }
+    }
+}
+
+// like redis, the listener carries on. clients over maxclients are closed right after they
+// are accepted, which gives their fds back
+async fn accept_failed(err: io::Error) {
+    warn!("Accepting client connection: {}", err);
+    tokio::time::sleep(ACCEPT_BACKOFF).await;
+}
+
+// like redis, raise the open files limit to fit maxclients, or lower maxclients to what the
+// limit allows
+fn adjust_open_files_limit(maxclients: usize) -> Result<usize, String> {
+    let wanted = maxclients as u64 + RESERVED_FDS;
+    let limit = match raise_open_files_limit(maxclients) {
+        Ok(limit) => limit,
+        Err(err) => {
+            warn!("Unable to obtain the current NOFILE limit ({}), assuming 1024 and setting the max clients configuration accordingly.", err);
+            return Ok(maxclients.min(1024 - RESERVED_FDS as usize));
+        }
+    };
+    if limit >= wanted {
+        return Ok(maxclients);
+    }
+    if limit <= RESERVED_FDS {
+        return Err(format!(
+            "Your current 'ulimit -n' of {} is not enough for the server to start. Please increase your open file limit to at least {}. Exiting.",
+            limit, wanted
+        ));
+    }
+    let reduced = (limit - RESERVED_FDS) as usize;
+    warn!(
+        "You requested maxclients of {} requiring at least {} max file descriptors.",
+        maxclients, wanted
+    );
+    warn!(
+        "Current maximum open files is {}. maxclients has been reduced to {} to compensate for low ulimit. If you need higher maxclients increase 'ulimit -n'.",
+        limit, reduced
+    );
+    Ok(reduced)
+}
+
+// a socket file left behind by an earlier run is replaced, like redis does
+fn bind_unix(path: &Path, perm: u32, backlog: u32) -> Result<UnixListener> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    // std and tokio listen with a fixed backlog
+    let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+    socket.bind(&SockAddr::unix(path)?)?;
+    socket.listen(backlog.try_into().unwrap_or(i32::MAX))?;
+    socket.set_nonblocking(true)?;
+    let listener = UnixListener::from_std(socket.into())?;
+    if perm != 0 {
+        fs::set_permissions(path, fs::Permissions::from_mode(perm))?;
+    }
+    Ok(listener)
+}
+
+fn bind_tcp(addr: SocketAddr, backlog: u32) -> Result<TcpListener> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    Ok(socket.listen(backlog)?)
+}
+
+fn set_tcp_options(stream: &TcpStream, backend: &Backend) -> io::Result<()> {
+    // replies are coalesced before they are written, nagle would only hold back the last one
+    stream.set_nodelay(true)?;
+    // like redis, the first probe goes out after tcp-keepalive seconds of silence and three
+    // more follow a third of that apart before the peer is given up on
+    let secs = backend.config().tcp_keepalive;
+    if secs > 0 {
+        let keepalive = TcpKeepalive::new()
+            .with_time(Duration::from_secs(secs))
+            .with_interval(Duration::from_secs((secs / 3).max(1)))
+            .with_retries(3);
+        SockRef::from(stream).set_tcp_keepalive(&keepalive)?;
+    }
+    Ok(())
+}
+
+// the kernel silently caps the backlog, redis warns about it too
+fn check_somaxconn(backlog: u32) {
+    let somaxconn = fs::read_to_string("/proc/sys/net/core/somaxconn")
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok());
+    if let Some(somaxconn) = somaxconn.filter(|n| *n < backlog) {
+        warn!(
+            "The TCP backlog setting of {} cannot be enforced because /proc/sys/net/core/somaxconn is set to the lower value of {}.",
+            backlog, somaxconn
+        );
+    }
+}
+
+// SIGTERM or SIGINT
+async fn terminate() -> Result<()> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        _ = sigterm.recv() => Ok(()),
+        ret = tokio::signal::ctrl_c() => Ok(ret?),
+    }
+}
+
+fn exit_with(err: impl std::fmt::Display) -> ! {
+    eprintln!("{}", err);
+    process::exit(1)
+}
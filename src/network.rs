@@ -1,66 +1,887 @@
-use crate::{Backend, Command, CommandExecutor, RespDecodeV2, RespEncode, RespError, RespFrame};
+use std::{
+    collections::HashSet,
+    io,
+    time::{Duration, Instant},
+};
+
+#[cfg(test)]
+use crate::RespLimits;
+use crate::{
+    key_hash_slot, with_client, Auth, Backend, BulkString, Client, ClientClass, ClientContext,
+    ClientSlot, Command, CommandExecutor, Hello, OutputBufferLimit, PubSubMessage, RespArray,
+    RespEncode, RespFrame, RespFrameDecoder, RespMap, RespPush, SimpleError, Subscriber,
+    TrackingOptions,
+};
 use anyhow::Result;
 
-use futures::SinkExt;
-use tokio::net::TcpStream;
+use futures::{FutureExt, SinkExt};
+use lazy_static::lazy_static;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    sync::watch,
+    time::timeout,
+};
 use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
-use tracing::info;
+use tracing::debug;
+
+// the redis version whose protocol the server implements, reported by HELLO
+pub(crate) const REDIS_VERSION: &str = "7.2.0";
+
+// bulk payloads this large are written to the socket straight from the value they belong to
+const LARGE_BULK_LEN: usize = 32 * 1024;
+
+// replies to a pipeline are flushed once this many bytes are queued, so a long pipeline
+// does not buffer all of them
+const MAX_BATCH_LEN: usize = 64 * 1024;
+
+// idle connections are checked this often, so a new timeout reaches them too
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// a client that does not read its replies has its output buffer limit checked this often
+const OUTPUT_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    static ref RESP_QUEUED: RespFrame = "QUEUED".into();
+    static ref RESP_OK: RespFrame = "OK".into();
+}
 
 #[derive(Debug)]
-struct RespFrameCodec;
+struct RespFrameCodec {
+    // keeps the frame being received across reads
+    decoder: RespFrameDecoder,
+}
 
 #[derive(Debug)]
 struct RedisRequest {
     frame: RespFrame,
-    backend: Backend,
 }
 
 #[derive(Debug)]
 struct RedisResponse {
-    frame: RespFrame,
+    // SUBSCRIBE and friends reply once per channel
+    frames: Vec<RespFrame>,
+}
+
+// the replies a client has not read yet, held to the output buffer limit of its class
+#[derive(Debug)]
+struct OutputBuffer {
+    class: ClientClass,
+    limit: OutputBufferLimit,
+    // when the unread replies went over the soft limit
+    over_soft_since: Option<Instant>,
+}
+
+// per connection state
+#[derive(Debug)]
+struct Session {
+    backend: Backend,
+    // commands queued between MULTI and EXEC
+    multi: Option<Vec<Command>>,
+    // a command failed to queue, EXEC will abort the transaction
+    multi_error: bool,
+    // watched keys and their modification count when WATCH was called
+    watched: Vec<(Vec<u8>, u64)>,
+    subscriber: Subscriber,
+    channels: HashSet<String>,
+    patterns: HashSet<String>,
+    shard_channels: HashSet<String>,
+    // QUIT was received, close once the reply is written
+    closing: bool,
+    // protocol version, push frames are only sent to RESP3 clients
+    protocol: u8,
+    // CLIENT TRACKING options while tracking is on
+    tracking: Option<TrackingOptions>,
+    // CLIENT CACHING yes|no, applies to the next command only
+    caching: Option<bool>,
+    // set with CLIENT SETNAME or HELLO SETNAME
+    name: Option<String>,
+    // AUTH succeeded, or no password was required when the connection was opened
+    authenticated: bool,
+}
+
+// serve one client connection, over tcp or a unix socket
+pub async fn stream_handler<S>(stream: S, backend: Backend) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let slot = backend.try_connect();
+    slot_handler(stream, backend, slot).await
 }
-pub async fn stream_handler(stream: TcpStream, backend: Backend) -> Result<()> {
+
+// serve a client that was counted against maxclients before its stream was set up, like tls
+// clients are before their handshake. none if there was no slot left for it
+pub async fn slot_handler<S>(
+    mut stream: S,
+    backend: Backend,
+    slot: Option<ClientSlot>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // like redis, a client over maxclients is told why it is closed
+    let Some(_slot) = slot else {
+        let _ = stream
+            .write_all(b"-ERR max number of clients reached\r\n")
+            .await;
+        return Ok(());
+    };
     // how to get a frame from a stream?
-    let mut framed = Framed::new(stream, RespFrameCodec);
+    let decoder = RespFrameDecoder::new(backend.resp_limits());
+    let mut framed = Framed::new(stream, RespFrameCodec { decoder });
+    let (subscriber, mut receiver) = backend.subscriber();
+    let mut shutdown = backend.shutdown_signal();
+    let mut session = Session::new(backend, subscriber);
+    let mut output = OutputBuffer::new(&session);
+    let mut last_interaction = Instant::now();
     loop {
-        match framed.next().await {
-            Some(Ok(frame)) => {
-                info!("Received frame: {:?}", frame);
-                let request = RedisRequest {
-                    frame,
-                    backend: backend.clone(),
-                };
-                let response = request_handler(request).await?;
-                info!("Sending response: {:?}", response.frame);
-                // how to send a frame to a stream?
-                framed.send(response.frame).await?;
+        let idle_timeout = session.idle_timeout();
+        let idle_check = match idle_timeout {
+            Some(timeout) => timeout.saturating_sub(last_interaction.elapsed()),
+            None => IDLE_CHECK_INTERVAL,
+        };
+        tokio::select! {
+            // the server is stopping, commands already read have been served
+            _ = stopping(&mut shutdown) => {
+                flush(&mut framed, &mut output).await?;
+                return Ok(());
+            }
+            _ = tokio::time::sleep(idle_check.min(IDLE_CHECK_INTERVAL)) => {
+                if idle_timeout.is_some_and(|timeout| last_interaction.elapsed() >= timeout) {
+                    debug!("Closing idle client");
+                    return Ok(());
+                }
             }
-            Some(Err(e)) => {
-                return Err(e);
+            frame = framed.next() => {
+                last_interaction = Instant::now();
+                // serve every frame the client pipelined, their replies go out in a single write
+                let mut frame = frame;
+                loop {
+                    match frame {
+                        Some(Ok(frame)) => {
+                            debug!("Received frame: {:?}", frame);
+                            let request = RedisRequest { frame };
+                            let response = request_handler(request, &mut session).await?;
+                            debug!("Sending response: {:?}", response.frames);
+                            // SUBSCRIBE moves the client to the pubsub class
+                            output.update(&session);
+                            for frame in response.frames {
+                                feed_frame(&mut framed, frame, &mut output).await?;
+                            }
+                            if session.closing {
+                                flush(&mut framed, &mut output).await?;
+                                return Ok(());
+                            }
+                            let limits = session.backend.resp_limits();
+                            framed.codec_mut().decoder.set_limits(limits);
+                            if framed.write_buffer().len() >= MAX_BATCH_LEN {
+                                flush(&mut framed, &mut output).await?;
+                            }
+                        }
+                        Some(Err(e)) => {
+                            // like redis, tell the client what was wrong with its request before
+                            // closing, after the replies to the requests ahead of it
+                            let _ = flush(&mut framed, &mut output).await;
+                            let reply = SimpleError::new(format!("ERR {}", e)).encode();
+                            let _ = framed.get_mut().write_all(&reply).await;
+                            return Err(e);
+                        }
+                        // the client closed the connection
+                        None => {
+                            let _ = flush(&mut framed, &mut output).await;
+                            return Ok(());
+                        }
+                    }
+                    // the next frame is taken only if it is buffered or readable without waiting
+                    match framed.next().now_or_never() {
+                        Some(next) => frame = next,
+                        None => break,
+                    }
+                }
+                flush(&mut framed, &mut output).await?;
             }
-            None => {
-                return Err(anyhow::anyhow!("connection closed"));
+            msg = receiver.recv() => match msg {
+                Some(msg) => {
+                    if let Some(frame) = session.message_frame(msg) {
+                        output.update(&session);
+                        feed_frame(&mut framed, frame, &mut output).await?;
+                        flush(&mut framed, &mut output).await?;
+                    }
+                }
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "client output buffer limit reached for pubsub client"
+                    ));
+                }
+            },
+        }
+    }
+}
+
+// resolves once the server is shutting down
+async fn stopping(shutdown: &mut watch::Receiver<Option<i32>>) {
+    let _ = shutdown.wait_for(Option::is_some).await;
+}
+
+// queue a reply on the connection, large bulk payloads skip the write buffer. whatever is
+// buffered ahead of them is written first, so replies stay in order
+async fn feed_frame<S>(
+    framed: &mut Framed<S, RespFrameCodec>,
+    frame: RespFrame,
+    output: &mut OutputBuffer,
+) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let mut chunks = Vec::new();
+    frame.encode_chunks(framed.write_buffer_mut(), LARGE_BULK_LEN, &mut chunks);
+    let mut unwritten: usize = chunks.iter().map(|chunk| chunk.len()).sum();
+    for chunk in chunks {
+        let mut chunk = &chunk[..];
+        while !chunk.is_empty() {
+            output.check(unwritten + framed.write_buffer().len())?;
+            // a partial write is kept when the timeout cancels it
+            let Ok(n) = timeout(OUTPUT_CHECK_INTERVAL, framed.get_mut().write(chunk)).await else {
+                continue;
+            };
+            let n = n?;
+            if n == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
             }
+            chunk = &chunk[n..];
+            unwritten -= n;
         }
     }
+    output.check(framed.write_buffer().len())
 }
 
-async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
-    let (frame, backend) = (request.frame, request.backend);
-    let cmd = Command::try_from(frame)?;
-    info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
-    let response = RedisResponse { frame };
+// write out the queued replies, giving up on a client that lets them pile up over its limit
+async fn flush<S>(framed: &mut Framed<S, RespFrameCodec>, output: &mut OutputBuffer) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    loop {
+        output.check(framed.write_buffer().len())?;
+        // the framed writer keeps what it could not write yet, so the flush can be retried
+        if let Ok(ret) = timeout(OUTPUT_CHECK_INTERVAL, framed.flush()).await {
+            ret?;
+            return output.check(0);
+        }
+    }
+}
+
+async fn request_handler(request: RedisRequest, session: &mut Session) -> Result<RedisResponse> {
+    let frames = session
+        .handle(request.frame)
+        .into_iter()
+        .map(|frame| session.render(frame))
+        .collect();
+    let response = RedisResponse { frames };
     Ok(response)
 }
 
+impl OutputBuffer {
+    fn new(session: &Session) -> Self {
+        let mut output = Self {
+            class: ClientClass::Normal,
+            limit: OutputBufferLimit::default(),
+            over_soft_since: None,
+        };
+        output.update(session);
+        output
+    }
+
+    // the limits can change with CONFIG SET, and the class with SUBSCRIBE and UNSUBSCRIBE
+    fn update(&mut self, session: &Session) {
+        self.class = match session.subscribed() {
+            true => ClientClass::PubSub,
+            false => ClientClass::Normal,
+        };
+        self.limit = session.backend.output_buffer_limit(self.class);
+    }
+
+    fn check(&mut self, pending: usize) -> Result<()> {
+        self.limit
+            .check(pending, &mut self.over_soft_since)
+            .map_err(|reason| {
+                anyhow::anyhow!(
+                    "client output buffer limit reached for {} client: {}",
+                    self.class,
+                    reason
+                )
+            })
+    }
+}
+
+impl Session {
+    fn new(backend: Backend, subscriber: Subscriber) -> Self {
+        backend.register_client(&subscriber);
+        backend.stats().record_connection();
+        let authenticated = backend.requirepass().is_none();
+        Self {
+            backend,
+            multi: None,
+            multi_error: false,
+            watched: Vec::new(),
+            subscriber,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            shard_channels: HashSet::new(),
+            closing: false,
+            protocol: 2,
+            tracking: None,
+            caching: None,
+            name: None,
+            authenticated,
+        }
+    }
+
+    fn id(&self) -> u64 {
+        self.subscriber.id()
+    }
+
+    fn handle(&mut self, frame: RespFrame) -> Vec<RespFrame> {
+        let name = command_name(&frame);
+        let cmd = match Command::try_from(frame) {
+            Ok(cmd) => cmd,
+            Err(e) => {
+                if self.multi.is_some() {
+                    self.multi_error = true;
+                }
+                return vec![SimpleError::new(format!("ERR {}", e)).into()];
+            }
+        };
+        debug!("Executing command: {:?}", cmd);
+        self.backend.stats().record_command();
+
+        // like redis, a client that has not authenticated can only authenticate or leave
+        if !self.authenticated
+            && !matches!(cmd, Command::Auth(_) | Command::Hello(_) | Command::Quit(_))
+        {
+            if self.multi.is_some() {
+                self.multi_error = true;
+            }
+            return vec![SimpleError::new("NOAUTH Authentication required.").into()];
+        }
+
+        let caching = matches!(cmd, Command::Client(Client::Caching(_)));
+        let ret = match cmd {
+            Command::Subscribe(cmd) if self.multi.is_none() => self.subscribe(cmd.channels),
+            Command::PSubscribe(cmd) if self.multi.is_none() => self.psubscribe(cmd.patterns),
+            Command::Unsubscribe(cmd) if self.multi.is_none() => self.unsubscribe(cmd.channels),
+            Command::PUnsubscribe(cmd) if self.multi.is_none() => {
+                self.punsubscribe(cmd.patterns)
+            }
+            Command::SSubscribe(cmd) if self.multi.is_none() => self.ssubscribe(cmd.channels),
+            Command::SUnsubscribe(cmd) if self.multi.is_none() => {
+                self.sunsubscribe(cmd.channels)
+            }
+            // RESP3 clients can run any command while subscribed, pushes are told apart from replies
+            Command::Ping(ping) if self.subscribed() && self.protocol < 3 => {
+                let message = ping.message.unwrap_or_else(|| BulkString::from(""));
+                vec![RespArray::new([BulkString::from("pong").into(), message.into()]).into()]
+            }
+            Command::Quit(_) => {
+                self.closing = true;
+                vec![RESP_OK.clone()]
+            }
+            // like redis, the connection is closed without a reply once the server is stopping
+            Command::Shutdown(cmd) if self.multi.is_none() => {
+                if cmd.abort {
+                    return vec![SimpleError::new("ERR No shutdown in progress.").into()];
+                }
+                match self.backend.shutdown(cmd.options) {
+                    Ok(()) => {
+                        self.closing = true;
+                        vec![]
+                    }
+                    Err(e) => vec![SimpleError::new(format!("ERR {}", e)).into()],
+                }
+            }
+            _ if self.subscribed() && self.protocol < 3 => vec![SimpleError::new(format!(
+                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT are allowed in this context",
+                name
+            ))
+            .into()],
+            cmd => vec![self.handle_command(cmd)],
+        };
+        if !caching {
+            self.caching = None;
+        }
+        ret
+    }
+
+    fn handle_command(&mut self, cmd: Command) -> RespFrame {
+        if self.multi.is_none() {
+            return match cmd {
+                Command::Multi(_) => {
+                    self.multi = Some(Vec::new());
+                    RESP_OK.clone()
+                }
+                Command::Watch(watch) => {
+                    for key in watch.keys {
+                        let version = self.backend.watch(&key);
+                        self.watched.push((key, version));
+                    }
+                    RESP_OK.clone()
+                }
+                Command::Unwatch(_) => {
+                    self.unwatch_all();
+                    RESP_OK.clone()
+                }
+                Command::Client(cmd) => self.client(cmd),
+                Command::Hello(cmd) => self.hello(cmd),
+                Command::Auth(cmd) => self.auth(cmd),
+                cmd => {
+                    let _guard = self.backend.shared_guard();
+                    with_client(self.context(), || cmd.execute(&self.backend))
+                }
+            };
+        }
+
+        match cmd {
+            Command::Exec(_) => self.exec(),
+            Command::Discard(_) => {
+                self.multi = None;
+                self.multi_error = false;
+                self.unwatch_all();
+                RESP_OK.clone()
+            }
+            // MULTI and WATCH are rejected without aborting the transaction
+            cmd @ (Command::Multi(_) | Command::Watch(_)) => cmd.execute(&self.backend),
+            cmd => {
+                if let Some(queue) = self.multi.as_mut() {
+                    queue.push(cmd);
+                }
+                RESP_QUEUED.clone()
+            }
+        }
+    }
+
+    fn exec(&mut self) -> RespFrame {
+        let queue = self.multi.take().unwrap_or_default();
+        if std::mem::take(&mut self.multi_error) {
+            self.unwatch_all();
+            return SimpleError::new("EXECABORT Transaction discarded because of previous errors.")
+                .into();
+        }
+
+        let ret = {
+            let _guard = self.backend.exclusive_guard();
+            let dirty = self
+                .watched
+                .iter()
+                .any(|(key, version)| self.backend.watched_version(key) != *version);
+            if dirty {
+                RespArray(None).into()
+            } else {
+                let ret = with_client(self.context(), || {
+                    queue
+                        .into_iter()
+                        .map(|cmd| cmd.execute(&self.backend))
+                        .collect::<Vec<_>>()
+                });
+                RespArray::new(ret).into()
+            }
+        };
+        self.unwatch_all();
+        ret
+    }
+
+    // the client context commands run with, reads are tracked unless the caching mode opts out
+    fn context(&self) -> ClientContext {
+        let track_reads = match &self.tracking {
+            Some(opts) if opts.bcast => false,
+            Some(opts) if opts.optin => self.caching == Some(true),
+            Some(opts) if opts.optout => self.caching != Some(false),
+            Some(_) => true,
+            None => false,
+        };
+        ClientContext {
+            id: self.id(),
+            track_reads,
+        }
+    }
+
+    fn client(&mut self, cmd: Client) -> RespFrame {
+        match cmd {
+            Client::Id => RespFrame::Integer(self.id() as i64),
+            Client::GetRedir => {
+                let redirect = match &self.tracking {
+                    Some(opts) => opts.redirect.map_or(0, |id| id as i64),
+                    None => -1,
+                };
+                RespFrame::Integer(redirect)
+            }
+            Client::Caching(yes) => {
+                let err = match &self.tracking {
+                    Some(opts) if opts.optin && yes => None,
+                    Some(opts) if opts.optout && !yes => None,
+                    Some(opts) if opts.optin => {
+                        Some("ERR CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode.")
+                    }
+                    Some(opts) if opts.optout => {
+                        Some("ERR CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode.")
+                    }
+                    _ => Some("ERR CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled"),
+                };
+                match err {
+                    Some(err) => SimpleError::new(err).into(),
+                    None => {
+                        self.caching = Some(yes);
+                        RESP_OK.clone()
+                    }
+                }
+            }
+            Client::GetName => match &self.name {
+                Some(name) => BulkString::from(name.as_str()).into(),
+                None => BulkString(None).into(),
+            },
+            Client::SetName(name) => match self.set_name(name) {
+                Ok(()) => RESP_OK.clone(),
+                Err(e) => e,
+            },
+            Client::Tracking(None) => {
+                self.backend.disable_tracking(self.id());
+                self.tracking = None;
+                RESP_OK.clone()
+            }
+            Client::Tracking(Some(opts)) => match self.enable_tracking(opts) {
+                Ok(()) => RESP_OK.clone(),
+                Err(e) => SimpleError::new(e).into(),
+            },
+        }
+    }
+
+    fn set_name(&mut self, name: String) -> Result<(), RespFrame> {
+        if name.chars().any(|c| !c.is_ascii_graphic()) {
+            return Err(SimpleError::new(
+                "ERR Client names cannot contain spaces, newlines or special characters.",
+            )
+            .into());
+        }
+        // an empty name removes it
+        self.name = (!name.is_empty()).then_some(name);
+        Ok(())
+    }
+
+    fn hello(&mut self, cmd: Hello) -> RespFrame {
+        let protocol = match cmd.protover.as_deref().map(str::parse::<u8>) {
+            None => self.protocol,
+            Some(Ok(v @ (2 | 3))) => v,
+            Some(Ok(_)) => return SimpleError::new("NOPROTO unsupported protocol version").into(),
+            Some(Err(_)) => {
+                return SimpleError::new("ERR Protocol version is not an integer or out of range")
+                    .into()
+            }
+        };
+        match &cmd.auth {
+            Some((user, password)) if !self.check_password(user, password) => {
+                return wrongpass();
+            }
+            Some(_) => self.authenticated = true,
+            None if !self.authenticated => {
+                return SimpleError::new("NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time")
+                    .into();
+            }
+            None => {}
+        }
+        if let Some(name) = cmd.setname {
+            if let Err(e) = self.set_name(name) {
+                return e;
+            }
+        }
+        self.protocol = protocol;
+
+        let mut info = RespMap::new();
+        info.insert(b"server".to_vec(), BulkString::from("redis").into());
+        info.insert(b"version".to_vec(), BulkString::from(REDIS_VERSION).into());
+        info.insert(b"proto".to_vec(), RespFrame::Integer(protocol as i64));
+        info.insert(b"id".to_vec(), RespFrame::Integer(self.id() as i64));
+        info.insert(b"mode".to_vec(), BulkString::from("standalone").into());
+        info.insert(b"role".to_vec(), BulkString::from("master").into());
+        info.insert(b"modules".to_vec(), RespArray::new([]).into());
+        info.into()
+    }
+
+    fn auth(&mut self, cmd: Auth) -> RespFrame {
+        let requirepass = self.backend.requirepass();
+        if cmd.username.is_none() && requirepass.is_none() {
+            return SimpleError::new("ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?")
+                .into();
+        }
+        let user = cmd.username.as_deref().unwrap_or("default");
+        if !self.check_password(user, &cmd.password) {
+            return wrongpass();
+        }
+        self.authenticated = true;
+        RESP_OK.clone()
+    }
+
+    // there is no ACL, only the default user, which takes any password unless requirepass is set
+    fn check_password(&self, user: &str, password: &str) -> bool {
+        user == "default"
+            && self
+                .backend
+                .requirepass()
+                .is_none_or(|p| constant_time_eq(p.as_bytes(), password.as_bytes()))
+    }
+
+    // replies to RESP2 clients are downgraded, RESP3 clients get them as is
+    fn render(&self, frame: RespFrame) -> RespFrame {
+        if self.protocol >= 3 {
+            frame
+        } else {
+            frame.into_resp2()
+        }
+    }
+
+    fn enable_tracking(&mut self, mut opts: TrackingOptions) -> Result<(), String> {
+        if let Some(redirect) = opts.redirect {
+            if !self.backend.client_exists(redirect) {
+                return Err("ERR The client ID you want redirect to does not exist".to_string());
+            }
+        }
+        if let Some(current) = &self.tracking {
+            if current.bcast != opts.bcast {
+                return Err("ERR You can't switch BCAST mode on/off before disabling tracking for this client, and then re-enabling it with a different mode.".to_string());
+            }
+            if current.optin != opts.optin || current.optout != opts.optout {
+                return Err("ERR You can't switch OPTIN/OPTOUT mode before disabling tracking for this client, and then re-enabling it with a different mode.".to_string());
+            }
+        }
+        if let Some((a, b)) = opts.overlapping_prefixes() {
+            return Err(format!(
+                "ERR Prefix '{}' overlaps with another provided prefix '{}'. Prefixes for a single client must not overlap.",
+                a, b
+            ));
+        }
+        if opts.bcast && opts.prefixes.is_empty() {
+            opts.prefixes.push(String::new());
+        }
+        let prefixes = opts.bcast.then_some(opts.prefixes.as_slice());
+        self.backend
+            .enable_tracking(&self.subscriber, opts.redirect, opts.noloop, prefixes);
+        self.tracking = Some(opts);
+        Ok(())
+    }
+
+    // render a queued message, RESP2 connections only get invalidations while subscribed
+    fn message_frame(&self, msg: PubSubMessage) -> Option<RespFrame> {
+        if self.protocol >= 3 {
+            return Some(msg.into_push().into());
+        }
+        match msg {
+            PubSubMessage::Invalidate { .. } if self.subscribed() => {
+                Some(RespArray::new(msg.into_frames()).into())
+            }
+            PubSubMessage::Invalidate { .. } | PubSubMessage::TrackingRedirBroken { .. } => None,
+            msg => Some(RespArray::new(msg.into_frames()).into()),
+        }
+    }
+
+    fn unwatch_all(&mut self) {
+        for (key, _) in self.watched.drain(..) {
+            self.backend.unwatch(&key);
+        }
+    }
+
+    // how long the client may stay idle, subscribers only listen and are never closed
+    fn idle_timeout(&self) -> Option<Duration> {
+        let timeout = self.backend.client_timeout();
+        (timeout > 0 && !self.subscribed()).then(|| Duration::from_secs(timeout))
+    }
+
+    fn subscribed(&self) -> bool {
+        !self.channels.is_empty() || !self.patterns.is_empty() || !self.shard_channels.is_empty()
+    }
+
+    // shard channels are counted apart from channels and patterns
+    fn subscription_count(&self, kind: &str) -> i64 {
+        match kind {
+            "ssubscribe" | "sunsubscribe" => self.shard_channels.len() as i64,
+            _ => (self.channels.len() + self.patterns.len()) as i64,
+        }
+    }
+
+    fn subscribe(&mut self, channels: Vec<String>) -> Vec<RespFrame> {
+        channels
+            .into_iter()
+            .map(|channel| {
+                if self.channels.insert(channel.clone()) {
+                    self.backend.subscribe(&channel, &self.subscriber);
+                }
+                self.subscription_reply("subscribe", Some(channel))
+            })
+            .collect()
+    }
+
+    fn psubscribe(&mut self, patterns: Vec<String>) -> Vec<RespFrame> {
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                if self.patterns.insert(pattern.clone()) {
+                    self.backend.psubscribe(&pattern, &self.subscriber);
+                }
+                self.subscription_reply("psubscribe", Some(pattern))
+            })
+            .collect()
+    }
+
+    // without arguments, unsubscribe from every channel
+    fn unsubscribe(&mut self, channels: Vec<String>) -> Vec<RespFrame> {
+        let mut channels = channels;
+        if channels.is_empty() {
+            channels = self.channels.iter().cloned().collect();
+            channels.sort();
+            if channels.is_empty() {
+                return vec![self.subscription_reply("unsubscribe", None)];
+            }
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                if self.channels.remove(&channel) {
+                    self.backend.unsubscribe(&channel, &self.subscriber);
+                }
+                self.subscription_reply("unsubscribe", Some(channel))
+            })
+            .collect()
+    }
+
+    fn punsubscribe(&mut self, patterns: Vec<String>) -> Vec<RespFrame> {
+        let mut patterns = patterns;
+        if patterns.is_empty() {
+            patterns = self.patterns.iter().cloned().collect();
+            patterns.sort();
+            if patterns.is_empty() {
+                return vec![self.subscription_reply("punsubscribe", None)];
+            }
+        }
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                if self.patterns.remove(&pattern) {
+                    self.backend.punsubscribe(&pattern, &self.subscriber);
+                }
+                self.subscription_reply("punsubscribe", Some(pattern))
+            })
+            .collect()
+    }
+
+    fn ssubscribe(&mut self, channels: Vec<String>) -> Vec<RespFrame> {
+        if !same_slot(&channels) {
+            return vec![crossslot_error()];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                if self.shard_channels.insert(channel.clone()) {
+                    self.backend.ssubscribe(&channel, &self.subscriber);
+                }
+                self.subscription_reply("ssubscribe", Some(channel))
+            })
+            .collect()
+    }
+
+    fn sunsubscribe(&mut self, channels: Vec<String>) -> Vec<RespFrame> {
+        let mut channels = channels;
+        if channels.is_empty() {
+            channels = self.shard_channels.iter().cloned().collect();
+            channels.sort();
+            if channels.is_empty() {
+                return vec![self.subscription_reply("sunsubscribe", None)];
+            }
+        } else if !same_slot(&channels) {
+            return vec![crossslot_error()];
+        }
+        channels
+            .into_iter()
+            .map(|channel| {
+                if self.shard_channels.remove(&channel) {
+                    self.backend.sunsubscribe(&channel, &self.subscriber);
+                }
+                self.subscription_reply("sunsubscribe", Some(channel))
+            })
+            .collect()
+    }
+
+    fn subscription_reply(&self, kind: &str, name: Option<String>) -> RespFrame {
+        let name = match name {
+            Some(name) => BulkString::from(name).into(),
+            None => BulkString(None).into(),
+        };
+        let frames = [
+            BulkString::from(kind).into(),
+            name,
+            RespFrame::Integer(self.subscription_count(kind)),
+        ];
+        // RESP3 clients get subscription changes as pushes, like published messages
+        if self.protocol >= 3 {
+            RespPush::new(frames).into()
+        } else {
+            RespArray::new(frames).into()
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.unwatch_all();
+        self.backend.unregister_client(self.id());
+        for channel in self.channels.drain() {
+            self.backend.unsubscribe(&channel, &self.subscriber);
+        }
+        for pattern in self.patterns.drain() {
+            self.backend.punsubscribe(&pattern, &self.subscriber);
+        }
+        for channel in self.shard_channels.drain() {
+            self.backend.sunsubscribe(&channel, &self.subscriber);
+        }
+    }
+}
+
+// all shard channels of a single command must map to the same hash slot
+fn same_slot(channels: &[String]) -> bool {
+    let mut slots = channels.iter().map(|c| key_hash_slot(c.as_bytes()));
+    match slots.next() {
+        Some(first) => slots.all(|slot| slot == first),
+        None => true,
+    }
+}
+
+fn wrongpass() -> RespFrame {
+    SimpleError::new("WRONGPASS invalid username-password pair or user is disabled.").into()
+}
+
+// compare passwords without returning early, so the time taken does not tell how much matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn crossslot_error() -> RespFrame {
+    SimpleError::new("CROSSSLOT Keys in request don't hash to the same slot").into()
+}
+
+// the command name as the client sent it, used in error replies
+fn command_name(frame: &RespFrame) -> String {
+    match frame {
+        RespFrame::Array(RespArray(Some(args))) => match args.first() {
+            Some(RespFrame::BulkString(BulkString(Some(name)))) => {
+                String::from_utf8_lossy(name).to_string()
+            }
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
 impl Encoder<RespFrame> for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn encode(&mut self, item: RespFrame, dst: &mut bytes::BytesMut) -> Result<()> {
-        let encoded = item.encode();
-        dst.extend_from_slice(&encoded);
+        dst.reserve(item.encoded_len());
+        item.encode_to(dst);
         Ok(())
     }
 }
@@ -70,10 +891,599 @@ impl Decoder for RespFrameCodec {
     type Error = anyhow::Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<RespFrame>> {
-        match RespFrame::decode(src) {
-            Ok(frame) => Ok(Some(frame)),
-            Err(RespError::NotComplete) => Ok(None),
-            Err(e) => Err(e.into()),
+        Ok(self.decoder.decode(src)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cmd(args: &[&str]) -> RespFrame {
+        RespArray::new(
+            args.iter()
+                .map(|a| BulkString::from(*a).into())
+                .collect::<Vec<RespFrame>>(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_multi_exec() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        assert_eq!(session.handle(cmd(&["multi"])), vec![RESP_OK.clone()]);
+        assert_eq!(
+            session.handle(cmd(&["set", "a", "1"])),
+            vec![RESP_QUEUED.clone()]
+        );
+        assert_eq!(
+            session.handle(cmd(&["get", "a"])),
+            vec![RESP_QUEUED.clone()]
+        );
+        assert_eq!(backend.get(b"a"), None);
+        assert_eq!(
+            session.handle(cmd(&["exec"])),
+            vec![RespArray::new([RESP_OK.clone(), b"1".into()]).into()]
+        );
+        assert_eq!(
+            session.handle(cmd(&["exec"])),
+            vec![SimpleError::new("ERR EXEC without MULTI").into()]
+        );
+    }
+
+    #[test]
+    fn test_command_names_are_case_insensitive() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        assert_eq!(
+            session.handle(cmd(&["SET", "a", "1"])),
+            vec![RESP_OK.clone()]
+        );
+        assert_eq!(session.handle(cmd(&["Get", "a"])), vec![b"1".into()]);
+    }
+
+    #[test]
+    fn test_malformed_commands_are_errors() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        assert_eq!(
+            session.handle(RespArray(None).into()),
+            vec![SimpleError::new("ERR Invalid command Command must not be a null array").into()]
+        );
+        assert_eq!(
+            session.handle(RespFrame::Integer(1)),
+            vec![SimpleError::new("ERR Invalid command Command must be an array").into()]
+        );
+        assert_eq!(
+            session.handle(cmd(&["ping"])),
+            vec![RespFrame::from("PONG")]
+        );
+    }
+
+    #[test]
+    fn test_multi_discard_and_execabort() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        session.handle(cmd(&["multi"]));
+        session.handle(cmd(&["set", "a", "1"]));
+        assert_eq!(session.handle(cmd(&["discard"])), vec![RESP_OK.clone()]);
+        assert_eq!(backend.get(b"a"), None);
+
+        session.handle(cmd(&["multi"]));
+        session.handle(cmd(&["set", "a", "1"]));
+        assert!(matches!(
+            session.handle(cmd(&["get", "a", "b"]))[0],
+            RespFrame::Error(_)
+        ));
+        assert_eq!(
+            session.handle(cmd(&["exec"])),
+            vec![
+                SimpleError::new("EXECABORT Transaction discarded because of previous errors.")
+                    .into()
+            ]
+        );
+        assert_eq!(backend.get(b"a"), None);
+    }
+
+    #[test]
+    fn test_watch_aborts_exec() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        let mut other = Session::new(backend.clone(), backend.subscriber().0);
+        session.handle(cmd(&["watch", "a"]));
+        other.handle(cmd(&["set", "a", "2"]));
+        session.handle(cmd(&["multi"]));
+        session.handle(cmd(&["set", "a", "1"]));
+        assert_eq!(session.handle(cmd(&["exec"])), vec![RespArray(None).into()]);
+        assert_eq!(backend.get(b"a"), Some(b"2".into()));
+
+        // watches are released after EXEC
+        session.handle(cmd(&["watch", "a"]));
+        session.handle(cmd(&["multi"]));
+        session.handle(cmd(&["set", "a", "1"]));
+        assert_eq!(
+            session.handle(cmd(&["exec"])),
+            vec![RespArray::new([RESP_OK.clone()]).into()]
+        );
+        assert!(backend.watches.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_mode() {
+        let backend = Backend::new();
+        let (subscriber, mut receiver) = backend.subscriber();
+        let mut session = Session::new(backend.clone(), subscriber);
+        assert_eq!(
+            session.handle(cmd(&["subscribe", "a", "b"])),
+            vec![
+                RespArray::new([b"subscribe".into(), b"a".into(), RespFrame::Integer(1)]).into(),
+                RespArray::new([b"subscribe".into(), b"b".into(), RespFrame::Integer(2)]).into(),
+            ]
+        );
+        assert_eq!(
+            session.handle(cmd(&["psubscribe", "c*"])),
+            vec![
+                RespArray::new([b"psubscribe".into(), b"c*".into(), RespFrame::Integer(3)]).into()
+            ]
+        );
+        assert_eq!(backend.publish("a", "hi".into()), 1);
+        assert_eq!(backend.publish("cat", "hi".into()), 1);
+        assert_eq!(
+            RespArray::new(receiver.recv().await.unwrap().into_frames()),
+            RespArray::new([b"message".into(), b"a".into(), b"hi".into()])
+        );
+
+        assert_eq!(
+            session.handle(cmd(&["get", "a"])),
+            vec![SimpleError::new(
+                "ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT are allowed in this context"
+            )
+            .into()]
+        );
+        assert_eq!(
+            session.handle(cmd(&["ping"])),
+            vec![RespArray::new([b"pong".into(), b"".into()]).into()]
+        );
+
+        assert_eq!(session.handle(cmd(&["unsubscribe"])).len(), 2);
+        assert_eq!(
+            session.handle(cmd(&["punsubscribe"])),
+            vec![
+                RespArray::new([b"punsubscribe".into(), b"c*".into(), RespFrame::Integer(0)])
+                    .into()
+            ]
+        );
+        assert_eq!(
+            session.handle(cmd(&["unsubscribe"])),
+            vec![RespArray::new([
+                b"unsubscribe".into(),
+                BulkString(None).into(),
+                RespFrame::Integer(0)
+            ])
+            .into()]
+        );
+        assert_eq!(session.handle(cmd(&["ping"])), vec!["PONG".into()]);
+        assert_eq!(backend.publish("a", "hi".into()), 0);
+    }
+
+    #[test]
+    fn test_session_drop_unsubscribes() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        session.handle(cmd(&["subscribe", "a"]));
+        session.handle(cmd(&["psubscribe", "*"]));
+        assert!(!session.handle(cmd(&["quit"])).is_empty());
+        assert!(session.closing);
+        drop(session);
+        assert!(backend.pubsub_channels(None).is_empty());
+        assert_eq!(backend.pubsub_numpat(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shard_subscriptions() {
+        let backend = Backend::new();
+        let (subscriber, mut receiver) = backend.subscriber();
+        let mut session = Session::new(backend.clone(), subscriber);
+        assert_eq!(
+            session.handle(cmd(&["ssubscribe", "a", "b"])),
+            vec![SimpleError::new("CROSSSLOT Keys in request don't hash to the same slot").into()]
+        );
+        assert_eq!(
+            session.handle(cmd(&["ssubscribe", "{user}.a", "{user}.b"])),
+            vec![
+                RespArray::new([
+                    b"ssubscribe".into(),
+                    b"{user}.a".into(),
+                    RespFrame::Integer(1)
+                ])
+                .into(),
+                RespArray::new([
+                    b"ssubscribe".into(),
+                    b"{user}.b".into(),
+                    RespFrame::Integer(2)
+                ])
+                .into(),
+            ]
+        );
+        assert_eq!(
+            session.handle(cmd(&["subscribe", "c"])),
+            vec![RespArray::new([b"subscribe".into(), b"c".into(), RespFrame::Integer(1)]).into()]
+        );
+
+        let mut other = Session::new(backend.clone(), backend.subscriber().0);
+        assert_eq!(
+            other.handle(cmd(&["spublish", "{user}.a", "hi"])),
+            vec![RespFrame::Integer(1)]
+        );
+        assert_eq!(
+            RespArray::new(receiver.recv().await.unwrap().into_frames()),
+            RespArray::new([b"smessage".into(), b"{user}.a".into(), b"hi".into()])
+        );
+
+        assert_eq!(session.handle(cmd(&["sunsubscribe"])).len(), 2);
+        assert!(backend.pubsub_shardchannels(None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_client_tracking_redirect() {
+        let backend = Backend::new();
+        let (subscriber, mut receiver) = backend.subscriber();
+        let mut target = Session::new(backend.clone(), subscriber);
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        let mut other = Session::new(backend.clone(), backend.subscriber().0);
+        target.handle(cmd(&["subscribe", "__redis__:invalidate"]));
+
+        assert_eq!(
+            session.handle(cmd(&["client", "tracking", "on", "redirect", "999"])),
+            vec![SimpleError::new("ERR The client ID you want redirect to does not exist").into()]
+        );
+        let id = target.id().to_string();
+        assert_eq!(
+            session.handle(cmd(&["client", "tracking", "on", "redirect", &id])),
+            vec![RESP_OK.clone()]
+        );
+        assert_eq!(
+            session.handle(cmd(&["client", "getredir"])),
+            vec![RespFrame::Integer(target.id() as i64)]
+        );
+        session.handle(cmd(&["get", "foo"]));
+        other.handle(cmd(&["set", "foo", "bar"]));
+
+        let msg = receiver.recv().await.unwrap();
+        assert_eq!(
+            target.message_frame(msg),
+            Some(
+                RespArray::new([
+                    b"message".into(),
+                    b"__redis__:invalidate".into(),
+                    RespArray::new([b"foo".into()]).into()
+                ])
+                .into()
+            )
+        );
+
+        session.handle(cmd(&["client", "tracking", "off"]));
+        assert_eq!(
+            session.handle(cmd(&["client", "getredir"])),
+            vec![RespFrame::Integer(-1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_client_tracking_optin() {
+        let backend = Backend::new();
+        let (subscriber, mut receiver) = backend.subscriber();
+        let mut session = Session::new(backend.clone(), subscriber);
+        session.protocol = 3;
+        assert!(matches!(
+            session.handle(cmd(&["client", "caching", "yes"]))[0],
+            RespFrame::Error(_)
+        ));
+        session.handle(cmd(&["client", "tracking", "on", "optin"]));
+        assert!(matches!(
+            session.handle(cmd(&["client", "caching", "no"]))[0],
+            RespFrame::Error(_)
+        ));
+
+        // only reads right after CLIENT CACHING yes are tracked
+        session.handle(cmd(&["get", "a"]));
+        session.handle(cmd(&["client", "caching", "yes"]));
+        session.handle(cmd(&["get", "b"]));
+        session.handle(cmd(&["get", "c"]));
+        backend.set(b"a".to_vec(), RespFrame::Integer(1));
+        backend.set(b"c".to_vec(), RespFrame::Integer(1));
+        backend.set(b"b".to_vec(), RespFrame::Integer(1));
+
+        let msg = receiver.recv().await.unwrap();
+        assert_eq!(
+            session.message_frame(msg),
+            Some(
+                RespPush::new([b"invalidate".into(), RespArray::new([b"b".into()]).into()]).into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_requirepass() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        let err = |msg: &str| vec![RespFrame::from(SimpleError::new(msg))];
+        assert_eq!(
+            session.handle(cmd(&["auth", "secret"])),
+            err("ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?")
+        );
+
+        backend.set_requirepass(Some("secret".to_string()));
+        // connections opened before requirepass was set stay authenticated
+        assert_eq!(
+            session.handle(cmd(&["set", "a", "1"])),
+            vec![RESP_OK.clone()]
+        );
+
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        assert_eq!(
+            session.handle(cmd(&["get", "a"])),
+            err("NOAUTH Authentication required.")
+        );
+        assert!(matches!(
+            session.handle(cmd(&["hello", "3"]))[0],
+            RespFrame::Error(_)
+        ));
+        assert_eq!(
+            session.handle(cmd(&["auth", "wrong"])),
+            err("WRONGPASS invalid username-password pair or user is disabled.")
+        );
+        assert_eq!(
+            session.handle(cmd(&["auth", "admin", "secret"])),
+            err("WRONGPASS invalid username-password pair or user is disabled.")
+        );
+        assert_eq!(
+            session.handle(cmd(&["auth", "secret"])),
+            vec![RESP_OK.clone()]
+        );
+        assert_eq!(session.handle(cmd(&["get", "a"])), vec![b"1".into()]);
+
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        assert!(matches!(
+            session.handle(cmd(&["hello", "3", "auth", "default", "secret"]))[0],
+            RespFrame::Map(_)
+        ));
+        assert_eq!(session.handle(cmd(&["get", "a"])), vec![b"1".into()]);
+    }
+
+    #[test]
+    fn test_hello_negotiates_protocol() {
+        let backend = Backend::new();
+        let mut session = Session::new(backend.clone(), backend.subscriber().0);
+        let reply = |session: &mut Session, args: &[&str]| {
+            let frame = session.handle(cmd(args)).remove(0);
+            session.render(frame).encode()
+        };
+        assert_eq!(reply(&mut session, &["get", "missing"]), b"$-1\r\n");
+        assert_eq!(
+            reply(&mut session, &["hello", "4"]),
+            b"-NOPROTO unsupported protocol version\r\n"
+        );
+        assert_eq!(
+            reply(&mut session, &["hello", "3", "auth", "admin", "pass"]),
+            b"-WRONGPASS invalid username-password pair or user is disabled.\r\n"
+        );
+
+        let frame = session
+            .handle(cmd(&["hello", "3", "setname", "app"]))
+            .remove(0);
+        let RespFrame::Map(info) = session.render(frame) else {
+            panic!("HELLO must reply with a map");
+        };
+        assert_eq!(info.get(b"proto".as_slice()), Some(&RespFrame::Integer(3)));
+        assert_eq!(info.get(b"server".as_slice()), Some(&b"redis".into()));
+        assert_eq!(reply(&mut session, &["get", "missing"]), b"_\r\n");
+        assert_eq!(
+            reply(&mut session, &["client", "getname"]),
+            b"$3\r\napp\r\n"
+        );
+
+        // RESP3 subscribers keep running commands and get pushes
+        assert_eq!(
+            reply(&mut session, &["subscribe", "a"]),
+            b">3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n"
+        );
+        assert_eq!(reply(&mut session, &["get", "missing"]), b"_\r\n");
+
+        let frame = session.handle(cmd(&["hello", "2"])).remove(0);
+        assert!(matches!(session.render(frame), RespFrame::Array(_)));
+    }
+
+    #[test]
+    fn test_codec_enforces_proto_max_bulk_len() {
+        let backend = Backend::new();
+        backend.set_resp_limits(RespLimits {
+            max_bulk_len: 1024,
+            ..Default::default()
+        });
+        let mut codec = RespFrameCodec {
+            decoder: RespFrameDecoder::new(backend.resp_limits()),
+        };
+        let mut buf = bytes::BytesMut::from("*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1000\r\nabc");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert!(buf.capacity() >= 1000 + 2 + buf.len() - 3);
+
+        buf.extend_from_slice(&[b'x'; 997]);
+        buf.extend_from_slice(b"\r\n");
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+
+        codec.decoder.set_limits(RespLimits {
+            max_bulk_len: 999,
+            ..backend.resp_limits()
+        });
+        let mut buf = bytes::BytesMut::from("*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1000\r\n");
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.to_string(), "Protocol error: invalid bulk length");
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies_are_written_in_order() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            stream_handler(stream, Backend::new()).await
+        });
+
+        let mut request = Vec::new();
+        let mut expected = Vec::new();
+        for i in 1..=1000 {
+            let member = i.to_string();
+            request.extend_from_slice(
+                format!(
+                    "*3\r\n$4\r\nsadd\r\n$1\r\ns\r\n${}\r\n{}\r\n",
+                    member.len(),
+                    member
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(b":1\r\n");
         }
+        // an inline command and a bad one behind it, the error comes after every reply
+        request.extend_from_slice(b"sismember s 1000\r\n*1\r\n$-2\r\n");
+        expected.extend_from_slice(b":1\r\n-ERR Invalid frame length:-2\r\n");
+
+        let mut client = tokio::net::TcpStream::connect(addr).await?;
+        client.write_all(&request).await?;
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await?;
+        assert_eq!(
+            String::from_utf8_lossy(&reply),
+            String::from_utf8_lossy(&expected)
+        );
+        assert!(server.await?.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_handler_over_unix_socket() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let (mut client, server) = tokio::net::UnixStream::pair()?;
+        let server = tokio::spawn(stream_handler(server, Backend::new()));
+        client
+            .write_all(b"*3\r\n$3\r\nset\r\n$1\r\nk\r\n$1\r\nv\r\nget k\r\nquit\r\n")
+            .await?;
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await?;
+        assert_eq!(reply, b"+OK\r\n$1\r\nv\r\n+OK\r\n");
+        server.await??;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maxclients_rejects_extra_clients() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let backend = Backend::new();
+        backend
+            .config_set(&[("maxclients".to_string(), "1".to_string())])
+            .unwrap();
+        let (mut first, server) = tokio::net::UnixStream::pair()?;
+        tokio::spawn(stream_handler(server, backend.clone()));
+        first.write_all(b"PING\r\n").await?;
+        let mut reply = [0; 7];
+        first.read_exact(&mut reply).await?;
+        assert_eq!(&reply, b"+PONG\r\n");
+
+        let (mut second, server) = tokio::net::UnixStream::pair()?;
+        stream_handler(server, backend.clone()).await?;
+        let mut reply = Vec::new();
+        second.read_to_end(&mut reply).await?;
+        assert_eq!(reply, b"-ERR max number of clients reached\r\n");
+        assert_eq!(backend.stats().rejected_connections(), 1);
+
+        // the slot is given back once the first client leaves
+        drop(first);
+        while backend.stats().connected_clients() > 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(backend.try_connect().is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_clients_are_closed() -> Result<()> {
+        use tokio::io::AsyncReadExt;
+
+        let backend = Backend::new();
+        backend
+            .config_set(&[("timeout".to_string(), "1".to_string())])
+            .unwrap();
+        let (mut idle, server) = tokio::net::UnixStream::pair()?;
+        tokio::spawn(stream_handler(server, backend.clone()));
+        let (mut subscriber, server) = tokio::net::UnixStream::pair()?;
+        tokio::spawn(stream_handler(server, backend.clone()));
+        subscriber.write_all(b"SUBSCRIBE news\r\n").await?;
+        let mut reply = vec![0; b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n".len()];
+        subscriber.read_exact(&mut reply).await?;
+
+        let start = Instant::now();
+        let mut reply = Vec::new();
+        idle.read_to_end(&mut reply).await?;
+        assert!(reply.is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(900));
+
+        // subscribers only listen, they are not closed for being idle
+        backend.publish("news", "hi".into());
+        let expected = b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n";
+        let mut reply = vec![0; expected.len()];
+        subscriber.read_exact(&mut reply).await?;
+        assert_eq!(reply, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_clients_over_the_output_buffer_limit_are_closed() -> Result<()> {
+        let backend = Backend::new();
+        backend
+            .config_set(&[(
+                "client-output-buffer-limit".to_string(),
+                "normal 1mb 64kb 1".to_string(),
+            )])
+            .unwrap();
+        let big = BulkString::new(vec![b'x'; 512 * 1024]);
+        backend.set(b"big".to_vec(), big.into());
+        let huge = BulkString::new(vec![b'x'; 2 * 1024 * 1024]);
+        backend.set(b"huge".to_vec(), huge.into());
+
+        // over the hard limit, the reply is not even started
+        let (mut client, server) = tokio::net::UnixStream::pair()?;
+        client.write_all(b"GET huge\r\n").await?;
+        let err = stream_handler(server, backend.clone()).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "client output buffer limit reached for normal client: 2097164 bytes over the hard limit of 1048576"
+        );
+
+        // over the soft limit, the client gets a second to read
+        let (mut client, server) = tokio::net::UnixStream::pair()?;
+        client.write_all(b"GET big\r\n").await?;
+        let start = Instant::now();
+        let err = stream_handler(server, backend.clone()).await.unwrap_err();
+        assert!(start.elapsed() >= Duration::from_millis(900));
+        assert!(
+            err.to_string()
+                .ends_with("over the soft limit of 65536 for 1 seconds"),
+            "{}",
+            err
+        );
+
+        // a client that keeps up is served in full
+        let (mut client, server) = tokio::net::UnixStream::pair()?;
+        tokio::spawn(stream_handler(server, backend.clone()));
+        client.write_all(b"GET big\r\n").await?;
+        let mut reply = vec![0; 512 * 1024 + b"$524288\r\n\r\n".len()];
+        tokio::io::AsyncReadExt::read_exact(&mut client, &mut reply).await?;
+        assert!(reply.ends_with(b"xx\r\n"));
+        Ok(())
     }
 }
@@ -1,27 +1,36 @@
 use std::ops::Deref;
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{calc_total_length, frame::RespFrame, parse_length, BUF_CAP, CRLF_LEN, NULL_ARRAY};
+use super::{
+    calc_total_length, frame::RespFrame, header_len, parse_length, put_header, CRLF_LEN, NULL_ARRAY,
+};
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct RespArray(pub(crate) Option<Vec<RespFrame>>);
 
 // array: *<number-of-elements>\r\n<element-1>...<element-n>
 
 impl RespEncode for RespArray {
-    fn encode(self) -> Vec<u8> {
-        match self.0 {
+    fn encoded_len(&self) -> usize {
+        match &self.0 {
             Some(v) => {
-                let mut buf = Vec::with_capacity(BUF_CAP);
-                buf.extend_from_slice(&format!("*{}\r\n", v.len()).into_bytes());
+                header_len(v.len() as i64) + v.iter().map(|f| f.encoded_len()).sum::<usize>()
+            }
+            None => NULL_ARRAY.len(),
+        }
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        match &self.0 {
+            Some(v) => {
+                put_header(buf, b'*', v.len() as i64);
                 for frame in v {
-                    buf.extend_from_slice(&frame.encode());
+                    frame.encode_to(buf);
                 }
-                buf
             }
-            None => NULL_ARRAY.clone(),
+            None => buf.put_slice(&NULL_ARRAY),
         }
     }
 }
@@ -44,7 +53,7 @@ impl RespDecode for RespArray {
         buf.advance(end + CRLF_LEN); // skip the *...
         let mut array = Vec::with_capacity(len);
         for _ in 0..len {
-            let frame = RespFrame::decode(buf)?;
+            let frame = RespFrame::decode_frame(buf)?;
             array.push(frame);
         }
         Ok(RespArray::new(array))
@@ -0,0 +1,97 @@
+use bytes::{Buf, BufMut, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError};
+
+use super::{
+    calc_total_length,
+    frame::RespFrame,
+    header_len,
+    map::{decode_key, entries_len, put_entries, RespMap},
+    parse_length, put_header, CRLF_LEN,
+};
+
+// auxiliary data attached to the reply that follows it
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct RespAttribute {
+    pub(crate) attributes: RespMap,
+    pub(crate) frame: Box<RespFrame>,
+}
+
+// attribute: |<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n><reply>
+impl RespEncode for RespAttribute {
+    fn encoded_len(&self) -> usize {
+        header_len(self.attributes.len() as i64)
+            + entries_len(&self.attributes)
+            + self.frame.encoded_len()
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_header(buf, b'|', self.attributes.len() as i64);
+        put_entries(buf, &self.attributes);
+        self.frame.encode_to(buf);
+    }
+}
+
+impl RespDecode for RespAttribute {
+    const PREFIX: &'static str = "|";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let total_len = Self::expect_length(buf)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        buf.advance(end + CRLF_LEN);
+        let mut attributes = RespMap::new();
+        for _ in 0..len {
+            let key = decode_key(buf)?;
+            let value = RespFrame::decode_frame(buf)?;
+            attributes.insert(key, value);
+        }
+        let frame = RespFrame::decode_frame(buf)?;
+        Ok(RespAttribute::new(attributes, frame))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+impl RespAttribute {
+    pub fn new(attributes: RespMap, frame: impl Into<RespFrame>) -> Self {
+        Self {
+            attributes,
+            frame: Box::new(frame.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attribute_encode() {
+        let mut attributes = RespMap::new();
+        attributes.insert(b"ttl".to_vec(), RespFrame::Integer(3600));
+        let frame: RespFrame = RespAttribute::new(attributes, RespFrame::Integer(1)).into();
+        assert_eq!(frame.encode(), b"|1\r\n+ttl\r\n:3600\r\n:1\r\n");
+    }
+
+    #[test]
+    fn test_attribute_decode() {
+        let mut buf = BytesMut::from("|1\r\n+ttl\r\n:3600\r\n*1\r\n:1\r\n");
+        let frame = RespAttribute::decode(&mut buf).unwrap();
+        assert_eq!(
+            frame.attributes.get(b"ttl".as_slice()),
+            Some(&RespFrame::Integer(3600))
+        );
+        assert_eq!(
+            *frame.frame,
+            crate::RespArray::new([RespFrame::Integer(1)]).into()
+        );
+
+        let mut buf = BytesMut::from("|1\r\n+ttl\r\n:3600\r\n");
+        assert_eq!(RespAttribute::decode(&mut buf), Err(RespError::NotComplete));
+    }
+}
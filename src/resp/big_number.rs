@@ -0,0 +1,76 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError};
+
+use super::{extract_simple_frame_data, line_len, put_line, CRLF_LEN};
+
+// integers too large for i64, kept as their decimal representation
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
+pub struct BigNumber(pub(crate) String);
+
+// big number: ([+|-]<number>\r\n
+impl RespEncode for BigNumber {
+    fn encoded_len(&self) -> usize {
+        line_len(self.0.as_bytes())
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_line(buf, b'(', self.0.as_bytes());
+    }
+}
+
+impl RespDecode for BigNumber {
+    const PREFIX: &'static str = "(";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        let data = buf.split_to(end + CRLF_LEN);
+        let s = String::from_utf8(data[1..end].to_vec())?;
+        BigNumber::new(s)
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let end = extract_simple_frame_data(buf, Self::PREFIX)?;
+        Ok(end + CRLF_LEN)
+    }
+}
+
+impl BigNumber {
+    pub fn new(s: impl Into<String>) -> Result<Self, RespError> {
+        let s = s.into();
+        let digits = s.strip_prefix(['+', '-']).unwrap_or(&s);
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(RespError::InvalidFrame(format!(
+                "invalid big number: {}",
+                s
+            )));
+        }
+        Ok(BigNumber(s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[test]
+    fn test_big_number_encode() {
+        let frame: RespFrame = BigNumber::new("3492890328409238509324850943850943825024385")
+            .unwrap()
+            .into();
+        assert_eq!(
+            frame.encode(),
+            b"(3492890328409238509324850943850943825024385\r\n"
+        );
+    }
+
+    #[test]
+    fn test_big_number_decode() {
+        let mut buf = BytesMut::from("(-3492890328409238509324850943850943825024385\r\n");
+        let frame = BigNumber::decode(&mut buf).unwrap();
+        assert_eq!(frame.0, "-3492890328409238509324850943850943825024385");
+
+        let mut buf = BytesMut::from("(12a\r\n");
+        assert!(BigNumber::decode(&mut buf).is_err());
+    }
+}
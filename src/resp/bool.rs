@@ -1,4 +1,4 @@
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
@@ -30,11 +30,15 @@ impl RespDecode for bool {
 
 // #<t|f>\r\n
 impl RespEncode for bool {
-    fn encode(self) -> Vec<u8> {
-        if self {
-            b"#t\r\n".to_vec()
+    fn encoded_len(&self) -> usize {
+        4
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        if *self {
+            buf.put_slice(b"#t\r\n");
         } else {
-            b"#f\r\n".to_vec()
+            buf.put_slice(b"#f\r\n");
         }
     }
 }
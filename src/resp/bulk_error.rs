@@ -0,0 +1,78 @@
+use std::ops::Deref;
+
+use bytes::{BufMut, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError};
+
+use super::{blob_len, parse_length, put_blob, CRLF_LEN};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
+pub struct BulkError(pub(crate) String);
+
+// bulk error: !<length>\r\n<error>\r\n
+impl RespEncode for BulkError {
+    fn encoded_len(&self) -> usize {
+        blob_len(self.0.len())
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_blob(buf, b'!', self.0.as_bytes());
+    }
+}
+
+impl RespDecode for BulkError {
+    const PREFIX: &'static str = "!";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let total = Self::expect_length(buf)?;
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let data = buf.split_to(total);
+        let start = end + CRLF_LEN;
+        let s = String::from_utf8_lossy(&data[start..start + len]);
+        Ok(BulkError::new(s))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total = end + CRLF_LEN + len + CRLF_LEN;
+        if buf.len() < total {
+            return Err(RespError::NotComplete);
+        }
+        Ok(total)
+    }
+}
+
+impl BulkError {
+    pub fn new(s: impl Into<String>) -> Self {
+        Self(s.into())
+    }
+}
+
+impl Deref for BulkError {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[test]
+    fn test_bulk_error_encode() {
+        let frame: RespFrame = BulkError::new("SYNTAX invalid syntax").into();
+        assert_eq!(frame.encode(), b"!21\r\nSYNTAX invalid syntax\r\n");
+    }
+
+    #[test]
+    fn test_bulk_error_decode() {
+        let mut buf = BytesMut::from("!21\r\nSYNTAX invalid syntax\r\n");
+        let frame = BulkError::decode(&mut buf).unwrap();
+        assert_eq!(frame, BulkError::new("SYNTAX invalid syntax"));
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from("!21\r\nSYNTAX");
+        assert_eq!(BulkError::decode(&mut buf), Err(RespError::NotComplete));
+    }
+}
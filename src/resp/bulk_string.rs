@@ -1,25 +1,27 @@
 use std::{fmt::Display, ops::Deref};
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{parse_length, CRLF_LEN, NULL_BULK_STRING};
+use super::{blob_len, parse_length, put_blob, CRLF_LEN, NULL_BULK_STRING};
 
+// the payload is a slice of the frozen read buffer, decoding never copies it
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
-pub struct BulkString(pub(crate) Option<Vec<u8>>);
+pub struct BulkString(pub(crate) Option<Bytes>);
 
 impl RespEncode for BulkString {
-    fn encode(self) -> Vec<u8> {
-        match self.0 {
-            Some(v) => {
-                let mut buf = Vec::with_capacity(v.len() + 16);
-                buf.extend_from_slice(&format!("${}\r\n", v.len()).into_bytes());
-                buf.extend_from_slice(&v);
-                buf.extend_from_slice(b"\r\n");
-                buf
-            }
-            None => NULL_BULK_STRING.clone(),
+    fn encoded_len(&self) -> usize {
+        match &self.0 {
+            Some(v) => blob_len(v.len()),
+            None => NULL_BULK_STRING.len(),
+        }
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        match &self.0 {
+            Some(v) => put_blob(buf, b'$', v),
+            None => buf.put_slice(&NULL_BULK_STRING),
         }
     }
 }
@@ -35,10 +37,13 @@ impl RespDecode for BulkString {
             return Ok(BulkString(None));
         }
         let (end, len) = parse_length(buf, Self::PREFIX)?;
+        if buf.len() < end + CRLF_LEN + len + CRLF_LEN {
+            return Err(RespError::NotComplete);
+        }
         buf.advance(end + CRLF_LEN); // skip the *...
                                      // consume the bulk string data
-        let bs = BulkString::new(&buf[..len]);
-        buf.advance(len + CRLF_LEN); // skip the length and \r\n
+        let bs = BulkString(Some(buf.split_to(len).freeze()));
+        buf.advance(CRLF_LEN); // skip the \r\n
         Ok(bs)
     }
 
@@ -59,7 +64,7 @@ impl RespDecode for BulkString {
 }
 
 impl Deref for BulkString {
-    type Target = Vec<u8>;
+    type Target = [u8];
     fn deref(&self) -> &Self::Target {
         match &self.0 {
             Some(v) => v,
@@ -74,26 +79,26 @@ impl Display for BulkString {
 }
 
 impl BulkString {
-    pub fn new(s: impl Into<Vec<u8>>) -> Self {
+    pub fn new(s: impl Into<Bytes>) -> Self {
         Self(Some(s.into()))
     }
 }
 
 impl From<String> for BulkString {
     fn from(s: String) -> Self {
-        BulkString(Some(s.into_bytes()))
+        BulkString(Some(s.into()))
     }
 }
 
 impl From<&str> for BulkString {
     fn from(s: &str) -> Self {
-        Self(Some(s.as_bytes().to_vec()))
+        Self(Some(Bytes::copy_from_slice(s.as_bytes())))
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for BulkString {
     fn from(s: &[u8; N]) -> Self {
-        BulkString(Some(s.to_vec()))
+        BulkString(Some(Bytes::copy_from_slice(s)))
     }
 }
 impl AsRef<[u8]> for BulkString {
@@ -138,4 +143,13 @@ mod tests {
         let frame = BulkString::new("Ok".to_string());
         assert_eq!(BulkString::decode(&mut buf).unwrap(), frame);
     }
+
+    #[test]
+    fn test_bulk_string_decode_shares_the_read_buffer() {
+        let mut buf = BytesMut::from("$6\r\nfoobar\r\n+OK\r\n");
+        let payload = buf.as_ptr() as usize + 4;
+        let frame = BulkString::decode(&mut buf).unwrap();
+        assert_eq!(frame.as_ptr() as usize, payload);
+        assert_eq!(buf.as_ref(), b"+OK\r\n");
+    }
 }
@@ -1,21 +1,57 @@
-use bytes::BytesMut;
+use std::fmt::{self, Write};
+
+use bytes::{BufMut, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use super::{extract_simple_frame_data, line_len, put_line, CRLF_LEN};
 
 // double: ,[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n
 impl RespEncode for f64 {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(32);
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
-            format!(",{:+e}\r\n", self)
+    fn encoded_len(&self) -> usize {
+        line_len(DoubleText::new(*self).as_bytes())
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_line(buf, b',', DoubleText::new(*self).as_bytes());
+    }
+}
+
+// the textual form of a double, formatted on the stack
+struct DoubleText {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl DoubleText {
+    fn new(d: f64) -> Self {
+        let mut text = Self {
+            buf: [0; 64],
+            len: 0,
+        };
+        let ret = if d.abs() > 1e+8 || d.abs() < 1e-8 {
+            write!(text, "{:+e}", d)
         } else {
-            format!(",{:+}\r\n", self)
+            write!(text, "{:+}", d)
         };
+        ret.expect("a formatted double fits in 64 bytes");
+        text
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
 
-        buf.extend_from_slice(&ret.into_bytes());
-        buf
+impl fmt::Write for DoubleText {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
     }
 }
 
@@ -1,11 +1,13 @@
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use enum_dispatch::enum_dispatch;
 
-use crate::{RespDecode, RespEncode, RespError, SimpleString};
+use crate::{RespDecode, RespEncode, RespError, RespLimits, SimpleString};
 
 use super::{
-    array::RespArray, bulk_string::BulkString, extract_fixed_data, map::RespMap, set::RespSet,
-    simple_error::SimpleError,
+    array::RespArray, attribute::RespAttribute, big_number::BigNumber, bulk_error::BulkError,
+    bulk_string::BulkString, extract_fixed_data, map::put_entries, map::put_key, map::RespMap,
+    push::RespPush, put_header, set::RespSet, simple_error::SimpleError,
+    verbatim_string::VerbatimString, CRLF,
 };
 
 // SimpleString +OK\r\n
@@ -22,6 +24,8 @@ use super::{
 // Verbatim strings =<length>\r\n<encoding>:<data>\r\n =15\r\ntxt:Some string\r\n
 // Maps %<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n> %2\r\n+first\r\n:1\r\n+second\r\n:2\r\n
 // Sets ~<number-of-elements>\r\n<element-1>...<element-n>
+// Attributes |<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n><reply>
+// Pushes ><number-of-elements>\r\n<element-1>...<element-n>
 #[enum_dispatch(RespEncode)]
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub enum RespFrame {
@@ -35,6 +39,11 @@ pub enum RespFrame {
     Double(f64),
     Map(RespMap),
     Set(RespSet),
+    BigNumber(BigNumber),
+    BulkError(BulkError),
+    VerbatimString(VerbatimString),
+    Attribute(RespAttribute),
+    Push(RespPush),
 }
 
 // SimpleString +OK\r\n
@@ -50,9 +59,28 @@ pub enum RespFrame {
 // Verbatim strings =<length>\r\n<encoding>:<data>\r\n =15\r\ntxt:Some string\r\n
 // Maps %<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n> %2\r\n+first\r\n:1\r\n+second\r\n:2\r\n
 // Sets ~<number-of-elements>\r\n<element-1>...<element-n>
+// Attributes |<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n><reply>
+// Pushes ><number-of-elements>\r\n<element-1>...<element-n>
 impl RespDecode for RespFrame {
     const PREFIX: &'static str = "";
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        Self::decode_with_limits(buf, &RespLimits::default())
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        RespLimits::default().check(buf)?;
+        Self::frame_length(buf)
+    }
+
+    // the limits are checked once for the whole frame, then the recursive decoders run
+    fn decode_with_limits(buf: &mut BytesMut, limits: &RespLimits) -> Result<Self, RespError> {
+        limits.check(buf)?;
+        Self::decode_frame(buf)
+    }
+}
+
+impl RespFrame {
+    pub(crate) fn decode_frame(buf: &mut BytesMut) -> Result<Self, RespError> {
         let mut iter = buf.iter().peekable();
         match iter.peek() {
             Some(b'+') => {
@@ -100,6 +128,26 @@ impl RespDecode for RespFrame {
                 let frame = RespSet::decode(buf)?;
                 Ok(frame.into())
             }
+            Some(b'(') => {
+                let frame = BigNumber::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'!') => {
+                let frame = BulkError::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'=') => {
+                let frame = VerbatimString::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'|') => {
+                let frame = RespAttribute::decode(buf)?;
+                Ok(frame.into())
+            }
+            Some(b'>') => {
+                let frame = RespPush::decode(buf)?;
+                Ok(frame.into())
+            }
             None => Err(RespError::NotComplete),
             _ => Err(RespError::InvalidFrameType(format!(
                 "expect: frame, got: {:?}",
@@ -108,7 +156,7 @@ impl RespDecode for RespFrame {
         }
     }
 
-    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+    pub(crate) fn frame_length(buf: &[u8]) -> Result<usize, RespError> {
         let mut iter = buf.iter().peekable();
         match iter.peek() {
             Some(b'+') => SimpleString::expect_length(buf),
@@ -121,16 +169,126 @@ impl RespDecode for RespFrame {
             Some(b',') => f64::expect_length(buf),
             Some(b'%') => RespMap::expect_length(buf),
             Some(b'~') => RespSet::expect_length(buf),
+            Some(b'(') => BigNumber::expect_length(buf),
+            Some(b'!') => BulkError::expect_length(buf),
+            Some(b'=') => VerbatimString::expect_length(buf),
+            Some(b'|') => RespAttribute::expect_length(buf),
+            Some(b'>') => RespPush::expect_length(buf),
             _ => Err(RespError::NotComplete),
         }
     }
+
+    // render the frame for a RESP2 client, RESP3 only types fall back to their RESP2 form
+    pub fn into_resp2(self) -> RespFrame {
+        match self {
+            RespFrame::Null(_) => BulkString(None).into(),
+            RespFrame::Boolean(b) => RespFrame::Integer(b as i64),
+            RespFrame::Double(d) => BulkString::from(format_double(d)).into(),
+            RespFrame::Array(RespArray(Some(frames))) => RespArray::new(
+                frames
+                    .into_iter()
+                    .map(RespFrame::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Map(map) => RespArray::new(
+                map.0
+                    .into_iter()
+                    .flat_map(|(k, v)| [BulkString::new(k).into(), v.into_resp2()])
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Set(set) => RespArray::new(
+                set.0
+                    .into_iter()
+                    .map(RespFrame::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::Push(push) => RespArray::new(
+                push.0
+                    .into_iter()
+                    .map(RespFrame::into_resp2)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            RespFrame::BigNumber(n) => BulkString::from(n.0).into(),
+            // a simple error is a single line
+            RespFrame::BulkError(e) => SimpleError::new(e.0.replace(['\r', '\n'], " ")).into(),
+            RespFrame::VerbatimString(s) => BulkString::new(s.data).into(),
+            // RESP2 has no out of band data, the reply is sent on its own
+            RespFrame::Attribute(attr) => attr.frame.into_resp2(),
+            frame => frame,
+        }
+    }
+
+    // encode into `buf` like `encode_to`, except for bulk string payloads of `large` bytes or
+    // more: what is pending in `buf` is split off into `chunks`, followed by the payload itself,
+    // which is shared with the frame instead of copied
+    pub fn encode_chunks(&self, buf: &mut BytesMut, large: usize, chunks: &mut Vec<Bytes>) {
+        match self {
+            RespFrame::BulkString(BulkString(Some(data))) if data.len() >= large => {
+                put_header(buf, b'$', data.len() as i64);
+                chunks.push(buf.split().freeze());
+                chunks.push(data.clone());
+                buf.put_slice(CRLF);
+            }
+            RespFrame::Array(RespArray(Some(frames))) => {
+                put_header(buf, b'*', frames.len() as i64);
+                for frame in frames {
+                    frame.encode_chunks(buf, large, chunks);
+                }
+            }
+            RespFrame::Set(set) => {
+                put_header(buf, b'~', set.len() as i64);
+                for frame in set.iter() {
+                    frame.encode_chunks(buf, large, chunks);
+                }
+            }
+            RespFrame::Push(push) => {
+                put_header(buf, b'>', push.len() as i64);
+                for frame in push.iter() {
+                    frame.encode_chunks(buf, large, chunks);
+                }
+            }
+            RespFrame::Map(map) => {
+                put_header(buf, b'%', map.len() as i64);
+                for (k, v) in map.iter() {
+                    put_key(buf, k);
+                    v.encode_chunks(buf, large, chunks);
+                }
+            }
+            RespFrame::Attribute(attr) => {
+                put_header(buf, b'|', attr.attributes.len() as i64);
+                put_entries(buf, &attr.attributes);
+                attr.frame.encode_chunks(buf, large, chunks);
+            }
+            frame => {
+                buf.reserve(frame.encoded_len());
+                frame.encode_to(buf);
+            }
+        }
+    }
+}
+
+// doubles are sent to RESP2 clients as bulk strings, like redis does
+fn format_double(d: f64) -> String {
+    if d.is_nan() {
+        "nan".to_string()
+    } else {
+        d.to_string()
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
 pub struct RespNull;
 impl RespEncode for RespNull {
-    fn encode(self) -> Vec<u8> {
-        b"_\r\n".to_vec()
+    fn encoded_len(&self) -> usize {
+        3
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(b"_\r\n");
     }
 }
 
@@ -148,13 +306,13 @@ impl RespDecode for RespNull {
 
 impl From<&[u8]> for RespFrame {
     fn from(s: &[u8]) -> Self {
-        BulkString(Some(s.to_vec())).into()
+        BulkString(Some(Bytes::copy_from_slice(s))).into()
     }
 }
 
 impl<const N: usize> From<&[u8; N]> for RespFrame {
     fn from(s: &[u8; N]) -> Self {
-        BulkString(Some(s.to_vec())).into()
+        BulkString(Some(Bytes::copy_from_slice(s))).into()
     }
 }
 
@@ -217,9 +375,98 @@ mod tests {
 
         let mut buf = BytesMut::from("%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
         let mut map = RespMap::new();
-        map.insert("first".to_string(), 1.into());
-        map.insert("second".to_string(), 2.into());
+        map.insert(b"first".to_vec(), 1.into());
+        map.insert(b"second".to_vec(), 2.into());
         let frame = map.into();
         assert_eq!(RespFrame::decode(&mut buf).unwrap(), frame);
     }
+
+    #[test]
+    fn test_resp_frame_into_resp2() {
+        let mut map = RespMap::new();
+        map.insert(b"ok".to_vec(), true.into());
+        map.insert(b"score".to_vec(), 1.5.into());
+        let frame: RespFrame = RespArray::new([map.into(), RespNull.into()]).into();
+        assert_eq!(
+            frame.into_resp2().encode(),
+            b"*2\r\n*4\r\n$2\r\nok\r\n:1\r\n$5\r\nscore\r\n$3\r\n1.5\r\n$-1\r\n"
+        );
+
+        let frame: RespFrame = RespSet::new().into();
+        assert_eq!(frame.into_resp2(), RespArray::new([]).into());
+        let frame: RespFrame = f64::NAN.into();
+        assert_eq!(frame.into_resp2(), BulkString::from("nan").into());
+
+        let frame: RespFrame = BigNumber::new("-3492890328409238509324850943850943825024385")
+            .unwrap()
+            .into();
+        assert_eq!(
+            frame.into_resp2(),
+            BulkString::from("-3492890328409238509324850943850943825024385").into()
+        );
+        let frame: RespFrame = BulkError::new("SYNTAX invalid\r\nsyntax").into();
+        assert_eq!(
+            frame.into_resp2(),
+            SimpleError::new("SYNTAX invalid  syntax").into()
+        );
+        let frame: RespFrame = VerbatimString::new(*b"txt", "Some string").into();
+        assert_eq!(frame.into_resp2(), BulkString::from("Some string").into());
+
+        let mut attributes = RespMap::new();
+        attributes.insert(b"ttl".to_vec(), 3600.into());
+        let frame: RespFrame = RespArray::new([RespAttribute::new(attributes, true).into()]).into();
+        assert_eq!(frame.into_resp2().encode(), b"*1\r\n:1\r\n");
+    }
+
+    #[test]
+    fn test_resp_frame_encode_to_writes_encoded_len_bytes() {
+        let mut map = RespMap::new();
+        map.insert(b"ok".to_vec(), true.into());
+        map.insert(vec![0xff], (-1.5e-10).into());
+        let frame: RespFrame = RespArray::new([
+            map.into(),
+            BulkString(None).into(),
+            RespArray(None).into(),
+            RespFrame::Integer(-1234),
+            VerbatimString::new(*b"txt", "hi").into(),
+            RespPush::new([SimpleError::new("ERR".to_string()).into()]).into(),
+        ])
+        .into();
+
+        let mut buf = BytesMut::with_capacity(frame.encoded_len());
+        frame.encode_to(&mut buf);
+        assert_eq!(buf.len(), frame.encoded_len());
+        assert_eq!(buf.capacity(), frame.encoded_len());
+        assert_eq!(RespFrame::decode(&mut buf), Ok(frame));
+    }
+
+    #[test]
+    fn test_encode_chunks_shares_large_payloads() {
+        let large = Bytes::from(vec![b'x'; 64]);
+        let mut map = RespMap::new();
+        map.insert(b"value".to_vec(), BulkString::new(large.clone()).into());
+        let frame: RespFrame = RespArray::new([
+            BulkString::from("small").into(),
+            map.into(),
+            BulkString::new(large.clone()).into(),
+        ])
+        .into();
+
+        let mut buf = BytesMut::from("+OK\r\n");
+        let mut chunks = Vec::new();
+        frame.encode_chunks(&mut buf, 64, &mut chunks);
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[1].as_ptr(), large.as_ptr());
+        assert_eq!(chunks[3].as_ptr(), large.as_ptr());
+
+        let written: Vec<u8> = chunks
+            .iter()
+            .chain([&buf.freeze()])
+            .flatten()
+            .copied()
+            .collect();
+        let mut expected = b"+OK\r\n".to_vec();
+        expected.extend(frame.encode());
+        assert_eq!(written, expected);
+    }
 }
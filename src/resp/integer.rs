@@ -1,12 +1,16 @@
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use super::{extract_simple_frame_data, header_len, put_header, CRLF_LEN};
 
 impl RespEncode for i64 {
-    fn encode(self) -> Vec<u8> {
-        format!(":{}\r\n", self).into_bytes()
+    fn encoded_len(&self) -> usize {
+        header_len(*self)
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_header(buf, b':', *self);
     }
 }
 
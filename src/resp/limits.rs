@@ -0,0 +1,154 @@
+use super::{find_crlf, RespError, CRLF_LEN};
+
+// the proto-max-bulk-len default, 512mb like redis
+pub const PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+// the inline command limit default, 64kb like redis
+pub const PROTO_INLINE_MAX_SIZE: usize = 64 * 1024;
+// aggregates longer than this are rejected before their elements arrive
+pub const PROTO_MAX_MULTIBULK_LEN: usize = 1024 * 1024;
+// requests are flat, replies nest a few levels at most
+pub const PROTO_MAX_NESTING: usize = 128;
+// the client-query-buffer-limit default, 1gb like redis
+pub const CLIENT_QUERY_BUFFER_LIMIT: usize = 1024 * 1024 * 1024;
+
+// limits a decoder enforces on what a client sends, so a declared length or nesting depth
+// cannot make it allocate or recurse without bound
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RespLimits {
+    pub max_bulk_len: usize,
+    pub max_multibulk_len: usize,
+    pub max_nesting: usize,
+    pub max_inline_len: usize,
+    // bytes of a single request buffered before it is complete
+    pub max_query_buffer: usize,
+}
+
+impl Default for RespLimits {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: PROTO_MAX_BULK_LEN,
+            max_multibulk_len: PROTO_MAX_MULTIBULK_LEN,
+            max_nesting: PROTO_MAX_NESTING,
+            max_inline_len: PROTO_INLINE_MAX_SIZE,
+            max_query_buffer: CLIENT_QUERY_BUFFER_LIMIT,
+        }
+    }
+}
+
+impl RespLimits {
+    // walk the headers of the frame at the front of `buf` without recursing, before a decoder
+    // does. only what has arrived is checked, malformed headers are left to the decoder
+    pub fn check(&self, buf: &[u8]) -> Result<(), RespError> {
+        if buf.len() > self.max_query_buffer {
+            return Err(RespError::QueryBufferFull(buf.len()));
+        }
+        let mut pos = 0;
+        // elements still expected at each nesting level, the frame itself is the first
+        let mut levels = vec![1usize];
+        while let Some(remaining) = levels.last_mut() {
+            if *remaining == 0 {
+                levels.pop();
+                continue;
+            }
+            *remaining -= 1;
+            if pos >= buf.len() {
+                return Ok(());
+            }
+            let Some(end) = find_crlf(&buf[pos..], 1) else {
+                return Ok(());
+            };
+            let prefix = buf[pos];
+            let header = String::from_utf8_lossy(&buf[pos + 1..pos + end]);
+            pos += end + CRLF_LEN;
+            let len = match prefix {
+                b'*' | b'~' | b'>' | b'%' | b'|' | b'$' | b'!' | b'=' => {
+                    match header.parse::<i64>() {
+                        Ok(len) => len,
+                        Err(_) => return Ok(()),
+                    }
+                }
+                _ => continue,
+            };
+            // null frames
+            if len < 0 {
+                continue;
+            }
+            let len = len as usize;
+            match prefix {
+                b'$' | b'!' | b'=' => {
+                    self.check_bulk_len(len)?;
+                    pos += len + CRLF_LEN;
+                }
+                _ => {
+                    self.check_multibulk_len(len)?;
+                    self.check_nesting(levels.len() - 1)?;
+                    levels.push(match prefix {
+                        b'%' => 2 * len,
+                        b'|' => 2 * len + 1,
+                        _ => len,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_bulk_len(&self, len: usize) -> Result<(), RespError> {
+        if len > self.max_bulk_len {
+            return Err(RespError::BulkTooLarge(len));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_multibulk_len(&self, len: usize) -> Result<(), RespError> {
+        if len > self.max_multibulk_len {
+            return Err(RespError::MultibulkTooLarge(len));
+        }
+        Ok(())
+    }
+
+    // `depth` aggregates are already open
+    pub(crate) fn check_nesting(&self, depth: usize) -> Result<(), RespError> {
+        if depth >= self.max_nesting {
+            return Err(RespError::NestingTooDeep(self.max_nesting));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_check() {
+        let limits = RespLimits {
+            max_bulk_len: 5,
+            max_multibulk_len: 3,
+            max_nesting: 2,
+            max_inline_len: 16,
+            max_query_buffer: 64,
+        };
+        assert_eq!(limits.check(b"*2\r\n$5\r\nhello\r\n*3\r\n:1\r\n"), Ok(()));
+        assert_eq!(limits.check(b"*-1\r\n"), Ok(()));
+        assert_eq!(
+            limits.check(b"*2\r\n$5\r\nhello\r\n$6\r\n"),
+            Err(RespError::BulkTooLarge(6))
+        );
+        assert_eq!(
+            limits.check(b"%2\r\n+a\r\n*4\r\n"),
+            Err(RespError::MultibulkTooLarge(4))
+        );
+        assert_eq!(
+            limits.check(b"*1\r\n*1\r\n*1\r\n:1\r\n"),
+            Err(RespError::NestingTooDeep(2))
+        );
+        assert_eq!(
+            limits.check(&[b'+'; 65]),
+            Err(RespError::QueryBufferFull(65))
+        );
+
+        // only the frame at the front is checked, not the pipelined ones behind it
+        assert_eq!(limits.check(b"$5\r\nhello\r\n$9\r\n"), Ok(()));
+    }
+}
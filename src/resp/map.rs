@@ -3,25 +3,27 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
-use crate::{RespDecode, RespEncode, RespError, SimpleString};
+use crate::{BulkString, RespDecode, RespEncode, RespError, SimpleString};
 
-use super::{calc_total_length, frame::RespFrame, parse_length, BUF_CAP};
+use super::{
+    blob_len, calc_total_length, frame::RespFrame, header_len, line_len, parse_length, put_blob,
+    put_header, put_line,
+};
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
-pub struct RespMap(pub(crate) BTreeMap<String, RespFrame>);
+pub struct RespMap(pub(crate) BTreeMap<Vec<u8>, RespFrame>);
 
 // map: %<number-of-entries>\r\n<key-1><value-1>...<key-n><value-n>
 impl RespEncode for RespMap {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("%{}\r\n", self.len()).into_bytes());
-        for (k, v) in self.0 {
-            buf.extend_from_slice(&SimpleString::new(k).encode());
-            buf.extend_from_slice(&v.encode());
-        }
-        buf
+    fn encoded_len(&self) -> usize {
+        header_len(self.len() as i64) + entries_len(self)
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_header(buf, b'%', self.len() as i64);
+        put_entries(buf, self);
     }
 }
 
@@ -39,9 +41,9 @@ impl RespDecode for RespMap {
         let len = s.parse()?;
         let mut map = RespMap::new();
         for _ in 0..len {
-            let key = SimpleString::decode(buf)?;
-            let value = RespFrame::decode(buf)?;
-            map.insert(key.0, value);
+            let key = decode_key(buf)?;
+            let value = RespFrame::decode_frame(buf)?;
+            map.insert(key, value);
         }
         Ok(map)
     }
@@ -53,7 +55,7 @@ impl RespDecode for RespMap {
 }
 
 impl Deref for RespMap {
-    type Target = BTreeMap<String, RespFrame>;
+    type Target = BTreeMap<Vec<u8>, RespFrame>;
     fn deref(&self) -> &Self::Target {
         &self.0
     }
@@ -75,12 +77,63 @@ impl Default for RespMap {
         Self::new()
     }
 }
-impl From<BTreeMap<String, RespFrame>> for RespMap {
-    fn from(map: BTreeMap<String, RespFrame>) -> Self {
+impl From<BTreeMap<Vec<u8>, RespFrame>> for RespMap {
+    fn from(map: BTreeMap<Vec<u8>, RespFrame>) -> Self {
         RespMap(map)
     }
 }
 
+// keys are sent as simple strings when they can be, binary keys as bulk strings
+fn is_simple_key(key: &[u8]) -> bool {
+    std::str::from_utf8(key).is_ok() && !key.contains(&b'\r') && !key.contains(&b'\n')
+}
+
+// the encoded keys and values of a map or an attribute
+pub(crate) fn entries_len(map: &RespMap) -> usize {
+    map.iter()
+        .map(|(k, v)| {
+            let key_len = if is_simple_key(k) {
+                line_len(k)
+            } else {
+                blob_len(k.len())
+            };
+            key_len + v.encoded_len()
+        })
+        .sum()
+}
+
+pub(crate) fn put_entries<B: BufMut>(buf: &mut B, map: &RespMap) {
+    for (k, v) in map.iter() {
+        put_key(buf, k);
+        v.encode_to(buf);
+    }
+}
+
+pub(crate) fn put_key<B: BufMut>(buf: &mut B, key: &[u8]) {
+    if is_simple_key(key) {
+        put_line(buf, b'+', key);
+    } else {
+        put_blob(buf, b'$', key);
+    }
+}
+
+pub(crate) fn decode_key(buf: &mut BytesMut) -> Result<Vec<u8>, RespError> {
+    match buf.first() {
+        Some(b'$') => BulkString::decode(buf)?
+            .0
+            .map(|key| key.to_vec())
+            .ok_or_else(|| RespError::InvalidFrame("map key must not be null".to_string())),
+        _ => Ok(SimpleString::decode(buf)?.0.into_bytes()),
+    }
+}
+
+pub(crate) fn key_length(buf: &[u8]) -> Result<usize, RespError> {
+    match buf.first() {
+        Some(b'$') => BulkString::expect_length(buf),
+        _ => SimpleString::expect_length(buf),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -89,7 +142,7 @@ mod tests {
     #[test]
     fn test_map_encode() {
         let mut m = RespMap::new();
-        m.insert("key".to_string(), RespFrame::Integer(1));
+        m.insert(b"key".to_vec(), RespFrame::Integer(1));
 
         assert_eq!(m.encode(), b"%1\r\n+key\r\n:1\r\n");
     }
@@ -98,8 +151,8 @@ mod tests {
     fn test_map_decode() {
         let mut buf = BytesMut::from("%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
         let mut map = RespMap::new();
-        map.insert("first".to_string(), 1.into());
-        map.insert("second".to_string(), 2.into());
+        map.insert(b"first".to_vec(), 1.into());
+        map.insert(b"second".to_vec(), 2.into());
         let frame = map;
         assert_eq!(RespMap::decode(&mut buf).unwrap(), frame);
 
@@ -107,4 +160,20 @@ mod tests {
         let ret = RespMap::decode(&mut buf);
         assert_eq!(ret, Err(RespError::NotComplete));
     }
+
+    #[test]
+    fn test_map_binary_keys() {
+        let mut m = RespMap::new();
+        m.insert(vec![0xff, 0x00], RespFrame::Integer(1));
+        m.insert(b"a\r\nb".to_vec(), RespFrame::Integer(2));
+        let encoded = m.clone().encode();
+        assert_eq!(
+            encoded,
+            b"%2\r\n$4\r\na\r\nb\r\n:2\r\n$2\r\n\xff\x00\r\n:1\r\n"
+        );
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(RespMap::expect_length(&buf), Ok(encoded.len()));
+        assert_eq!(RespMap::decode(&mut buf), Ok(m));
+    }
 }
@@ -1,33 +1,44 @@
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 use enum_dispatch::enum_dispatch;
 
 use thiserror::Error;
 
 mod array;
+mod attribute;
+mod big_number;
 mod bool;
+mod bulk_error;
 mod bulk_string;
 mod double;
 
 mod frame;
 mod integer;
+mod limits;
 mod map;
+mod push;
 mod set;
 mod simple_error;
 mod simple_string;
+mod verbatim_string;
 pub use self::array::RespArray;
+pub use attribute::RespAttribute;
+pub use big_number::BigNumber;
+pub use bulk_error::BulkError;
 
 pub use bulk_string::BulkString;
 
 pub use frame::*;
 use lazy_static::lazy_static;
+pub use limits::*;
 pub use map::RespMap;
+pub use push::RespPush;
 pub use set::RespSet;
 pub use simple_error::SimpleError;
 pub use simple_string::SimpleString;
+pub use verbatim_string::VerbatimString;
 
 const CRLF: &[u8] = b"\r\n";
 const CRLF_LEN: usize = CRLF.len();
-const BUF_CAP: usize = 4096;
 lazy_static! {
     static ref NULL_BULK_STRING: Vec<u8> = "$-1\r\n".into();
     static ref NULL_ARRAY: Vec<u8> = "*-1\r\n".into();
@@ -35,7 +46,20 @@ lazy_static! {
 
 #[enum_dispatch]
 pub trait RespEncode {
-    fn encode(self) -> Vec<u8>;
+    // the exact number of bytes `encode_to` writes
+    fn encoded_len(&self) -> usize;
+
+    // write the frame in place, nothing is allocated when the buffer has room for it
+    fn encode_to<B: BufMut>(&self, buf: &mut B);
+
+    fn encode(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        let mut buf = Vec::with_capacity(self.encoded_len());
+        self.encode_to(&mut buf);
+        buf
+    }
 }
 
 #[derive(Error, Debug, PartialEq, Eq)]
@@ -48,6 +72,18 @@ pub enum RespError {
     InvalidFrameLength(isize),
     #[error("Frame is not complete ")]
     NotComplete,
+    #[error("Protocol error: invalid bulk length")]
+    BulkTooLarge(usize),
+    #[error("Protocol error: invalid multibulk length")]
+    MultibulkTooLarge(usize),
+    #[error("Protocol error: frames nested deeper than {0} levels")]
+    NestingTooDeep(usize),
+    #[error("Protocol error: query buffer of {0} bytes is over the client limit")]
+    QueryBufferFull(usize),
+    #[error("Protocol error: too big inline request")]
+    InlineTooLarge,
+    #[error("Protocol error: unbalanced quotes in request")]
+    UnbalancedQuotes,
 
     #[error("Invalid integer: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
@@ -61,6 +97,11 @@ pub trait RespDecode: Sized {
     const PREFIX: &'static str;
     fn decode(buf: &mut BytesMut) -> Result<Self, RespError>;
     fn expect_length(buf: &[u8]) -> Result<usize, RespError>;
+
+    fn decode_with_limits(buf: &mut BytesMut, limits: &RespLimits) -> Result<Self, RespError> {
+        limits.check(buf)?;
+        Self::decode(buf)
+    }
 }
 
 // utility functions
@@ -105,6 +146,39 @@ fn extract_simple_frame_data(buf: &[u8], prefix: &str) -> Result<usize, RespErro
     Ok(end)
 }
 
+// a type prefix followed by a length or an integer, e.g. `*2\r\n` or `:1000\r\n`
+fn header_len(n: i64) -> usize {
+    1 + itoa::Buffer::new().format(n).len() + CRLF_LEN
+}
+
+fn put_header<B: BufMut>(buf: &mut B, prefix: u8, n: i64) {
+    buf.put_u8(prefix);
+    buf.put_slice(itoa::Buffer::new().format(n).as_bytes());
+    buf.put_slice(CRLF);
+}
+
+// a type prefix followed by a line of text, e.g. `+OK\r\n`
+fn line_len(s: &[u8]) -> usize {
+    1 + s.len() + CRLF_LEN
+}
+
+fn put_line<B: BufMut>(buf: &mut B, prefix: u8, s: &[u8]) {
+    buf.put_u8(prefix);
+    buf.put_slice(s);
+    buf.put_slice(CRLF);
+}
+
+// a length prefixed payload, e.g. `$6\r\nfoobar\r\n`
+fn blob_len(len: usize) -> usize {
+    header_len(len as i64) + len + CRLF_LEN
+}
+
+fn put_blob<B: BufMut>(buf: &mut B, prefix: u8, data: &[u8]) {
+    put_header(buf, prefix, data.len() as i64);
+    buf.put_slice(data);
+    buf.put_slice(CRLF);
+}
+
 // find nth CRLF in the buffer
 fn find_crlf(buf: &[u8], nth: usize) -> Option<usize> {
     let mut count = 0;
@@ -132,28 +206,32 @@ fn calc_total_length(buf: &[u8], end: usize, len: usize, prefix: &str) -> Result
     let mut total = end + CRLF_LEN;
     let mut data: &[u8] = &buf[total..];
     match prefix {
-        "*" | "~" => {
+        "*" | "~" | ">" => {
             // find nth CRLF in the buffer, for array and set, we need to find 1 CRLF for each element
             for _ in 0..len {
-                let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                let len = RespFrame::frame_length(data)?;
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
 
                 total += len;
             }
             Ok(total)
         }
-        "%" => {
+        "%" | "|" => {
             // find nth CRLF in the buffer. For map, we need to find 2 CRLF for each key-value pair
             for _ in 0..len {
-                let len = SimpleString::expect_length(data)?;
+                let len = map::key_length(data)?;
 
-                data = &data[len..];
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
 
-                let len = RespFrame::expect_length(data)?;
-                data = &data[len..];
+                let len = RespFrame::frame_length(data)?;
+                data = data.get(len..).ok_or(RespError::NotComplete)?;
                 total += len;
             }
+            // attributes are followed by the reply they describe
+            if prefix == "|" {
+                total += RespFrame::frame_length(data)?;
+            }
             Ok(total)
         }
         _ => Ok(len + CRLF_LEN),
@@ -0,0 +1,93 @@
+use std::ops::Deref;
+
+use bytes::{Buf, BufMut, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError};
+
+use super::{calc_total_length, frame::RespFrame, header_len, parse_length, put_header, CRLF_LEN};
+
+// out of band data sent by the server to RESP3 clients
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct RespPush(pub(crate) Vec<RespFrame>);
+
+// push: ><number-of-elements>\r\n<element-1>...<element-n>
+impl RespEncode for RespPush {
+    fn encoded_len(&self) -> usize {
+        header_len(self.len() as i64) + self.iter().map(|f| f.encoded_len()).sum::<usize>()
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_header(buf, b'>', self.len() as i64);
+        for frame in self.iter() {
+            frame.encode_to(buf);
+        }
+    }
+}
+
+impl RespDecode for RespPush {
+    const PREFIX: &'static str = ">";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total_len = calc_total_length(buf, end, len, Self::PREFIX)?;
+        if buf.len() < total_len {
+            return Err(RespError::NotComplete);
+        }
+        buf.advance(end + CRLF_LEN);
+        let mut frames = Vec::with_capacity(len);
+        for _ in 0..len {
+            frames.push(RespFrame::decode_frame(buf)?);
+        }
+        Ok(RespPush::new(frames))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        calc_total_length(buf, end, len, Self::PREFIX)
+    }
+}
+
+impl Deref for RespPush {
+    type Target = Vec<RespFrame>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl RespPush {
+    pub fn new(s: impl Into<Vec<RespFrame>>) -> Self {
+        RespPush(s.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_push_encode() {
+        let frame: RespFrame = RespPush::new([
+            BulkString::new("invalidate".to_string()).into(),
+            RespFrame::Integer(1),
+        ])
+        .into();
+        assert_eq!(frame.encode(), b">2\r\n$10\r\ninvalidate\r\n:1\r\n");
+    }
+
+    #[test]
+    fn test_push_decode() {
+        let mut buf = BytesMut::from(">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n");
+        let frame = RespPush::decode(&mut buf).unwrap();
+        assert_eq!(
+            frame,
+            RespPush::new([
+                BulkString::new("invalidate".to_string()).into(),
+                crate::RespArray::new([BulkString::new("foo".to_string()).into()]).into(),
+            ])
+        );
+
+        let mut buf = BytesMut::from(">2\r\n$10\r\ninvalidate\r\n");
+        assert_eq!(RespPush::decode(&mut buf), Err(RespError::NotComplete));
+    }
+}
@@ -1,23 +1,25 @@
 use std::ops::{Deref, DerefMut};
 
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{calc_total_length, frame::RespFrame, parse_length, BUF_CAP};
+use super::{calc_total_length, frame::RespFrame, header_len, parse_length, put_header};
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct RespSet(pub(crate) Vec<RespFrame>);
 
 // set: ~<number-of-elements>\r\n<element-1>...<element-n>
 impl RespEncode for RespSet {
-    fn encode(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(BUF_CAP);
-        buf.extend_from_slice(&format!("~{}\r\n", self.len()).into_bytes());
-        for frame in self.0 {
-            buf.extend_from_slice(&frame.encode());
+    fn encoded_len(&self) -> usize {
+        header_len(self.len() as i64) + self.iter().map(|f| f.encoded_len()).sum::<usize>()
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_header(buf, b'~', self.len() as i64);
+        for frame in self.iter() {
+            frame.encode_to(buf);
         }
-        buf
     }
 }
 
@@ -35,7 +37,7 @@ impl RespDecode for RespSet {
         let len = s.parse()?;
         let mut set = RespSet::new();
         for _ in 0..len {
-            let frame = RespFrame::decode(buf)?;
+            let frame = RespFrame::decode_frame(buf)?;
             set.push(frame);
         }
         Ok(set)
@@ -1,17 +1,21 @@
 use std::ops::Deref;
 
-use bytes::BytesMut;
+use bytes::{BufMut, BytesMut};
 
 use crate::{RespDecode, RespEncode, RespError};
 
-use super::{extract_simple_frame_data, CRLF_LEN};
+use super::{extract_simple_frame_data, line_len, put_line, CRLF_LEN};
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
 pub struct SimpleError(pub(crate) String);
 
 impl RespEncode for SimpleError {
-    fn encode(self) -> Vec<u8> {
-        format!("-{}\r\n", self.0).into_bytes()
+    fn encoded_len(&self) -> usize {
+        line_len(self.0.as_bytes())
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_line(buf, b'-', self.0.as_bytes());
     }
 }
 impl RespDecode for SimpleError {
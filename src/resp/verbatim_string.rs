@@ -0,0 +1,87 @@
+use bytes::{BufMut, BytesMut};
+
+use crate::{RespDecode, RespEncode, RespError};
+
+use super::{blob_len, parse_length, put_header, CRLF, CRLF_LEN};
+
+// a string with a three bytes format hint, e.g. `txt` or `mkd`
+#[derive(Debug, PartialEq, Eq, PartialOrd, Clone)]
+pub struct VerbatimString {
+    pub(crate) format: [u8; 3],
+    pub(crate) data: Vec<u8>,
+}
+
+// verbatim string: =<length>\r\n<encoding>:<data>\r\n
+impl RespEncode for VerbatimString {
+    fn encoded_len(&self) -> usize {
+        // the format and its `:` separator are part of the payload
+        blob_len(self.data.len() + 4)
+    }
+
+    fn encode_to<B: BufMut>(&self, buf: &mut B) {
+        put_header(buf, b'=', self.data.len() as i64 + 4);
+        buf.put_slice(&self.format);
+        buf.put_u8(b':');
+        buf.put_slice(&self.data);
+        buf.put_slice(CRLF);
+    }
+}
+
+impl RespDecode for VerbatimString {
+    const PREFIX: &'static str = "=";
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        let total = Self::expect_length(buf)?;
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let data = buf.split_to(total);
+        let content = &data[end + CRLF_LEN..end + CRLF_LEN + len];
+        if len < 4 || content[3] != b':' {
+            return Err(RespError::InvalidFrame(
+                "verbatim string must start with a 3 bytes format and ':'".to_string(),
+            ));
+        }
+        Ok(VerbatimString::new(
+            [content[0], content[1], content[2]],
+            &content[4..],
+        ))
+    }
+
+    fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        let (end, len) = parse_length(buf, Self::PREFIX)?;
+        let total = end + CRLF_LEN + len + CRLF_LEN;
+        if buf.len() < total {
+            return Err(RespError::NotComplete);
+        }
+        Ok(total)
+    }
+}
+
+impl VerbatimString {
+    pub fn new(format: [u8; 3], data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            format,
+            data: data.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RespFrame;
+
+    #[test]
+    fn test_verbatim_string_encode() {
+        let frame: RespFrame = VerbatimString::new(*b"txt", "Some string").into();
+        assert_eq!(frame.encode(), b"=15\r\ntxt:Some string\r\n");
+    }
+
+    #[test]
+    fn test_verbatim_string_decode() {
+        let mut buf = BytesMut::from("=15\r\ntxt:Some string\r\n");
+        let frame = VerbatimString::decode(&mut buf).unwrap();
+        assert_eq!(frame, VerbatimString::new(*b"txt", "Some string"));
+
+        let mut buf = BytesMut::from("=3\r\ntxt\r\n");
+        assert!(VerbatimString::decode(&mut buf).is_err());
+    }
+}
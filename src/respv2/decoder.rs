@@ -0,0 +1,515 @@
+use crate::{
+    BulkString, RespArray, RespAttribute, RespError, RespFrame, RespLimits, RespMap, RespNull,
+    RespPush, RespSet,
+};
+use bytes::{Buf, Bytes, BytesMut};
+
+use super::{parse_frame, split_inline_args};
+
+const CRLF: &[u8] = b"\r\n";
+
+// room made at a time for a bulk payload that is still arriving, like redis' PROTO_IOBUF_LEN.
+// the buffer grows with the bytes the client actually sends, not with the length it announces
+const BULK_RESERVE_LEN: usize = 64 * 1024;
+
+// frames shorter than this are copied out of the read buffer, like redis' PROTO_MBULK_BIG_ARG.
+// a shared slice would keep the whole buffer alive for as long as the stored value lives
+const BIG_ARG_LEN: usize = 32 * 1024;
+
+// an incremental decoder, frames are parsed element by element as their bytes arrive and
+// parsing resumes where it stopped on the next call instead of starting over. every byte is
+// parsed once, however the frame is split across reads
+#[derive(Debug)]
+pub struct RespFrameDecoder {
+    // checked as each element arrives, oversize lengths are rejected from their header
+    limits: RespLimits,
+    // aggregates whose elements are still arriving, innermost last
+    stack: Vec<Aggregate>,
+    // bytes of the current line already searched for its CRLF
+    scanned: usize,
+    // bytes of the frame being received already consumed from the buffer
+    consumed: usize,
+}
+
+// an aggregate whose elements are still arriving
+#[derive(Debug)]
+struct Aggregate {
+    prefix: u8,
+    // elements still expected, map values count as one, attributes count their reply
+    remaining: usize,
+    frames: Vec<RespFrame>,
+    map: RespMap,
+    // the key read ahead of the value being received
+    key: Option<Vec<u8>>,
+}
+
+impl RespFrameDecoder {
+    pub fn new(limits: RespLimits) -> Self {
+        Self {
+            limits,
+            stack: Vec::new(),
+            scanned: 0,
+            consumed: 0,
+        }
+    }
+
+    pub fn set_limits(&mut self, limits: RespLimits) {
+        self.limits = limits;
+    }
+
+    // decode a frame from the front of `buf`, returns None when more bytes are needed. complete
+    // elements are consumed from `buf` as they are parsed, bulk strings keep sharing it
+    pub fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        let len = buf.len();
+        let ret = self.decode_frame(buf);
+        // what is buffered of the request, parsed or not, counts against the query buffer limit
+        match ret {
+            Ok(Some(_)) => self.consumed = 0,
+            Ok(None) => {
+                self.consumed += len - buf.len();
+                if self.consumed + buf.len() > self.limits.max_query_buffer {
+                    return Err(RespError::QueryBufferFull(self.consumed + buf.len()));
+                }
+            }
+            Err(_) => {}
+        }
+        ret
+    }
+
+    fn decode_frame(&mut self, buf: &mut BytesMut) -> Result<Option<RespFrame>, RespError> {
+        loop {
+            // anything not starting with a type byte is an inline command, typed over telnet
+            if self.stack.is_empty() && buf.first().is_some_and(|c| !is_type_byte(*c)) {
+                match self.decode_inline(buf)? {
+                    Some(args) if args.is_empty() => continue,
+                    Some(args) => {
+                        let args = args.into_iter().map(|a| BulkString::new(a).into());
+                        return Ok(Some(RespArray::new(args.collect::<Vec<_>>()).into()));
+                    }
+                    None => return Ok(None),
+                }
+            }
+
+            let expect_key = self.stack.last().is_some_and(Aggregate::expects_key);
+            let Some(end) = self.find_crlf(buf) else {
+                return Ok(None);
+            };
+            let prefix = buf[0];
+            let line = end + CRLF.len();
+
+            if expect_key {
+                let key = match prefix {
+                    b'+' => {
+                        let key = buf[1..end].to_vec();
+                        buf.advance(line);
+                        key
+                    }
+                    b'$' => match self.bulk_len(buf, end)? {
+                        // a null bulk string is just its header
+                        Some(total) if total == line => {
+                            return Err(RespError::InvalidFrame(
+                                "map keys cannot be null".to_string(),
+                            ))
+                        }
+                        Some(total) => {
+                            let key = buf[line..total - CRLF.len()].to_vec();
+                            buf.advance(total);
+                            key
+                        }
+                        None => return Ok(None),
+                    },
+                    _ => {
+                        return Err(RespError::InvalidFrame(format!(
+                            "map keys must be strings, got: {:?}",
+                            prefix as char
+                        )))
+                    }
+                };
+                if let Some(agg) = self.stack.last_mut() {
+                    agg.key = Some(key);
+                }
+                continue;
+            }
+
+            let frame = match prefix {
+                b'*' | b'~' | b'>' | b'%' | b'|' => {
+                    let len = parse_len(&buf[1..end])?;
+                    if prefix == b'*' && len == -1 {
+                        buf.advance(line);
+                        RespArray(None).into()
+                    } else if len < 0 {
+                        return Err(RespError::InvalidFrameLength(len as isize));
+                    } else {
+                        self.limits.check_multibulk_len(len as usize)?;
+                        self.limits.check_nesting(self.stack.len())?;
+                        buf.advance(line);
+                        let agg = Aggregate::new(prefix, len as usize);
+                        if agg.remaining > 0 {
+                            self.stack.push(agg);
+                            continue;
+                        }
+                        agg.into_frame()
+                    }
+                }
+                b'$' | b'!' | b'=' => match self.bulk_len(buf, end)? {
+                    Some(len) => scalar(buf, len)?,
+                    None => return Ok(None),
+                },
+                _ => scalar(buf, line)?,
+            };
+
+            // hand the frame to the aggregates it completes
+            let mut frame = frame;
+            loop {
+                let Some(mut agg) = self.stack.pop() else {
+                    return Ok(Some(frame));
+                };
+                agg.push(frame);
+                if agg.remaining > 0 {
+                    self.stack.push(agg);
+                    break;
+                }
+                frame = agg.into_frame();
+            }
+        }
+    }
+
+    // the length of a length prefixed frame whose header ends at `end`, None until it has arrived
+    fn bulk_len(&self, buf: &mut BytesMut, end: usize) -> Result<Option<usize>, RespError> {
+        let line = end + CRLF.len();
+        let len = parse_len(&buf[1..end])?;
+        if len == -1 && buf[0] == b'$' {
+            return Ok(Some(line));
+        }
+        if len < 0 {
+            return Err(RespError::InvalidFrameLength(len as isize));
+        }
+        self.limits.check_bulk_len(len as usize)?;
+        let total = line + len as usize + CRLF.len();
+        if buf.len() < total {
+            buf.reserve((total - buf.len()).min(BULK_RESERVE_LEN));
+            return Ok(None);
+        }
+        Ok(Some(total))
+    }
+
+    // the arguments of an inline command terminated by \n or \r\n, None until it has arrived
+    fn decode_inline(&mut self, buf: &mut BytesMut) -> Result<Option<Vec<Vec<u8>>>, RespError> {
+        let from = self.scanned;
+        let Some(end) = buf[from..].iter().position(|c| *c == b'\n') else {
+            if buf.len() > self.limits.max_inline_len {
+                return Err(RespError::InlineTooLarge);
+            }
+            self.scanned = buf.len();
+            return Ok(None);
+        };
+        self.scanned = 0;
+        let end = from + end;
+        if end > self.limits.max_inline_len {
+            return Err(RespError::InlineTooLarge);
+        }
+        let line = buf.split_to(end + 1);
+        let line = line.strip_suffix(b"\r\n").unwrap_or(&line[..end]);
+        match split_inline_args(line) {
+            Some(args) => Ok(Some(args)),
+            None => Err(RespError::UnbalancedQuotes),
+        }
+    }
+
+    // find the CRLF ending the line at the front of `buf`, resuming the search where the last
+    // call gave up
+    fn find_crlf(&mut self, buf: &[u8]) -> Option<usize> {
+        let from = self.scanned.saturating_sub(1).max(1);
+        match buf.get(from..)?.windows(2).position(|w| w == CRLF) {
+            Some(i) => {
+                self.scanned = 0;
+                Some(from + i)
+            }
+            None => {
+                self.scanned = buf.len();
+                None
+            }
+        }
+    }
+}
+
+impl Default for RespFrameDecoder {
+    fn default() -> Self {
+        Self::new(RespLimits::default())
+    }
+}
+
+fn is_type_byte(c: u8) -> bool {
+    matches!(
+        c,
+        b'+' | b'-'
+            | b':'
+            | b'$'
+            | b'*'
+            | b'_'
+            | b'#'
+            | b','
+            | b'%'
+            | b'~'
+            | b'('
+            | b'!'
+            | b'='
+            | b'|'
+            | b'>'
+    )
+}
+
+// a complete frame without children, parsed by the v2 parser
+fn scalar(buf: &mut BytesMut, len: usize) -> Result<RespFrame, RespError> {
+    let data = if len < BIG_ARG_LEN {
+        let data = Bytes::copy_from_slice(&buf[..len]);
+        buf.advance(len);
+        data
+    } else {
+        buf.split_to(len).freeze()
+    };
+    parse_frame(&mut data.as_ref(), &data).map_err(|e| RespError::InvalidFrame(e.to_string()))
+}
+
+fn parse_len(s: &[u8]) -> Result<i64, RespError> {
+    Ok(String::from_utf8_lossy(s).parse()?)
+}
+
+impl Aggregate {
+    fn new(prefix: u8, len: usize) -> Self {
+        let remaining = if prefix == b'|' { len + 1 } else { len };
+        // the length is not trusted for the capacity, the elements have not arrived yet
+        let cap = if matches!(prefix, b'%' | b'|') {
+            0
+        } else {
+            len.min(1024)
+        };
+        Self {
+            prefix,
+            remaining,
+            frames: Vec::with_capacity(cap),
+            map: RespMap::new(),
+            key: None,
+        }
+    }
+
+    fn expects_key(&self) -> bool {
+        let entries = match self.prefix {
+            b'%' => self.remaining,
+            b'|' => self.remaining - 1,
+            _ => 0,
+        };
+        entries > 0 && self.key.is_none()
+    }
+
+    fn push(&mut self, frame: RespFrame) {
+        match self.key.take() {
+            Some(key) => {
+                self.map.insert(key, frame);
+            }
+            None => self.frames.push(frame),
+        }
+        self.remaining -= 1;
+    }
+
+    fn into_frame(mut self) -> RespFrame {
+        match self.prefix {
+            b'*' => RespArray::new(self.frames).into(),
+            b'~' => RespSet(self.frames).into(),
+            b'>' => RespPush::new(self.frames).into(),
+            b'%' => self.map.into(),
+            _ => match self.frames.pop() {
+                Some(reply) => RespAttribute::new(self.map, reply).into(),
+                None => RespNull.into(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BulkString, RespDecodeV2};
+
+    #[test]
+    fn test_decoder_resumes_across_reads() {
+        let data =
+            b"*3\r\n$3\r\nset\r\n%1\r\n+k\r\n#t\r\n|1\r\n$3\r\nttl\r\n:1\r\n$5\r\nhello\r\n+OK\r\n";
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::new();
+        let mut frames = Vec::new();
+        for b in data {
+            buf.extend_from_slice(&[*b]);
+            if let Some(frame) = decoder.decode(&mut buf).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert!(buf.is_empty());
+
+        let mut whole = BytesMut::from(&data[..]);
+        let first = RespFrame::decode(&mut whole).unwrap();
+        let second = RespFrame::decode(&mut whole).unwrap();
+        assert_eq!(frames, vec![first, second]);
+    }
+
+    #[test]
+    fn test_decoder_consumes_complete_elements() {
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("*2\r\n$3\r\nget\r\n$5\r\nhel");
+        assert_eq!(decoder.decode(&mut buf), Ok(None));
+        // only the element still arriving is left to parse
+        assert_eq!(&buf[..], b"$5\r\nhel");
+
+        buf.extend_from_slice(b"lo\r\n");
+        assert_eq!(
+            decoder.decode(&mut buf),
+            Ok(Some(
+                RespArray::new([
+                    BulkString::from("get").into(),
+                    BulkString::from("hello").into()
+                ])
+                .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_decoder_rejects_bad_frames() {
+        let mut decoder = RespFrameDecoder::new(RespLimits {
+            max_bulk_len: 10,
+            ..Default::default()
+        });
+        let mut buf = BytesMut::from("*1\r\n$11\r\n");
+        assert_eq!(decoder.decode(&mut buf), Err(RespError::BulkTooLarge(11)));
+
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("%1\r\n$-1\r\n:1\r\n");
+        assert!(decoder.decode(&mut buf).is_err());
+        let mut buf = BytesMut::from("%1\r\n$-01\r\n:1\r\n");
+        assert!(decoder.decode(&mut buf).is_err());
+
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("~-1\r\n");
+        assert_eq!(
+            decoder.decode(&mut buf),
+            Err(RespError::InvalidFrameLength(-1))
+        );
+
+        let mut decoder = RespFrameDecoder::default();
+        // an unknown type, inline commands are only accepted outside of frames
+        let mut buf = BytesMut::from("*1\r\nhello\r\n");
+        assert!(decoder.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_decoder_inline_commands() {
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("\r\nSET a \"hello world\"\r\nping\n*1\r\n$4\r\nping\r\n");
+        let args = |args: &[&str]| -> RespFrame {
+            let args = args.iter().map(|a| BulkString::from(*a).into());
+            RespArray::new(args.collect::<Vec<_>>()).into()
+        };
+        assert_eq!(
+            decoder.decode(&mut buf),
+            Ok(Some(args(&["SET", "a", "hello world"])))
+        );
+        assert_eq!(decoder.decode(&mut buf), Ok(Some(args(&["ping"]))));
+        assert_eq!(decoder.decode(&mut buf), Ok(Some(args(&["ping"]))));
+
+        let mut buf = BytesMut::from("get a");
+        assert_eq!(decoder.decode(&mut buf), Ok(None));
+        buf.extend_from_slice(b"bc\r\n");
+        assert_eq!(decoder.decode(&mut buf), Ok(Some(args(&["get", "abc"]))));
+    }
+
+    #[test]
+    fn test_decoder_inline_limits() {
+        let mut decoder = RespFrameDecoder::new(RespLimits {
+            max_inline_len: 8,
+            ..Default::default()
+        });
+        let mut buf = BytesMut::from("get abcdef");
+        assert_eq!(decoder.decode(&mut buf), Err(RespError::InlineTooLarge));
+
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("set a \"b\r\n");
+        assert_eq!(decoder.decode(&mut buf), Err(RespError::UnbalancedQuotes));
+    }
+
+    #[test]
+    fn test_decoder_limits() {
+        let limits = RespLimits {
+            max_multibulk_len: 2,
+            max_nesting: 2,
+            max_query_buffer: 30,
+            ..Default::default()
+        };
+        let mut decoder = RespFrameDecoder::new(limits);
+        let mut buf = BytesMut::from("*3\r\n");
+        assert_eq!(
+            decoder.decode(&mut buf),
+            Err(RespError::MultibulkTooLarge(3))
+        );
+
+        let mut decoder = RespFrameDecoder::new(limits);
+        let mut buf = BytesMut::from("*1\r\n*1\r\n*1\r\n");
+        assert_eq!(decoder.decode(&mut buf), Err(RespError::NestingTooDeep(2)));
+
+        // consumed elements still count against the query buffer
+        let mut decoder = RespFrameDecoder::new(limits);
+        let mut buf = BytesMut::from("*2\r\n$10\r\n0123456789\r\n");
+        assert_eq!(decoder.decode(&mut buf), Ok(None));
+        buf.extend_from_slice(b"$10\r\n01234");
+        assert_eq!(
+            decoder.decode(&mut buf),
+            Err(RespError::QueryBufferFull(31))
+        );
+    }
+
+    #[test]
+    fn test_decoder_grows_with_the_payload() {
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("*1\r\n$536870912\r\n");
+        assert_eq!(decoder.decode(&mut buf), Ok(None));
+        // the announced 512mb are not reserved up front
+        assert!(buf.capacity() <= 2 * BULK_RESERVE_LEN);
+
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::from("$200000\r\n");
+        let payload = vec![b'x'; 200_000];
+        for chunk in payload.chunks(1000) {
+            assert_eq!(decoder.decode(&mut buf), Ok(None));
+            buf.extend_from_slice(chunk);
+        }
+        buf.extend_from_slice(CRLF);
+        assert_eq!(
+            decoder.decode(&mut buf),
+            Ok(Some(BulkString::new(payload).into()))
+        );
+    }
+
+    #[test]
+    fn test_decoder_copies_small_payloads() {
+        let decode_in_place = |decoder: &mut RespFrameDecoder, buf: &mut BytesMut| {
+            let start = buf.as_ptr() as usize;
+            let allocation = start..start + buf.capacity();
+            let frame = decoder.decode(buf).unwrap().unwrap();
+            let RespFrame::BulkString(BulkString(Some(data))) = frame else {
+                panic!("expected a bulk string");
+            };
+            allocation.contains(&(data.as_ptr() as usize))
+        };
+
+        let mut decoder = RespFrameDecoder::default();
+        let mut buf = BytesMut::with_capacity(1024 * 1024);
+        buf.extend_from_slice(b"$5\r\nhello\r\n");
+        // a small value does not pin the read buffer
+        assert!(!decode_in_place(&mut decoder, &mut buf));
+
+        let mut buf = BytesMut::with_capacity(1024 * 1024);
+        buf.extend_from_slice(format!("${}\r\n", BIG_ARG_LEN).as_bytes());
+        buf.extend_from_slice(&vec![b'x'; BIG_ARG_LEN]);
+        buf.extend_from_slice(CRLF);
+        assert!(decode_in_place(&mut decoder, &mut buf));
+    }
+}
@@ -0,0 +1,111 @@
+// split an inline command into its arguments like redis does. arguments are separated by
+// whitespace, double quoted ones understand escapes like \n and \x00, single quoted ones only \'.
+// returns None when quotes are unbalanced
+pub fn split_inline_args(line: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while i < line.len() && line[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == line.len() {
+            return Some(args);
+        }
+
+        let mut arg = Vec::new();
+        // the quote the argument is in, if any
+        let mut quote = None;
+        loop {
+            let c = line.get(i).copied();
+            match (quote, c) {
+                // quotes must be closed before the line ends
+                (Some(_), None) => return None,
+                (None, None) => break,
+                (None, Some(c)) if c.is_ascii_whitespace() => break,
+                (None, Some(c @ (b'"' | b'\''))) => quote = Some(c),
+                (None, Some(c)) => arg.push(c),
+                (Some(b'"'), Some(b'\\')) if i + 3 < line.len() && line[i + 1] == b'x' => {
+                    match hex_byte(line[i + 2], line[i + 3]) {
+                        Some(b) => {
+                            arg.push(b);
+                            i += 3;
+                        }
+                        None => arg.push(b'\\'),
+                    }
+                }
+                (Some(b'"'), Some(b'\\')) if i + 1 < line.len() => {
+                    i += 1;
+                    arg.push(match line[i] {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'b' => 0x08,
+                        b'a' => 0x07,
+                        c => c,
+                    });
+                }
+                (Some(b'\''), Some(b'\\')) if line.get(i + 1) == Some(&b'\'') => {
+                    i += 1;
+                    arg.push(b'\'');
+                }
+                (Some(q), Some(c)) if c == q => {
+                    // the closing quote must end the argument
+                    if line.get(i + 1).is_some_and(|c| !c.is_ascii_whitespace()) {
+                        return None;
+                    }
+                    i += 1;
+                    break;
+                }
+                (Some(_), Some(c)) => arg.push(c),
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+fn hex_byte(hi: u8, lo: u8) -> Option<u8> {
+    let digit = |c: u8| (c as char).to_digit(16);
+    Some((digit(hi)? * 16 + digit(lo)?) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Option<Vec<String>> {
+        split_inline_args(line.as_bytes()).map(|args| {
+            args.into_iter()
+                .map(|a| String::from_utf8_lossy(&a).into_owned())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_split_inline_args() {
+        assert_eq!(
+            args("  set  a 1 "),
+            Some(vec!["set".into(), "a".into(), "1".into()])
+        );
+        assert_eq!(args(""), Some(vec![]));
+        assert_eq!(
+            args(r#"set "hello world" 'it\'s'"#),
+            Some(vec!["set".into(), "hello world".into(), "it's".into()])
+        );
+        assert_eq!(
+            args(r#"echo "a\tb\n\x41\"" '\n'"#),
+            Some(vec!["echo".into(), "a\tb\nA\"".into(), "\\n".into()])
+        );
+        assert_eq!(
+            args(r#"echo "" x"#),
+            Some(vec!["echo".into(), "".into(), "x".into()])
+        );
+    }
+
+    #[test]
+    fn test_split_inline_args_unbalanced_quotes() {
+        assert_eq!(args(r#"set "a 1"#), None);
+        assert_eq!(args("set 'a"), None);
+        assert_eq!(args(r#"set "a"b"#), None);
+    }
+}
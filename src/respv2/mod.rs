@@ -1,23 +1,35 @@
+mod decoder;
+mod inline;
 mod parser;
 
+pub use self::decoder::RespFrameDecoder;
+pub use self::inline::split_inline_args;
 pub use self::parser::{parse_frame, parse_frame_length};
-use crate::{RespError, RespFrame};
+use crate::{RespError, RespFrame, RespLimits};
 use bytes::BytesMut;
 
 pub trait RespDecodeV2: Sized {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError>;
+    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
+        Self::decode_with_limits(buf, &RespLimits::default())
+    }
+
     fn expect_length(buf: &[u8]) -> Result<usize, RespError>;
+
+    fn decode_with_limits(buf: &mut BytesMut, limits: &RespLimits) -> Result<Self, RespError>;
 }
 
 impl RespDecodeV2 for RespFrame {
-    fn decode(buf: &mut BytesMut) -> Result<Self, RespError> {
-        let len = Self::expect_length(buf)?;
-        let data = buf.split_to(len);
+    // the limits are checked once for the whole frame, then the recursive parsers run
+    fn decode_with_limits(buf: &mut BytesMut, limits: &RespLimits) -> Result<Self, RespError> {
+        limits.check(buf)?;
+        let len = parse_frame_length(buf)?;
+        let data = buf.split_to(len).freeze();
 
-        parse_frame(&mut data.as_ref()).map_err(|e| RespError::InvalidFrame(e.to_string()))
+        parse_frame(&mut data.as_ref(), &data).map_err(|e| RespError::InvalidFrame(e.to_string()))
     }
 
     fn expect_length(buf: &[u8]) -> Result<usize, RespError> {
+        RespLimits::default().check(buf)?;
         parse_frame_length(buf)
     }
 }
@@ -25,7 +37,14 @@ impl RespDecodeV2 for RespFrame {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{BulkString, RespArray};
+    use crate::{
+        BigNumber, BulkError, BulkString, RespArray, RespAttribute, RespEncode, RespMap, RespNull,
+        RespPush, RespSet, SimpleError, SimpleString, VerbatimString,
+    };
+    use proptest::{
+        collection::{btree_map, vec},
+        prelude::*,
+    };
     use std::collections::BTreeMap;
 
     #[test]
@@ -144,19 +163,229 @@ mod tests {
 
     #[test]
     fn respv2_map_length_should_work() {
-        let buf = b"%2\r\n+OK\r\n-ERR\r\n";
+        let buf = b"%1\r\n+OK\r\n-ERR\r\n";
         let len = RespFrame::expect_length(buf).unwrap();
         assert_eq!(len, buf.len());
     }
 
     #[test]
     fn respv2_map_should_work() {
-        let mut buf = BytesMut::from("%2\r\n+OK\r\n-ERR\r\n");
+        let mut buf = BytesMut::from("%1\r\n+OK\r\n-ERR\r\n");
         let frame = RespFrame::decode(&mut buf).unwrap();
-        let items: BTreeMap<String, RespFrame> =
-            [("OK".to_string(), RespFrame::Error("ERR".into()))]
+        let items: BTreeMap<Vec<u8>, RespFrame> =
+            [(b"OK".to_vec(), RespFrame::Error("ERR".into()))]
                 .into_iter()
                 .collect();
         assert_eq!(frame, RespFrame::Map(items.into()));
     }
+
+    #[test]
+    fn respv2_set_should_work() {
+        let buf = b"~2\r\n+OK\r\n:1\r\n";
+        assert_eq!(RespFrame::expect_length(buf), Ok(buf.len()));
+        let frame = RespFrame::decode(&mut BytesMut::from(&buf[..])).unwrap();
+        assert_eq!(
+            frame,
+            RespSet(vec![RespFrame::SimpleString("OK".into()), 1.into()]).into()
+        );
+    }
+
+    // encode then decode with both decoders, every RESP3 type must survive the trip
+    fn assert_round_trip(frame: RespFrame) {
+        let encoded = frame.clone().encode();
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(
+            <RespFrame as crate::RespDecode>::expect_length(&buf),
+            Ok(encoded.len())
+        );
+        assert_eq!(
+            <RespFrame as crate::RespDecode>::decode(&mut buf),
+            Ok(frame.clone())
+        );
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(RespFrame::expect_length(&buf), Ok(encoded.len()));
+        assert_eq!(RespFrame::decode(&mut buf), Ok(frame));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn respv2_big_number_round_trip() {
+        assert_round_trip(
+            BigNumber::new("-3492890328409238509324850943850943825024385")
+                .unwrap()
+                .into(),
+        );
+    }
+
+    #[test]
+    fn respv2_bulk_error_round_trip() {
+        assert_round_trip(BulkError::new("SYNTAX invalid\r\nsyntax").into());
+    }
+
+    #[test]
+    fn respv2_verbatim_string_round_trip() {
+        assert_round_trip(VerbatimString::new(*b"mkd", "# title\r\n").into());
+    }
+
+    #[test]
+    fn respv2_attribute_round_trip() {
+        let mut attributes = RespMap::new();
+        attributes.insert(b"ttl".to_vec(), RespFrame::Integer(3600));
+        let frame = RespArray::new([BulkString::from("a").into(), RespFrame::Double(0.5)]);
+        assert_round_trip(RespAttribute::new(attributes, frame).into());
+    }
+
+    #[test]
+    fn respv2_push_round_trip() {
+        assert_round_trip(
+            RespPush::new([
+                BulkString::from("invalidate").into(),
+                RespArray::new([BulkString::from("foo").into()]).into(),
+            ])
+            .into(),
+        );
+    }
+
+    #[test]
+    fn respv2_bulk_string_shares_the_read_buffer() {
+        let mut buf = BytesMut::from("*2\r\n$3\r\nset\r\n$6\r\nfoobar\r\n");
+        let payload = buf.as_ptr() as usize + buf.len() - 8;
+        let frame = RespFrame::decode(&mut buf).unwrap();
+        let RespFrame::Array(RespArray(Some(frames))) = frame else {
+            panic!("expected an array");
+        };
+        let RespFrame::BulkString(BulkString(Some(value))) = &frames[1] else {
+            panic!("expected a bulk string");
+        };
+        assert_eq!(value.as_ptr() as usize, payload);
+    }
+
+    // decode with either decoder, returning the result and how many bytes it consumed
+    fn decode_with(
+        decode: fn(&mut BytesMut) -> Result<RespFrame, RespError>,
+        input: &[u8],
+    ) -> (Result<RespFrame, RespError>, usize) {
+        let mut buf = BytesMut::from(input);
+        let ret = decode(&mut buf);
+        (ret, input.len() - buf.len())
+    }
+
+    fn leaf() -> impl Strategy<Value = RespFrame> {
+        let text = "[^\r\n]{0,8}";
+        let bytes = || vec(any::<u8>(), 0..16);
+        prop_oneof![
+            text.prop_map(|s| SimpleString::new(s).into()),
+            text.prop_map(|s| SimpleError::new(s).into()),
+            any::<i64>().prop_map(RespFrame::Integer),
+            bytes().prop_map(|v| BulkString::new(v).into()),
+            Just(BulkString(None).into()),
+            Just(RespArray(None).into()),
+            Just(RespNull.into()),
+            any::<bool>().prop_map(RespFrame::Boolean),
+            // NaN is never equal to itself
+            any::<f64>()
+                .prop_filter("nan", |d| !d.is_nan())
+                .prop_map(RespFrame::Double),
+            "[+-]?[0-9]{1,40}".prop_map(|s| BigNumber::new(s).unwrap().into()),
+            any::<String>().prop_map(|s| BulkError::new(s).into()),
+            ("[a-z]{3}", bytes()).prop_map(|(format, data)| {
+                let format = format.as_bytes().try_into().unwrap();
+                VerbatimString::new(format, data).into()
+            }),
+        ]
+    }
+
+    fn frame() -> impl Strategy<Value = RespFrame> {
+        leaf().prop_recursive(3, 32, 4, |inner| {
+            let map = || btree_map(vec(any::<u8>(), 0..8), inner.clone(), 0..4);
+            prop_oneof![
+                vec(inner.clone(), 0..4).prop_map(|v| RespArray::new(v).into()),
+                vec(inner.clone(), 0..4).prop_map(|v| RespSet(v).into()),
+                vec(inner.clone(), 0..4).prop_map(|v| RespPush::new(v).into()),
+                map().prop_map(|m| RespMap(m).into()),
+                (map(), inner.clone()).prop_map(|(m, f)| RespAttribute::new(RespMap(m), f).into()),
+            ]
+        })
+    }
+
+    #[test]
+    fn respv2_decoders_share_limits() {
+        let deep = "*1\r\n".repeat(1000);
+        let cases = [
+            ("*2147483647\r\n", RespError::MultibulkTooLarge(2147483647)),
+            ("$2147483647\r\n", RespError::BulkTooLarge(2147483647)),
+            (deep.as_str(), RespError::NestingTooDeep(128)),
+        ];
+        for (input, err) in cases {
+            let v1 = decode_with(<RespFrame as crate::RespDecode>::decode, input.as_bytes());
+            let v2 = decode_with(<RespFrame as RespDecodeV2>::decode, input.as_bytes());
+            let incremental = RespFrameDecoder::default().decode(&mut BytesMut::from(input));
+            assert_eq!(v1.0.as_ref().unwrap_err(), &err);
+            assert_eq!(v2.0.as_ref().unwrap_err(), &err);
+            assert_eq!((v1.1, v2.1), (0, 0));
+            assert_eq!(incremental, Err(err));
+        }
+    }
+
+    proptest! {
+        // malformed input is an error, never a panic
+        #[test]
+        fn respv2_decoders_never_panic(
+            frame in frame(),
+            edits in vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+            garbage in vec(any::<u8>(), 0..32),
+        ) {
+            let mut input = frame.encode();
+            for (i, b) in edits {
+                let i = i.index(input.len());
+                input[i] = b;
+            }
+            for input in [input, garbage] {
+                let _ = decode_with(<RespFrame as crate::RespDecode>::decode, &input);
+                let _ = decode_with(<RespFrame as RespDecodeV2>::decode, &input);
+                let mut buf = BytesMut::from(&input[..]);
+                let mut decoder = RespFrameDecoder::default();
+                while let Ok(Some(_)) = decoder.decode(&mut buf) {}
+            }
+        }
+
+        // both decoders must agree on every frame, complete or not
+        #[test]
+        fn respv2_matches_v1_decoder(frame in frame(), chunk in 1usize..16) {
+            let mut encoded = frame.clone().encode();
+            let len = encoded.len();
+            for end in 0..len {
+                let v1 = decode_with(<RespFrame as crate::RespDecode>::decode, &encoded[..end]);
+                let v2 = decode_with(<RespFrame as RespDecodeV2>::decode, &encoded[..end]);
+                prop_assert_eq!(&v1, &v2, "prefix of {} bytes", end);
+                prop_assert_eq!(v1.0, Err(RespError::NotComplete));
+            }
+
+            // a pipelined frame right behind must be left in the buffer
+            encoded.extend_from_slice(b"+OK\r\n");
+            let v1 = decode_with(<RespFrame as crate::RespDecode>::decode, &encoded);
+            let v2 = decode_with(<RespFrame as RespDecodeV2>::decode, &encoded);
+            prop_assert_eq!(&v1, &v2);
+            prop_assert_eq!(v1, (Ok(frame.clone()), len));
+            prop_assert_eq!(
+                <RespFrame as crate::RespDecode>::expect_length(&encoded),
+                <RespFrame as RespDecodeV2>::expect_length(&encoded)
+            );
+
+            // the incremental decoder gets the bytes a few at a time
+            let mut decoder = RespFrameDecoder::default();
+            let mut buf = BytesMut::new();
+            let mut frames = Vec::new();
+            for bytes in encoded.chunks(chunk) {
+                buf.extend_from_slice(bytes);
+                while let Some(frame) = decoder.decode(&mut buf).unwrap() {
+                    frames.push(frame);
+                }
+            }
+            prop_assert!(buf.is_empty());
+            prop_assert_eq!(frames, vec![frame, RespFrame::SimpleString("OK".into())]);
+        }
+    }
 }
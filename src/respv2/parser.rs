@@ -1,10 +1,12 @@
 use crate::{
-    BulkString, RespArray, RespError, RespFrame, RespMap, RespNull, SimpleError, SimpleString,
+    BigNumber, BulkError, BulkString, RespArray, RespAttribute, RespError, RespFrame, RespMap,
+    RespNull, RespPush, RespSet, SimpleError, SimpleString, VerbatimString,
 };
+use bytes::Bytes;
 use std::{collections::BTreeMap, num::NonZeroUsize};
 use winnow::{
     ascii::{digit1, float},
-    combinator::{alt, dispatch, fail, opt, preceded, terminated},
+    combinator::{alt, dispatch, fail, opt, terminated},
     error::{ContextError, ErrMode, Needed},
     token::{any, take, take_until},
     PResult, Parser,
@@ -40,25 +42,42 @@ fn parse_frame_len(input: &mut &[u8]) -> PResult<()> {
         b'#' => simple_parser,
         b',' => simple_parser,
         b'%' => map_len,
-        // b'~' => set,
+        b'~' => array_len,
+        b'(' => simple_parser,
+        b'!' => bulk_string_len,
+        b'=' => bulk_string_len,
+        b'|' => attribute_len,
+        b'>' => array_len,
         _v => fail::<_, _, _>
     }
     .parse_next(input)
 }
 
-pub fn parse_frame(input: &mut &[u8]) -> PResult<RespFrame> {
+// the input is a suffix of `src`, bulk strings are sliced out of it instead of copied
+pub fn parse_frame(input: &mut &[u8], src: &Bytes) -> PResult<RespFrame> {
     // frame type has been processed
     dispatch! {any;
         b'+' => simple_string.map(RespFrame::SimpleString),
         b'-' => error.map(RespFrame::Error),
         b':' => integer.map(RespFrame::Integer),
-        b'$' => alt((null_bulk_string.map(RespFrame::BulkString),bulk_string.map(RespFrame::BulkString))),
-        b'*' => alt((null_array.map(RespFrame::Array), array.map(RespFrame::Array))),
+        b'$' => alt((
+            null_bulk_string.map(RespFrame::BulkString),
+            |i: &mut &[u8]| bulk_string(i, src).map(RespFrame::BulkString),
+        )),
+        b'*' => alt((
+            null_array.map(RespFrame::Array),
+            |i: &mut &[u8]| array(i, src).map(RespFrame::Array),
+        )),
         b'_' => null.map(RespFrame::Null),
         b'#' => boolean.map(RespFrame::Boolean),
         b',' => double.map(RespFrame::Double),
-        b'%' => map.map(RespFrame::Map),
-        // b'~' => set,
+        b'%' => |i: &mut &[u8]| map(i, src).map(RespFrame::Map),
+        b'~' => |i: &mut &[u8]| set(i, src).map(RespFrame::Set),
+        b'(' => big_number.map(RespFrame::BigNumber),
+        b'!' => bulk_error.map(RespFrame::BulkError),
+        b'=' => verbatim_string.map(RespFrame::VerbatimString),
+        b'|' => |i: &mut &[u8]| attribute(i, src).map(RespFrame::Attribute),
+        b'>' => |i: &mut &[u8]| push(i, src).map(RespFrame::Push),
         _v => fail::<_, _, _>
     }
     .parse_next(input)
@@ -88,23 +107,18 @@ fn null_bulk_string(input: &mut &[u8]) -> PResult<BulkString> {
 }
 
 // - bulk string: "$6\r\nfoobar\r\n"
-#[allow(clippy::comparison_chain)]
-fn bulk_string(input: &mut &[u8]) -> PResult<BulkString> {
+fn bulk_string(input: &mut &[u8], src: &Bytes) -> PResult<BulkString> {
     let len: i64 = integer.parse_next(input)?;
-    if len == 0 {
-        return Ok(BulkString(Some(vec![])));
-    } else if len < 0 {
+    if len < 0 {
         return Err(err_cut("bulk string length must be non-negative"));
     }
-    let data = terminated(take(len as usize), CRLF)
-        .map(|s: &[u8]| s.to_vec())
-        .parse_next(input)?;
-    Ok(BulkString(Some(data)))
+    let data = terminated(take(len as usize), CRLF).parse_next(input)?;
+    Ok(BulkString(Some(src.slice_ref(data))))
 }
 
 fn bulk_string_len(input: &mut &[u8]) -> PResult<()> {
     let len: i64 = integer.parse_next(input)?;
-    if len == 0 || len == -1 {
+    if len == -1 {
         return Ok(());
     } else if len < -1 {
         return Err(err_cut("bulk string length must be non-negative"));
@@ -127,7 +141,7 @@ fn null_array(input: &mut &[u8]) -> PResult<RespArray> {
 
 // - array: "*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n"
 #[allow(clippy::comparison_chain)]
-fn array(input: &mut &[u8]) -> PResult<RespArray> {
+fn array(input: &mut &[u8], src: &Bytes) -> PResult<RespArray> {
     let len: i64 = integer.parse_next(input)?;
     if len == 0 {
         return Ok(RespArray(Some(vec![])));
@@ -136,7 +150,7 @@ fn array(input: &mut &[u8]) -> PResult<RespArray> {
     }
     let mut arr = Vec::with_capacity(len as usize);
     for _ in 0..len {
-        arr.push(parse_frame(input)?);
+        arr.push(parse_frame(input, src)?);
     }
     Ok(RespArray(Some(arr)))
 }
@@ -156,7 +170,7 @@ fn array_len(input: &mut &[u8]) -> PResult<()> {
 
 // - boolean: "#t\r\n"
 fn boolean(input: &mut &[u8]) -> PResult<bool> {
-    let b = alt(('t', 'f')).parse_next(input)?;
+    let b = terminated(alt(('t', 'f')), CRLF).parse_next(input)?;
     Ok(b == 't')
 }
 
@@ -165,17 +179,16 @@ fn double(input: &mut &[u8]) -> PResult<f64> {
     terminated(float, CRLF).parse_next(input)
 }
 
-// - map: "%2\r\n+foo\r\n-bar\r\n"
-fn map(input: &mut &[u8]) -> PResult<RespMap> {
+// - map: "%1\r\n+foo\r\n-bar\r\n"
+fn map(input: &mut &[u8], src: &Bytes) -> PResult<RespMap> {
     let len: i64 = integer.parse_next(input)?;
-    if len <= 0 {
+    if len < 0 {
         return Err(err_cut("map length must be non-negative"));
     }
-    let len = len as usize / 2;
     let mut map = BTreeMap::new();
     for _ in 0..len {
-        let key = preceded('+', parse_string).parse_next(input)?;
-        let value = parse_frame(input)?;
+        let key = map_key.parse_next(input)?;
+        let value = parse_frame(input, src)?;
         map.insert(key, value);
     }
     Ok(RespMap(map))
@@ -183,19 +196,118 @@ fn map(input: &mut &[u8]) -> PResult<RespMap> {
 
 fn map_len(input: &mut &[u8]) -> PResult<()> {
     let len: i64 = integer.parse_next(input)?;
-    if len <= 0 {
+    if len < 0 {
         return Err(err_cut("map length must be non-negative"));
     }
-    let len = len as usize / 2;
     for _ in 0..len {
-        terminated(take_until(0.., CRLF), CRLF)
-            .value(())
-            .parse_next(input)?;
+        map_key_len.parse_next(input)?;
         parse_frame_len(input)?;
     }
     Ok(())
 }
 
+// map and attribute keys are simple strings, or bulk strings when they are binary
+fn map_key(input: &mut &[u8]) -> PResult<Vec<u8>> {
+    dispatch! {any;
+        b'+' => terminated(take_until(0.., CRLF), CRLF).map(|s: &[u8]| s.to_vec()),
+        b'$' => bulk_data.map(|s: &[u8]| s.to_vec()),
+        _v => fail::<_, _, _>
+    }
+    .parse_next(input)
+}
+
+fn map_key_len(input: &mut &[u8]) -> PResult<()> {
+    dispatch! {any;
+        b'+' => terminated(take_until(0.., CRLF), CRLF).value(()),
+        b'$' => bulk_string_len,
+        _v => fail::<_, _, _>
+    }
+    .parse_next(input)
+}
+
+// - set: "~2\r\n+foo\r\n:1\r\n"
+fn set(input: &mut &[u8], src: &Bytes) -> PResult<RespSet> {
+    let len: i64 = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(err_cut("set length must be non-negative"));
+    }
+    let mut set = RespSet(Vec::with_capacity(len as usize));
+    for _ in 0..len {
+        set.push(parse_frame(input, src)?);
+    }
+    Ok(set)
+}
+
+// - big number: "(3492890328409238509324850943850943825024385\r\n"
+fn big_number(input: &mut &[u8]) -> PResult<BigNumber> {
+    parse_string.try_map(BigNumber::new).parse_next(input)
+}
+
+// - bulk error: "!21\r\nSYNTAX invalid syntax\r\n"
+fn bulk_error(input: &mut &[u8]) -> PResult<BulkError> {
+    let data = bulk_data.parse_next(input)?;
+    Ok(BulkError::new(String::from_utf8_lossy(data)))
+}
+
+// - verbatim string: "=15\r\ntxt:Some string\r\n"
+fn verbatim_string(input: &mut &[u8]) -> PResult<VerbatimString> {
+    let data = bulk_data.parse_next(input)?;
+    if data.len() < 4 || data[3] != b':' {
+        return Err(err_cut("verbatim string must start with a 3 bytes format"));
+    }
+    Ok(VerbatimString::new([data[0], data[1], data[2]], &data[4..]))
+}
+
+// the payload of a length prefixed frame
+fn bulk_data<'a>(input: &mut &'a [u8]) -> PResult<&'a [u8]> {
+    let len: i64 = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(err_cut("length must be non-negative"));
+    }
+    terminated(take(len as usize), CRLF).parse_next(input)
+}
+
+// - attribute: "|1\r\n+ttl\r\n:3600\r\n:1\r\n", followed by the reply it describes
+fn attribute(input: &mut &[u8], src: &Bytes) -> PResult<RespAttribute> {
+    let len: i64 = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(err_cut("attribute length must be non-negative"));
+    }
+    let mut attributes = RespMap::new();
+    for _ in 0..len {
+        let key = map_key.parse_next(input)?;
+        let value = parse_frame(input, src)?;
+        attributes.insert(key, value);
+    }
+    let frame = parse_frame(input, src)?;
+    Ok(RespAttribute::new(attributes, frame))
+}
+
+fn attribute_len(input: &mut &[u8]) -> PResult<()> {
+    let len: i64 = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(err_cut("attribute length must be non-negative"));
+    }
+    for _ in 0..len {
+        map_key_len.parse_next(input)?;
+        parse_frame_len(input)?;
+    }
+    parse_frame_len(input)
+}
+
+// - push: ">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n"
+fn push(input: &mut &[u8], src: &Bytes) -> PResult<RespPush> {
+    let len: i64 = integer.parse_next(input)?;
+    if len < 0 {
+        return Err(err_cut("push length must be non-negative"));
+    }
+    let mut frames = Vec::with_capacity(len as usize);
+    for _ in 0..len {
+        frames.push(parse_frame(input, src)?);
+    }
+    Ok(RespPush::new(frames))
+}
+
 // - null: "_\r\n"
 fn null(input: &mut &[u8]) -> PResult<RespNull> {
     CRLF.value(RespNull).parse_next(input)
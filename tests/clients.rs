@@ -0,0 +1,49 @@
+mod common;
+
+use std::io::Write;
+
+use anyhow::Result;
+use common::{read_line, read_reply, Server};
+
+#[test]
+fn test_maxclients_fits_the_open_files_limit() -> Result<()> {
+    let server = Server::start_with_nofile(40, &[])?;
+    let mut admin = server.connect()?;
+    // 40 fds leave room for 8 clients besides the ones the server keeps for itself
+    admin.write_all(b"CONFIG GET maxclients\r\n")?;
+    read_reply(&mut admin, b"*2\r\n$10\r\nmaxclients\r\n$1\r\n8\r\n")?;
+
+    // clients past the limit are turned away instead of taking the server down
+    let mut clients = Vec::new();
+    for _ in 0..60 {
+        let mut stream = server.connect()?;
+        stream.write_all(b"PING\r\n")?;
+        clients.push(stream);
+    }
+    let mut served = 0;
+    for stream in &mut clients {
+        match read_line(stream)?.as_str() {
+            "+PONG\r\n" => served += 1,
+            reply => assert_eq!(reply, "-ERR max number of clients reached\r\n"),
+        }
+    }
+    assert_eq!(served, 7);
+    admin.write_all(b"PING\r\n")?;
+    read_reply(&mut admin, b"+PONG\r\n")?;
+    Ok(())
+}
+
+#[test]
+fn test_config_set_maxclients_checks_the_open_files_limit() -> Result<()> {
+    let server = Server::start_with_nofile(40, &["--maxclients", "4"])?;
+    let mut admin = server.connect()?;
+    // the hard limit is 40 as well, so it can't be raised past 8 clients
+    admin.write_all(b"CONFIG SET maxclients 100\r\n")?;
+    read_reply(&mut admin, b"-ERR CONFIG SET failed (possibly related to argument 'maxclients') - The operating system is not able to handle the specified number of clients, try with 8\r\n")?;
+    admin.write_all(b"CONFIG GET maxclients\r\n")?;
+    read_reply(&mut admin, b"*2\r\n$10\r\nmaxclients\r\n$1\r\n4\r\n")?;
+
+    admin.write_all(b"CONFIG SET maxclients 8\r\n")?;
+    read_reply(&mut admin, b"+OK\r\n")?;
+    Ok(())
+}
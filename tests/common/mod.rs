@@ -0,0 +1,112 @@
+#![allow(dead_code)]
+
+use std::{
+    io::{self, Read},
+    net::{SocketAddr, TcpListener, TcpStream},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+
+// the server binary, started on free local ports and killed when dropped
+pub struct Server {
+    child: Child,
+    pub addr: SocketAddr,
+}
+
+impl Server {
+    pub fn start(args: &[&str]) -> Result<Self> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+        let child = Command::new(env!("CARGO_BIN_EXE_simple-redis"))
+            .args(["--bind", "127.0.0.1", "--port", &addr.port().to_string()])
+            .args(args)
+            .stdout(Stdio::null())
+            .spawn()?;
+        Ok(Self { child, addr })
+    }
+
+    // like start, with the open files limit lowered to `nofile` first
+    pub fn start_with_nofile(nofile: u32, args: &[&str]) -> Result<Self> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], free_port()?));
+        let script = format!("ulimit -n {} && exec \"$0\" \"$@\"", nofile);
+        let child = Command::new("sh")
+            .args(["-c", &script, env!("CARGO_BIN_EXE_simple-redis")])
+            .args(["--bind", "127.0.0.1", "--port", &addr.port().to_string()])
+            .args(args)
+            .stdout(Stdio::null())
+            .spawn()?;
+        Ok(Self { child, addr })
+    }
+
+    pub fn connect(&self) -> Result<TcpStream> {
+        let stream = retry(|| TcpStream::connect(self.addr))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        Ok(stream)
+    }
+
+    pub fn signal(&self, name: &str) -> Result<()> {
+        let status = Command::new("kill")
+            .args([&format!("-{}", name), &self.child.id().to_string()])
+            .status()?;
+        assert!(status.success());
+        Ok(())
+    }
+
+    pub fn wait(&mut self) -> Result<ExitStatus> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            if let Some(status) = self.child.try_wait()? {
+                return Ok(status);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        Err(anyhow!("server did not exit"))
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// the server takes a moment to start listening
+pub fn retry<T>(connect: impl Fn() -> io::Result<T>) -> Result<T> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match connect() {
+            Ok(stream) => return Ok(stream),
+            Err(e) if Instant::now() > deadline => return Err(e.into()),
+            Err(_) => thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+pub fn read_reply(stream: &mut impl Read, expected: &[u8]) -> Result<()> {
+    let mut buf = vec![0; expected.len()];
+    stream.read_exact(&mut buf)?;
+    assert_eq!(
+        String::from_utf8_lossy(&buf),
+        String::from_utf8_lossy(expected)
+    );
+    Ok(())
+}
+
+// read a reply up to its first line break
+pub fn read_line(stream: &mut impl Read) -> Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while !line.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+// a port nothing listens on right now
+pub fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
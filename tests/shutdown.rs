@@ -0,0 +1,91 @@
+mod common;
+
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::TcpStream,
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    time::Duration,
+};
+
+use anyhow::Result;
+use common::{read_reply, retry, Server};
+
+#[test]
+fn test_sigterm_exits_cleanly() -> Result<()> {
+    let mut server = Server::start(&[])?;
+    let mut stream = server.connect()?;
+    stream.write_all(b"PING\r\n")?;
+    read_reply(&mut stream, b"+PONG\r\n")?;
+
+    server.signal("TERM")?;
+    assert_eq!(server.wait()?.code(), Some(0));
+    // the open connection is closed rather than left hanging
+    assert_eq!(stream.read(&mut [0; 16])?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_shutdown_finishes_pipelined_commands() -> Result<()> {
+    let mut server = Server::start(&[])?;
+    let mut stream = server.connect()?;
+    stream.write_all(b"SET k v\r\nGET k\r\nSHUTDOWN NOSAVE\r\nGET k\r\n")?;
+    read_reply(&mut stream, b"+OK\r\n$1\r\nv\r\n")?;
+    // SHUTDOWN has no reply and nothing after it runs
+    assert_eq!(stream.read(&mut [0; 16])?, 0);
+    assert_eq!(server.wait()?.code(), Some(0));
+    assert!(TcpStream::connect(server.addr).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_shutdown_with_save_points() -> Result<()> {
+    let mut server = Server::start(&["--save", "60", "1"])?;
+    let mut stream = server.connect()?;
+    stream.write_all(b"SHUTDOWN ABORT\r\n")?;
+    read_reply(&mut stream, b"-ERR No shutdown in progress.\r\n")?;
+    // the dataset can't be saved, so a plain SHUTDOWN refuses and the server keeps running
+    stream.write_all(b"SHUTDOWN\r\n")?;
+    read_reply(
+        &mut stream,
+        b"-ERR Errors trying to SHUTDOWN. Check logs.\r\n",
+    )?;
+    stream.write_all(b"PING\r\n")?;
+    read_reply(&mut stream, b"+PONG\r\n")?;
+
+    stream.write_all(b"SHUTDOWN SAVE FORCE\r\n")?;
+    assert_eq!(stream.read(&mut [0; 16])?, 0);
+    assert_eq!(server.wait()?.code(), Some(1));
+    Ok(())
+}
+
+#[test]
+fn test_unix_socket_is_removed_on_shutdown() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("simple-redis-{}.sock", std::process::id()));
+    // a socket file left behind by a crash does not keep the server from starting
+    fs::write(&path, "")?;
+    let path_arg = path.to_str().unwrap();
+    let mut server = Server::start(&[
+        "--port",
+        "0",
+        "--unixsocket",
+        path_arg,
+        "--unixsocketperm",
+        "700",
+    ])?;
+    let mut stream = retry(|| match fs::metadata(&path)?.permissions().mode() & 0o777 {
+        0o700 => UnixStream::connect(&path),
+        _ => Err(io::ErrorKind::NotConnected.into()),
+    })?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.write_all(b"PING\r\n")?;
+    read_reply(&mut stream, b"+PONG\r\n")?;
+    // tcp is off
+    assert!(TcpStream::connect(server.addr).is_err());
+
+    stream.write_all(b"SHUTDOWN NOSAVE\r\n")?;
+    assert_eq!(stream.read(&mut [0; 16])?, 0);
+    assert_eq!(server.wait()?.code(), Some(0));
+    assert!(!path.exists());
+    Ok(())
+}
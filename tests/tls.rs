@@ -0,0 +1,237 @@
+mod common;
+
+use std::{
+    fs,
+    io::Write,
+    net::{SocketAddr, TcpStream},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use common::{free_port, read_line, read_reply, retry, Server};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa, KeyPair};
+use tokio_rustls::rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, StreamOwned,
+};
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+// a CA issuing certificates for localhost, generated for each test
+struct Ca {
+    cert: Certificate,
+    key: KeyPair,
+}
+
+impl Ca {
+    fn new() -> Result<Self> {
+        let mut params = CertificateParams::new(Vec::new())?;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate()?;
+        let cert = params.self_signed(&key)?;
+        Ok(Self { cert, key })
+    }
+
+    fn issue(&self) -> Result<(Certificate, KeyPair)> {
+        let key = KeyPair::generate()?;
+        let cert = CertificateParams::new(vec!["localhost".to_string()])?
+            .signed_by(&key, &self.cert, &self.key)?;
+        Ok((cert, key))
+    }
+}
+
+// the files certificates are written to, removed with the directory when dropped
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Result<Self> {
+        let dir =
+            std::env::temp_dir().join(format!("simple-redis-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir)?;
+        Ok(Self(dir))
+    }
+
+    fn write(&self, name: &str, pem: &str) -> Result<String> {
+        let path = self.0.join(name);
+        fs::write(&path, pem)?;
+        Ok(path.to_str().unwrap().to_string())
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// a client trusting `ca`, presenting `cert` if given
+fn connect(addr: SocketAddr, ca: &Ca, cert: Option<&(Certificate, KeyPair)>) -> Result<TlsStream> {
+    let mut roots = RootCertStore::empty();
+    roots.add(ca.cert.der().clone())?;
+    let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots);
+    let config = match cert {
+        Some((cert, key)) => builder.with_client_auth_cert(
+            vec![cert.der().clone()],
+            PrivateKeyDer::try_from(key.serialize_der()).map_err(anyhow::Error::msg)?,
+        )?,
+        None => builder.with_no_client_auth(),
+    };
+    let conn = ClientConnection::new(Arc::new(config), ServerName::try_from("localhost")?)?;
+    let stream = retry(|| TcpStream::connect(addr))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    Ok(StreamOwned::new(conn, stream))
+}
+
+fn ping(stream: &mut TlsStream) -> Result<()> {
+    stream.write_all(b"PING\r\n")?;
+    read_reply(stream, b"+PONG\r\n")
+}
+
+fn server_cert(stream: &TlsStream) -> CertificateDer<'static> {
+    stream.conn.peer_certificates().unwrap()[0]
+        .clone()
+        .into_owned()
+}
+
+#[test]
+fn test_tls_clients_must_present_a_certificate() -> Result<()> {
+    let dir = TempDir::new("tls-auth")?;
+    let ca = Ca::new()?;
+    let (cert, key) = ca.issue()?;
+    let tls_port = free_port()?;
+    let server = Server::start(&[
+        "--tls-port",
+        &tls_port.to_string(),
+        "--tls-cert-file",
+        &dir.write("redis.crt", &cert.pem())?,
+        "--tls-key-file",
+        &dir.write("redis.key", &key.serialize_pem())?,
+        "--tls-ca-cert-file",
+        &dir.write("ca.crt", &ca.cert.pem())?,
+    ])?;
+    let addr = SocketAddr::new(server.addr.ip(), tls_port);
+
+    let client = ca.issue()?;
+    let mut stream = connect(addr, &ca, Some(&client))?;
+    ping(&mut stream)?;
+    stream.write_all(b"SET k v\r\nGET k\r\n")?;
+    read_reply(&mut stream, b"+OK\r\n$1\r\nv\r\n")?;
+
+    // no certificate, or one from another CA, is refused
+    assert!(ping(&mut connect(addr, &ca, None)?).is_err());
+    let stranger = Ca::new()?.issue()?;
+    assert!(ping(&mut connect(addr, &ca, Some(&stranger))?).is_err());
+
+    // tcp clients are still served on the plain port
+    let mut plain = server.connect()?;
+    plain.write_all(b"GET k\r\n")?;
+    read_reply(&mut plain, b"$1\r\nv\r\n")?;
+    Ok(())
+}
+
+#[test]
+fn test_tls_certificates_reload_on_config_set() -> Result<()> {
+    let dir = TempDir::new("tls-reload")?;
+    let ca = Ca::new()?;
+    let (cert, key) = ca.issue()?;
+    let cert_file = dir.write("redis.crt", &cert.pem())?;
+    let key_file = dir.write("redis.key", &key.serialize_pem())?;
+    let tls_port = free_port()?;
+    let server = Server::start(&[
+        "--tls-port",
+        &tls_port.to_string(),
+        "--tls-cert-file",
+        &cert_file,
+        "--tls-key-file",
+        &key_file,
+        "--tls-auth-clients",
+        "no",
+    ])?;
+    let addr = SocketAddr::new(server.addr.ip(), tls_port);
+    let mut old = connect(addr, &ca, None)?;
+    ping(&mut old)?;
+    assert_eq!(server_cert(&old), *cert.der());
+
+    // the certificate is rotated in place, setting the same paths picks it up
+    let (new_cert, new_key) = ca.issue()?;
+    dir.write("redis.crt", &new_cert.pem())?;
+    dir.write("redis.key", &new_key.serialize_pem())?;
+    let mut admin = server.connect()?;
+    admin.write_all(
+        format!(
+            "CONFIG SET tls-cert-file {} tls-key-file {}\r\n",
+            cert_file, key_file
+        )
+        .as_bytes(),
+    )?;
+    read_reply(&mut admin, b"+OK\r\n")?;
+    let mut new = connect(addr, &ca, None)?;
+    ping(&mut new)?;
+    assert_eq!(server_cert(&new), *new_cert.der());
+    // connections already open are not affected
+    ping(&mut old)?;
+
+    // a key that can't be loaded leaves the running certificates alone
+    admin.write_all(b"CONFIG SET tls-key-file /nonexistent/redis.key\r\n")?;
+    let reply = read_line(&mut admin)?;
+    assert!(
+        reply.starts_with("-ERR CONFIG SET failed (possibly related to argument 'tls-key-file') - Unable to update TLS configuration: /nonexistent/redis.key"),
+        "{}",
+        reply
+    );
+    admin.write_all(b"CONFIG GET tls-key-file\r\n")?;
+    // the array and bulk headers and the name come before the value
+    for _ in 0..4 {
+        read_line(&mut admin)?;
+    }
+    assert_eq!(read_line(&mut admin)?, format!("{}\r\n", key_file));
+    let mut stream = connect(addr, &ca, None)?;
+    ping(&mut stream)?;
+    assert_eq!(server_cert(&stream), *new_cert.der());
+    Ok(())
+}
+
+#[test]
+fn test_tls_handshakes_count_against_maxclients() -> Result<()> {
+    let dir = TempDir::new("tls-maxclients")?;
+    let ca = Ca::new()?;
+    let (cert, key) = ca.issue()?;
+    let tls_port = free_port()?;
+    let server = Server::start(&[
+        "--maxclients",
+        "1",
+        "--tls-port",
+        &tls_port.to_string(),
+        "--tls-cert-file",
+        &dir.write("redis.crt", &cert.pem())?,
+        "--tls-key-file",
+        &dir.write("redis.key", &key.serialize_pem())?,
+        "--tls-auth-clients",
+        "no",
+    ])?;
+    let addr = SocketAddr::new(server.addr.ip(), tls_port);
+
+    // a client that never starts its handshake still holds a slot
+    let stalled = retry(|| TcpStream::connect(addr))?;
+    let ping = || -> Result<String> {
+        let mut stream = server.connect()?;
+        stream.write_all(b"PING\r\n")?;
+        read_line(&mut stream)
+    };
+    retry(|| match ping() {
+        Ok(reply) if reply == "-ERR max number of clients reached\r\n" => Ok(()),
+        _ => Err(std::io::ErrorKind::WouldBlock.into()),
+    })?;
+
+    drop(stalled);
+    retry(|| match ping() {
+        Ok(reply) if reply == "+PONG\r\n" => Ok(()),
+        _ => Err(std::io::ErrorKind::WouldBlock.into()),
+    })?;
+    Ok(())
+}